@@ -1,10 +1,7 @@
 mod simulator;
-use simulator::{Color, OriginPosition};
+use simulator::{Color, ObjectHandle, OriginPosition};
 use simulator::objects::{Object, Cube, Spot};
 
-extern crate rand;
-use rand::Rng;
-
 fn main() -> Result<(), String> {
     if cfg!(feature="r3d") {
         test_3d()
@@ -12,6 +9,10 @@ fn main() -> Result<(), String> {
     else if cfg!(feature="r2d") {
         test_2d()
     }
+    else if cfg!(feature="simd_bench") {
+        simulator::simd::benchmark();
+        Ok(())
+    }
     else {
         Err(String::from("Neither 3D or 2D was provided as a compiler feature"))
     }
@@ -19,8 +20,6 @@ fn main() -> Result<(), String> {
 
 
 fn test_3d() -> Result<(), String> {
-    let mut rng = rand::thread_rng();
-
     let window = simulator::WindowBuilder::new()
         .set_size(600, 800)
         .set_background_color(Color::GREY)
@@ -29,15 +28,15 @@ fn test_3d() -> Result<(), String> {
 
     let mut sim = simulator::SimulationBuilder::new()
         .use_3d()
-        .use_object_clearing()
+        .use_dirty_rectangles()
         .build(window);
     
     sim.paint_background();
 
-    let mut cube_ids: Vec<usize> = Vec::new();
+    let mut cubes: Vec<ObjectHandle> = Vec::new();
 
     for _ in 0..50 {
-        cube_ids.push(
+        cubes.push(
             Cube::new()
                 .set_position(
                     0.0,
@@ -49,21 +48,16 @@ fn test_3d() -> Result<(), String> {
     }
     while sim.update().is_ok() {
         let delta = sim.time.get_delta_time();
-        for id in cube_ids.iter() {
-            let scaler: f32 = rng.gen();
-            sim.clear_from_screen(id);
-            let cube = sim.get_object_by_id(id).unwrap();
-            cube.transform_mut().rotation.x += scaler * 200.0 * delta;
-            cube.transform_mut().rotation.y += scaler * 500.0 * delta;
-            cube.transform_mut().rotation.z += scaler * 200.0 * delta;
+        for handle in cubes.iter() {
+            let scaler = sim.get_random_mut().next_f32();
+            let cube = sim.get_object_by_id(handle).unwrap();
+            cube.transform_mut().rotate(scaler * 200.0 * delta, scaler * 500.0 * delta, scaler * 200.0 * delta);
         }
     }
     Ok(())
 }
 
 fn test_2d() -> Result<(), String> {
-    let mut rng = rand::thread_rng();
-
     let window = simulator::WindowBuilder::new()
         .set_size(600, 800)
         .set_background_color(Color::GREY)
@@ -80,8 +74,10 @@ fn test_2d() -> Result<(), String> {
     const NUMBER_OF_SPOTS: u32 = 100_000;
 
     for _ in 0..NUMBER_OF_SPOTS {
+        let x = sim.get_random_mut().range_f32(0.0, 600.0);
+        let y = sim.get_random_mut().range_f32(0.0, 800.0);
         let obj = Spot::new(Color::RED)
-            .set_position(rng.gen::<f32>() * 600.0, rng.gen::<f32>() * 800.0);
+            .set_position(x, y);
         obj.register(&mut sim);
     }
 
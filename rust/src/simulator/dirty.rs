@@ -0,0 +1,226 @@
+// screen space rectangles used to track which parts of the frame
+// need redrawing and presenting
+
+// past this many separate regions they are merged into one as the
+// per region overhead outweighs the pixels saved
+const MAX_DIRTY_REGIONS: usize = 32;
+
+//#region Rect
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32
+}
+
+impl Rect {
+    pub fn new(x: i32, y: i32, width: i32, height: i32) -> Self {
+        Self { x, y, width, height }
+    }
+
+    // from inclusive minimum and maximum pixel coordinates, saturating
+    // rather than overflowing for bounds spanning the whole i32 range
+    pub fn from_bounds(min_x: i32, min_y: i32, max_x: i32, max_y: i32) -> Self {
+        Self {
+            x: min_x,
+            y: min_y,
+            width: max_x.saturating_sub(min_x).saturating_add(1),
+            height: max_y.saturating_sub(min_y).saturating_add(1)
+        }
+    }
+
+    pub fn get_right(&self) -> i32 {
+        self.x.saturating_add(self.width)
+    }
+
+    pub fn get_bottom(&self) -> i32 {
+        self.y.saturating_add(self.height)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.width <= 0 || self.height <= 0
+    }
+
+    pub fn area(&self) -> i64 {
+        if self.is_empty() {
+            0
+        }
+        else {
+            self.width as i64 * self.height as i64
+        }
+    }
+
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        x >= self.x && x < self.get_right() && y >= self.y && y < self.get_bottom()
+    }
+
+    pub fn intersects(&self, other: &Rect) -> bool {
+        !self.is_empty() && !other.is_empty()
+            && self.x < other.get_right() && other.x < self.get_right()
+            && self.y < other.get_bottom() && other.y < self.get_bottom()
+    }
+
+    pub fn intersection(&self, other: &Rect) -> Option<Rect> {
+        if !self.intersects(other) {
+            return None;
+        }
+        let x = self.x.max(other.x);
+        let y = self.y.max(other.y);
+        Some(Rect::new(
+            x,
+            y,
+            self.get_right().min(other.get_right()) - x,
+            self.get_bottom().min(other.get_bottom()) - y
+        ))
+    }
+
+    // smallest rectangle containing both
+    pub fn union(&self, other: &Rect) -> Rect {
+        if self.is_empty() {
+            return *other;
+        }
+        if other.is_empty() {
+            return *self;
+        }
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        Rect::new(
+            x,
+            y,
+            self.get_right().max(other.get_right()) - x,
+            self.get_bottom().max(other.get_bottom()) - y
+        )
+    }
+
+    // restricts the rectangle to a screen of the given size
+    pub fn clip(&self, width: i32, height: i32) -> Option<Rect> {
+        self.intersection(&Rect::new(0, 0, width, height))
+    }
+}
+//#endregion
+
+//#region DirtyRegions
+// a set of non overlapping rectangles, overlapping additions are merged
+#[derive(Clone, Debug, Default)]
+pub struct DirtyRegions {
+    regions: Vec<Rect>
+}
+
+impl DirtyRegions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get_regions(&self) -> &[Rect] {
+        &self.regions
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.regions.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.regions.clear();
+    }
+
+    pub fn add(&mut self, rect: Rect) {
+        if rect.is_empty() {
+            return;
+        }
+        let mut merged = rect;
+        // keep absorbing regions until the merged rectangle no longer overlaps any
+        loop {
+            let overlapping = self.regions.iter().position(|region| region.intersects(&merged));
+            match overlapping {
+                Some(index) => {
+                    merged = merged.union(&self.regions.swap_remove(index));
+                },
+                None => break
+            }
+        }
+        self.regions.push(merged);
+        if self.regions.len() > MAX_DIRTY_REGIONS {
+            let bounds = self.regions.iter().fold(Rect::new(0, 0, 0, 0), |bounds, region| bounds.union(region));
+            self.regions.clear();
+            self.regions.push(bounds);
+        }
+    }
+
+    pub fn intersects(&self, rect: &Rect) -> bool {
+        self.regions.iter().any(|region| region.intersects(rect))
+    }
+
+    // drops anything outside of a screen of the given size
+    pub fn clip(&mut self, width: i32, height: i32) {
+        self.regions = self.regions
+            .iter()
+            .filter_map(|region| region.clip(width, height))
+            .collect();
+    }
+}
+//#endregion
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_bounds_is_inclusive_and_saturates() {
+        assert_eq!(Rect::from_bounds(2, 3, 4, 3), Rect::new(2, 3, 3, 1));
+        let huge = Rect::from_bounds(i32::MIN, 10, i32::MAX, 10);
+        assert_eq!(huge.width, i32::MAX);
+        let edge = Rect::from_bounds(10, 10, i32::MAX, i32::MAX);
+        assert_eq!(edge.get_right(), i32::MAX);
+        assert_eq!(edge.clip(100, 50), Some(Rect::new(10, 10, 90, 40)));
+    }
+
+    #[test]
+    fn intersects_and_unions() {
+        let a = Rect::new(0, 0, 10, 10);
+        let b = Rect::new(5, 5, 10, 10);
+        assert_eq!(a.intersection(&b), Some(Rect::new(5, 5, 5, 5)));
+        assert_eq!(a.union(&b), Rect::new(0, 0, 15, 15));
+        // touching edges don't overlap
+        assert!(!a.intersects(&Rect::new(10, 0, 5, 5)));
+        assert_eq!(a.union(&Rect::new(50, 50, 0, 0)), a);
+        assert_eq!(Rect::new(-5, -5, 10, 10).clip(3, 3), Some(Rect::new(0, 0, 3, 3)));
+        assert_eq!(Rect::new(5, 5, 10, 10).clip(3, 3), None);
+    }
+
+    #[test]
+    fn merges_overlapping_regions() {
+        let mut regions = DirtyRegions::new();
+        regions.add(Rect::new(0, 0, 10, 10));
+        regions.add(Rect::new(20, 0, 10, 10));
+        assert_eq!(regions.get_regions().len(), 2);
+        // bridging the two pulls both into a single region
+        regions.add(Rect::new(5, 0, 20, 5));
+        assert_eq!(regions.get_regions(), &[Rect::new(0, 0, 30, 10)]);
+        regions.add(Rect::new(100, 100, 0, 5));
+        assert_eq!(regions.get_regions().len(), 1);
+        assert!(regions.intersects(&Rect::new(29, 9, 1, 1)));
+        regions.clear();
+        assert!(regions.is_empty());
+    }
+
+    #[test]
+    fn collapses_past_the_region_limit() {
+        let mut regions = DirtyRegions::new();
+        for i in 0..MAX_DIRTY_REGIONS as i32 {
+            regions.add(Rect::new(i * 10, 0, 5, 5));
+        }
+        assert_eq!(regions.get_regions().len(), MAX_DIRTY_REGIONS);
+        regions.add(Rect::new(0, 100, 5, 5));
+        assert_eq!(regions.get_regions(), &[Rect::new(0, 0, MAX_DIRTY_REGIONS as i32 * 10 - 5, 105)]);
+    }
+
+    #[test]
+    fn clips_to_the_screen() {
+        let mut regions = DirtyRegions::new();
+        regions.add(Rect::new(-10, -10, 20, 20));
+        regions.add(Rect::new(200, 200, 10, 10));
+        regions.clip(100, 100);
+        assert_eq!(regions.get_regions(), &[Rect::new(0, 0, 10, 10)]);
+    }
+}
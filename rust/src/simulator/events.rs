@@ -0,0 +1,46 @@
+// events delivered once per frame by the simulator, either polled or passed
+// to subscribed callbacks
+
+use super::input::{InputEvent, Key, MouseButton};
+use super::handle::ObjectHandle;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Event {
+    // the drawing area changed size
+    Resized {
+        width: i32,
+        height: i32
+    },
+    // the user asked to close the window
+    CloseRequested,
+    // the window has been destroyed, sent once before update starts failing
+    Closed,
+    FocusGained,
+    FocusLost,
+    KeyPressed(Key),
+    KeyReleased(Key),
+    MousePressed(MouseButton),
+    MouseReleased(MouseButton),
+    MouseMoved {
+        x: i32,
+        y: i32
+    },
+    MouseWheel(f32),
+    ObjectAdded(ObjectHandle),
+    ObjectRemoved(ObjectHandle),
+    // every frame of a replayed session has been played
+    ReplayFinished
+}
+
+impl From<InputEvent> for Event {
+    fn from(event: InputEvent) -> Self {
+        match event {
+            InputEvent::KeyDown(key) => Event::KeyPressed(key),
+            InputEvent::KeyUp(key) => Event::KeyReleased(key),
+            InputEvent::MouseDown(button) => Event::MousePressed(button),
+            InputEvent::MouseUp(button) => Event::MouseReleased(button),
+            InputEvent::MouseMoved { x, y } => Event::MouseMoved { x, y },
+            InputEvent::MouseWheel(delta) => Event::MouseWheel(delta)
+        }
+    }
+}
@@ -0,0 +1,132 @@
+// handles to registered objects, each simulator hands out its own
+//
+// a handle is a slot index plus the generation of that slot, removing an
+// object bumps the generation so older handles to the slot stop matching
+// anything even once the index is reused
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashSet};
+use std::convert::TryFrom;
+use std::fmt;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ObjectHandle {
+    index: u32,
+    generation: u32
+}
+
+impl ObjectHandle {
+    pub fn get_index(&self) -> u32 {
+        self.index
+    }
+
+    pub fn get_generation(&self) -> u32 {
+        self.generation
+    }
+}
+
+impl fmt::Display for ObjectHandle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}v{}", self.index, self.generation)
+    }
+}
+
+pub struct HandleAllocator {
+    // current generation of every slot
+    generations: Vec<u32>,
+    // slots whose handle has been released, the lowest is reused first so
+    // handles given out together keep their order however slots were freed
+    free: BinaryHeap<Reverse<u32>>,
+    // slots that went through every generation, their generation has wrapped
+    // back to that of their first handle so they can never match again
+    retired: HashSet<u32>
+}
+
+impl HandleAllocator {
+    pub fn new() -> Self {
+        Self {
+            generations: Vec::new(),
+            free: BinaryHeap::new(),
+            retired: HashSet::new()
+        }
+    }
+
+    pub fn allocate(&mut self) -> ObjectHandle {
+        match self.free.pop() {
+            Some(Reverse(index)) => ObjectHandle { index, generation: self.generations[index as usize] },
+            None => {
+                let index = u32::try_from(self.generations.len()).expect("Ran out of object handles");
+                self.generations.push(0);
+                ObjectHandle { index, generation: 0 }
+            }
+        }
+    }
+
+    // released slots have already moved on to the generation their next
+    // handle will get, so only handles given out and not released match
+    pub fn is_valid(&self, handle: ObjectHandle) -> bool {
+        self.generations.get(handle.index as usize) == Some(&handle.generation)
+            && !self.retired.contains(&handle.index)
+    }
+
+    // returns false if the handle was already released
+    pub fn release(&mut self, handle: ObjectHandle) -> bool {
+        if !self.is_valid(handle) {
+            return false;
+        }
+        let generation = &mut self.generations[handle.index as usize];
+        *generation = generation.wrapping_add(1);
+        // a slot that has gone through every generation is retired rather
+        // than letting its first handles become valid again
+        if *generation == 0 {
+            self.retired.insert(handle.index);
+        }
+        else {
+            self.free.push(Reverse(handle.index));
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reuses_the_lowest_released_slot() {
+        let mut handles = HandleAllocator::new();
+        let first = handles.allocate();
+        let second = handles.allocate();
+        let third = handles.allocate();
+        assert!(handles.release(third));
+        assert!(handles.release(first));
+        assert!(!handles.release(first));
+
+        let reused = handles.allocate();
+        assert_eq!(reused.get_index(), first.get_index());
+        assert_eq!(reused.get_generation(), first.get_generation() + 1);
+        assert_eq!(handles.allocate().get_index(), third.get_index());
+        assert_eq!(handles.allocate().get_index(), 3);
+
+        assert!(handles.is_valid(second));
+        assert!(handles.is_valid(reused));
+        assert!(!handles.is_valid(first));
+        assert!(!handles.is_valid(third));
+    }
+
+    #[test]
+    fn retires_slots_when_the_generation_wraps() {
+        let mut handles = HandleAllocator::new();
+        let first = handles.allocate();
+        handles.generations[0] = u32::MAX;
+        let last = ObjectHandle { index: 0, generation: u32::MAX };
+        assert!(handles.is_valid(last));
+        assert!(handles.release(last));
+
+        // the slot is not handed out again and its first handle stays invalid
+        assert!(!handles.is_valid(first));
+        assert!(!handles.is_valid(last));
+        assert!(!handles.release(first));
+        assert_eq!(handles.allocate().get_index(), 1);
+    }
+}
@@ -0,0 +1,282 @@
+// platform neutral keyboard and mouse state, windows translate their own
+// messages into input events which are then applied once per frame, held
+// keys are kept ordered so anything iterating them is deterministic
+
+use std::collections::BTreeSet;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Key {
+    A, B, C, D, E, F, G, H, I, J, K, L, M,
+    N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
+    Num0, Num1, Num2, Num3, Num4, Num5, Num6, Num7, Num8, Num9,
+    F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12,
+    Up,
+    Down,
+    Left,
+    Right,
+    Space,
+    Enter,
+    Escape,
+    Tab,
+    Backspace,
+    Delete,
+    Insert,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Shift,
+    Control,
+    Alt,
+    // a platform specific key code without a named variant
+    Other(u32)
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+    Other(u8)
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum InputEvent {
+    KeyDown(Key),
+    KeyUp(Key),
+    MouseDown(MouseButton),
+    MouseUp(MouseButton),
+    // position in pixels relative to the top left of the drawing area, the
+    // simulator converts it to the frame when drawing at an internal resolution
+    MouseMoved {
+        x: i32,
+        y: i32
+    },
+    // in notches, positive away from the user
+    MouseWheel(f32)
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Input {
+    keys_down: BTreeSet<Key>,
+    keys_pressed: BTreeSet<Key>,
+    keys_released: BTreeSet<Key>,
+    buttons_down: BTreeSet<MouseButton>,
+    buttons_pressed: BTreeSet<MouseButton>,
+    buttons_released: BTreeSet<MouseButton>,
+    // none until the first move, which only sets the position
+    mouse_position: Option<(i32, i32)>,
+    mouse_delta: (i32, i32),
+    wheel_delta: f32
+}
+
+impl Input {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // clears everything that only lasts a single frame
+    pub fn begin_frame(&mut self) {
+        self.keys_pressed.clear();
+        self.keys_released.clear();
+        self.buttons_pressed.clear();
+        self.buttons_released.clear();
+        self.mouse_delta = (0, 0);
+        self.wheel_delta = 0.0;
+    }
+
+    // returns whether the event changed the state, repeated key downs and
+    // ups for keys that aren't held do not
+    pub fn handle_event(&mut self, event: &InputEvent) -> bool {
+        match *event {
+            InputEvent::KeyDown(key) => {
+                // held keys repeat their down events, only the first counts as a press
+                let pressed = self.keys_down.insert(key);
+                if pressed {
+                    self.keys_pressed.insert(key);
+                }
+                pressed
+            },
+            InputEvent::KeyUp(key) => {
+                let released = self.keys_down.remove(&key);
+                if released {
+                    self.keys_released.insert(key);
+                }
+                released
+            },
+            InputEvent::MouseDown(button) => {
+                let pressed = self.buttons_down.insert(button);
+                if pressed {
+                    self.buttons_pressed.insert(button);
+                }
+                pressed
+            },
+            InputEvent::MouseUp(button) => {
+                let released = self.buttons_down.remove(&button);
+                if released {
+                    self.buttons_released.insert(button);
+                }
+                released
+            },
+            InputEvent::MouseMoved { x, y } => {
+                let previous = self.mouse_position.replace((x, y));
+                match previous {
+                    Some((previous_x, previous_y)) => {
+                        self.mouse_delta.0 += x - previous_x;
+                        self.mouse_delta.1 += y - previous_y;
+                        (x, y) != (previous_x, previous_y)
+                    },
+                    None => true
+                }
+            },
+            InputEvent::MouseWheel(delta) => {
+                self.wheel_delta += delta;
+                delta != 0.0
+            }
+        }
+    }
+
+    // releases everything that is held, used when the window loses focus
+    // and will not receive the matching up events
+    pub fn release_all(&mut self) -> Vec<InputEvent> {
+        let keys = std::mem::take(&mut self.keys_down).into_iter().map(InputEvent::KeyUp);
+        let buttons = std::mem::take(&mut self.buttons_down).into_iter().map(InputEvent::MouseUp);
+        let released: Vec<InputEvent> = keys.chain(buttons).collect();
+        for event in released.iter() {
+            match *event {
+                InputEvent::KeyUp(key) => { self.keys_released.insert(key); },
+                InputEvent::MouseUp(button) => { self.buttons_released.insert(button); },
+                _ => {}
+            }
+        }
+        released
+    }
+
+    pub fn is_key_down(&self, key: Key) -> bool {
+        self.keys_down.contains(&key)
+    }
+
+    // true only on the frame the key went down
+    pub fn was_key_pressed(&self, key: Key) -> bool {
+        self.keys_pressed.contains(&key)
+    }
+
+    // true only on the frame the key came up
+    pub fn was_key_released(&self, key: Key) -> bool {
+        self.keys_released.contains(&key)
+    }
+
+    pub fn get_keys_down(&self) -> impl Iterator<Item = &Key> {
+        self.keys_down.iter()
+    }
+
+    pub fn is_mouse_down(&self, button: MouseButton) -> bool {
+        self.buttons_down.contains(&button)
+    }
+
+    pub fn was_mouse_pressed(&self, button: MouseButton) -> bool {
+        self.buttons_pressed.contains(&button)
+    }
+
+    pub fn was_mouse_released(&self, button: MouseButton) -> bool {
+        self.buttons_released.contains(&button)
+    }
+
+    // (0, 0) until the mouse first moves
+    pub fn get_mouse_position(&self) -> (i32, i32) {
+        self.mouse_position.unwrap_or((0, 0))
+    }
+
+    // how far the mouse moved this frame
+    pub fn get_mouse_delta(&self) -> (i32, i32) {
+        self.mouse_delta
+    }
+
+    // notches scrolled this frame
+    pub fn get_wheel_delta(&self) -> f32 {
+        self.wheel_delta
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tracks_pressed_released_and_held_keys() {
+        let mut input = Input::new();
+        assert!(input.handle_event(&InputEvent::KeyDown(Key::A)));
+        // repeats while held aren't presses
+        assert!(!input.handle_event(&InputEvent::KeyDown(Key::A)));
+        assert!(input.is_key_down(Key::A) && input.was_key_pressed(Key::A));
+
+        input.begin_frame();
+        assert!(input.is_key_down(Key::A) && !input.was_key_pressed(Key::A));
+        assert!(input.handle_event(&InputEvent::KeyUp(Key::A)));
+        assert!(!input.handle_event(&InputEvent::KeyUp(Key::B)));
+        assert!(!input.is_key_down(Key::A) && input.was_key_released(Key::A));
+        assert!(!input.was_key_released(Key::B));
+
+        input.begin_frame();
+        assert!(!input.was_key_released(Key::A));
+        assert_eq!(input.get_keys_down().count(), 0);
+    }
+
+    #[test]
+    fn tracks_mouse_buttons() {
+        let mut input = Input::new();
+        assert!(input.handle_event(&InputEvent::MouseDown(MouseButton::Left)));
+        assert!(!input.handle_event(&InputEvent::MouseDown(MouseButton::Left)));
+        assert!(input.is_mouse_down(MouseButton::Left) && input.was_mouse_pressed(MouseButton::Left));
+        input.begin_frame();
+        assert!(input.handle_event(&InputEvent::MouseUp(MouseButton::Left)));
+        assert!(!input.is_mouse_down(MouseButton::Left) && input.was_mouse_released(MouseButton::Left));
+        assert!(!input.was_mouse_pressed(MouseButton::Left));
+    }
+
+    #[test]
+    fn releases_everything_held() {
+        let mut input = Input::new();
+        input.handle_event(&InputEvent::KeyDown(Key::Shift));
+        input.handle_event(&InputEvent::KeyDown(Key::Other(7)));
+        input.handle_event(&InputEvent::MouseDown(MouseButton::Other(4)));
+        input.begin_frame();
+        assert_eq!(input.release_all(), vec![
+            InputEvent::KeyUp(Key::Shift),
+            InputEvent::KeyUp(Key::Other(7)),
+            InputEvent::MouseUp(MouseButton::Other(4))
+        ]);
+        assert_eq!(input.get_keys_down().count(), 0);
+        assert!(input.was_key_released(Key::Shift) && input.was_mouse_released(MouseButton::Other(4)));
+        assert!(input.release_all().is_empty());
+    }
+
+    #[test]
+    fn measures_mouse_movement_from_the_first_position() {
+        let mut input = Input::new();
+        assert_eq!(input.get_mouse_position(), (0, 0));
+        // the first move only places the mouse
+        assert!(input.handle_event(&InputEvent::MouseMoved { x: 300, y: 200 }));
+        assert_eq!(input.get_mouse_delta(), (0, 0));
+        assert_eq!(input.get_mouse_position(), (300, 200));
+
+        input.handle_event(&InputEvent::MouseMoved { x: 310, y: 195 });
+        assert!(!input.handle_event(&InputEvent::MouseMoved { x: 310, y: 195 }));
+        input.handle_event(&InputEvent::MouseMoved { x: 305, y: 190 });
+        assert_eq!(input.get_mouse_delta(), (5, -10));
+        input.begin_frame();
+        assert_eq!(input.get_mouse_delta(), (0, 0));
+        assert_eq!(input.get_mouse_position(), (305, 190));
+    }
+
+    #[test]
+    fn sums_the_wheel_each_frame() {
+        let mut input = Input::new();
+        assert!(input.handle_event(&InputEvent::MouseWheel(1.0)));
+        assert!(input.handle_event(&InputEvent::MouseWheel(-0.5)));
+        assert!(!input.handle_event(&InputEvent::MouseWheel(0.0)));
+        assert_eq!(input.get_wheel_delta(), 0.5);
+        input.begin_frame();
+        assert_eq!(input.get_wheel_delta(), 0.0);
+    }
+}
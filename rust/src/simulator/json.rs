@@ -0,0 +1,491 @@
+// a minimal json reader and writer for the scene files
+//
+// numbers keep the text they were read from so an f32 written out reads back
+// as exactly the same value
+
+use std::fmt;
+
+// arrays and objects nested deeper than this are rejected rather than
+// parsed, as each level of nesting is a level of recursion
+pub const MAX_DEPTH: usize = 128;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(String),
+    String(String),
+    Array(Vec<Json>),
+    // keys are kept in the order they were written
+    Object(Vec<(String, Json)>)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct JsonError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at line {}, column {}", self.message, self.line, self.column)
+    }
+}
+
+impl std::error::Error for JsonError {}
+
+impl From<f32> for Json {
+    fn from(value: f32) -> Self {
+        if value.is_finite() {
+            Json::Number(value.to_string())
+        }
+        else {
+            Json::Null
+        }
+    }
+}
+
+impl From<usize> for Json {
+    fn from(value: usize) -> Self {
+        Json::Number(value.to_string())
+    }
+}
+
+impl From<u32> for Json {
+    fn from(value: u32) -> Self {
+        Json::Number(value.to_string())
+    }
+}
+
+impl From<&str> for Json {
+    fn from(value: &str) -> Self {
+        Json::String(String::from(value))
+    }
+}
+
+impl Json {
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(entries) => entries.iter().find(|(name, _)| name == key).map(|(_, value)| value),
+            _ => None
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(text) => Some(text),
+            _ => None
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Json::Array(values) => Some(values),
+            _ => None
+        }
+    }
+
+    // null is read as nan as that is how non finite numbers are written
+    pub fn as_f32(&self) -> Option<f32> {
+        match self {
+            Json::Number(text) => text.parse().ok(),
+            Json::Null => Some(f32::NAN),
+            _ => None
+        }
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            Json::Number(text) => text.parse().ok(),
+            _ => None
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Json::Bool(value) => Some(*value),
+            _ => None
+        }
+    }
+
+    // indented with four spaces, short arrays of numbers stay on one line
+    pub fn to_pretty_string(&self) -> String {
+        let mut output = String::new();
+        self.write(&mut output, 0);
+        output.push('\n');
+        output
+    }
+
+    fn write(&self, output: &mut String, depth: usize) {
+        let indent = |output: &mut String, depth: usize| {
+            for _ in 0..depth {
+                output.push_str("    ");
+            }
+        };
+        match self {
+            Json::Null => output.push_str("null"),
+            Json::Bool(value) => output.push_str(if *value { "true" } else { "false" }),
+            Json::Number(text) => output.push_str(text),
+            Json::String(text) => write_string(output, text),
+            Json::Array(values) => {
+                let inline = values.len() <= 4 && values.iter().all(|value| matches!(value, Json::Number(_) | Json::Null));
+                if values.is_empty() || inline {
+                    output.push('[');
+                    for (index, value) in values.iter().enumerate() {
+                        if index > 0 {
+                            output.push_str(", ");
+                        }
+                        value.write(output, depth);
+                    }
+                    output.push(']');
+                    return;
+                }
+                output.push_str("[\n");
+                for (index, value) in values.iter().enumerate() {
+                    indent(output, depth + 1);
+                    value.write(output, depth + 1);
+                    if index + 1 < values.len() {
+                        output.push(',');
+                    }
+                    output.push('\n');
+                }
+                indent(output, depth);
+                output.push(']');
+            },
+            Json::Object(entries) => {
+                if entries.is_empty() {
+                    output.push_str("{}");
+                    return;
+                }
+                output.push_str("{\n");
+                for (index, (key, value)) in entries.iter().enumerate() {
+                    indent(output, depth + 1);
+                    write_string(output, key);
+                    output.push_str(": ");
+                    value.write(output, depth + 1);
+                    if index + 1 < entries.len() {
+                        output.push(',');
+                    }
+                    output.push('\n');
+                }
+                indent(output, depth);
+                output.push('}');
+            }
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Json, JsonError> {
+        let mut parser = Parser { characters: text.chars().collect(), position: 0, depth: 0 };
+        parser.skip_whitespace();
+        let value = parser.parse_value()?;
+        parser.skip_whitespace();
+        if parser.position < parser.characters.len() {
+            return Err(parser.error("Unexpected text after the end of the document"));
+        }
+        Ok(value)
+    }
+}
+
+fn write_string(output: &mut String, text: &str) {
+    output.push('"');
+    for character in text.chars() {
+        match character {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            character if (character as u32) < 0x20 => output.push_str(&format!("\\u{:04x}", character as u32)),
+            character => output.push(character)
+        }
+    }
+    output.push('"');
+}
+
+//#region Parser
+struct Parser {
+    characters: Vec<char>,
+    position: usize,
+    // how many arrays and objects the parser is inside
+    depth: usize
+}
+
+impl Parser {
+    fn error(&self, message: &str) -> JsonError {
+        let before = &self.characters[..self.position.min(self.characters.len())];
+        let line = before.iter().filter(|character| **character == '\n').count() + 1;
+        let column = before.iter().rev().take_while(|character| **character != '\n').count() + 1;
+        JsonError { line, column, message: String::from(message) }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.characters.get(self.position).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let character = self.peek();
+        self.position += 1;
+        character
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(character) = self.peek() {
+            if !character.is_whitespace() {
+                break;
+            }
+            self.position += 1;
+        }
+    }
+
+    fn expect_word(&mut self, word: &str, value: Json) -> Result<Json, JsonError> {
+        for expected in word.chars() {
+            if self.next() != Some(expected) {
+                self.position -= 1;
+                return Err(self.error(&format!("Expected {}", word)));
+            }
+        }
+        Ok(value)
+    }
+
+    fn parse_value(&mut self) -> Result<Json, JsonError> {
+        match self.peek() {
+            Some('{') => self.parse_nested(Parser::parse_object),
+            Some('[') => self.parse_nested(Parser::parse_array),
+            Some('"') => Ok(Json::String(self.parse_string()?)),
+            Some('t') => self.expect_word("true", Json::Bool(true)),
+            Some('f') => self.expect_word("false", Json::Bool(false)),
+            Some('n') => self.expect_word("null", Json::Null),
+            Some(character) if character == '-' || character.is_ascii_digit() => self.parse_number(),
+            Some(_) => Err(self.error("Unexpected character")),
+            None => Err(self.error("Unexpected end of document"))
+        }
+    }
+
+    fn parse_nested(&mut self, parse: fn(&mut Parser) -> Result<Json, JsonError>) -> Result<Json, JsonError> {
+        if self.depth >= MAX_DEPTH {
+            return Err(self.error(&format!("Nested deeper than {} levels", MAX_DEPTH)));
+        }
+        self.depth += 1;
+        let result = parse(self);
+        self.depth -= 1;
+        result
+    }
+
+    fn parse_number(&mut self) -> Result<Json, JsonError> {
+        let start = self.position;
+        while let Some(character) = self.peek() {
+            if !(character.is_ascii_digit() || "+-.eE".contains(character)) {
+                break;
+            }
+            self.position += 1;
+        }
+        let text: String = self.characters[start..self.position].iter().collect();
+        if text.parse::<f64>().is_err() {
+            self.position = start;
+            return Err(self.error("Invalid number"));
+        }
+        Ok(Json::Number(text))
+    }
+
+    fn parse_hex(&mut self) -> Result<u32, JsonError> {
+        let mut value = 0;
+        for _ in 0..4 {
+            let digit = self.next()
+                .and_then(|character| character.to_digit(16))
+                .ok_or_else(|| self.error("Invalid unicode escape"))?;
+            value = value * 16 + digit;
+        }
+        Ok(value)
+    }
+
+    fn parse_string(&mut self) -> Result<String, JsonError> {
+        // opening quote
+        self.next();
+        let mut text = String::new();
+        loop {
+            match self.next() {
+                Some('"') => return Ok(text),
+                Some('\\') => {
+                    let character = match self.next() {
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('/') => '/',
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('u') => {
+                            let mut code = self.parse_hex()?;
+                            // surrogate pairs are written as two escapes, a high
+                            // half must be followed by a low one
+                            if (0xD800..0xDC00).contains(&code) {
+                                if self.next() != Some('\\') || self.next() != Some('u') {
+                                    return Err(self.error("Unpaired surrogate in unicode escape"));
+                                }
+                                let low = self.parse_hex()?;
+                                if !(0xDC00..0xE000).contains(&low) {
+                                    return Err(self.error("Unpaired surrogate in unicode escape"));
+                                }
+                                code = 0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00);
+                            }
+                            std::char::from_u32(code).ok_or_else(|| self.error("Invalid unicode escape"))?
+                        },
+                        _ => return Err(self.error("Invalid escape"))
+                    };
+                    text.push(character);
+                },
+                Some(character) => text.push(character),
+                None => return Err(self.error("Unterminated string"))
+            }
+        }
+    }
+
+    fn parse_array(&mut self) -> Result<Json, JsonError> {
+        self.next();
+        let mut values = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.next();
+            return Ok(Json::Array(values));
+        }
+        loop {
+            self.skip_whitespace();
+            values.push(self.parse_value()?);
+            self.skip_whitespace();
+            match self.next() {
+                Some(',') => continue,
+                Some(']') => return Ok(Json::Array(values)),
+                _ => {
+                    self.position -= 1;
+                    return Err(self.error("Expected , or ]"));
+                }
+            }
+        }
+    }
+
+    fn parse_object(&mut self) -> Result<Json, JsonError> {
+        self.next();
+        let mut entries = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.next();
+            return Ok(Json::Object(entries));
+        }
+        loop {
+            self.skip_whitespace();
+            if self.peek() != Some('"') {
+                return Err(self.error("Expected a key"));
+            }
+            let key = self.parse_string()?;
+            self.skip_whitespace();
+            if self.next() != Some(':') {
+                self.position -= 1;
+                return Err(self.error("Expected :"));
+            }
+            self.skip_whitespace();
+            entries.push((key, self.parse_value()?));
+            self.skip_whitespace();
+            match self.next() {
+                Some(',') => continue,
+                Some('}') => return Ok(Json::Object(entries)),
+                _ => {
+                    self.position -= 1;
+                    return Err(self.error("Expected , or }"));
+                }
+            }
+        }
+    }
+}
+//#endregion
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_every_kind_of_value() {
+        let json = Json::parse(r#"{"a": [1, -2.5e3, true, false, null], "b": {"c": "d"}, "e": []}"#).unwrap();
+        assert_eq!(json.get("a"), Some(&Json::Array(vec![
+            Json::Number(String::from("1")),
+            Json::Number(String::from("-2.5e3")),
+            Json::Bool(true),
+            Json::Bool(false),
+            Json::Null
+        ])));
+        assert_eq!(json.get("b").and_then(|b| b.get("c")).and_then(|c| c.as_str()), Some("d"));
+        assert_eq!(json.get("e").and_then(|e| e.as_array()).map(|e| e.len()), Some(0));
+        assert_eq!(json.get("missing"), None);
+    }
+
+    #[test]
+    fn pretty_string_round_trips() {
+        let json = Json::Object(vec![
+            (String::from("numbers"), Json::Array(vec![0.1f32.into(), 1e-7f32.into(), f32::MAX.into()])),
+            (String::from("text"), "quote \" slash \\ newline \n tab \t bell \u{7} snowman \u{2603}".into()),
+            (String::from("nested"), Json::Array(vec![
+                Json::Object(vec![(String::from("empty"), Json::Object(Vec::new()))]),
+                Json::Array(Vec::new()),
+                Json::Null
+            ])),
+            (String::from("count"), 42usize.into())
+        ]);
+        let text = json.to_pretty_string();
+        assert_eq!(Json::parse(&text).unwrap(), json);
+        assert_eq!(Json::parse(&text).unwrap().to_pretty_string(), text);
+    }
+
+    #[test]
+    fn f32_values_read_back_exactly() {
+        for value in [0.1f32, 1.0 / 3.0, -123.456, 1e-38, 3.4e38].iter() {
+            let text = Json::from(*value).to_pretty_string();
+            assert_eq!(Json::parse(&text).unwrap().as_f32(), Some(*value));
+        }
+        // non finite numbers are written as null
+        assert_eq!(Json::from(f32::INFINITY), Json::Null);
+        assert!(Json::Null.as_f32().unwrap().is_nan());
+    }
+
+    #[test]
+    fn reads_escapes() {
+        let json = Json::parse(r#""A\/😀\b\f""#).unwrap();
+        assert_eq!(json.as_str(), Some("A/\u{1F600}\u{8}\u{c}"));
+    }
+
+    #[test]
+    fn pairs_surrogate_escapes() {
+        let json = Json::parse(r#""A\ud83d\ude00\udbff\udfff""#).unwrap();
+        assert_eq!(json.as_str(), Some("A\u{1F600}\u{10FFFF}"));
+        // a high half has to be followed by an escaped low half
+        assert!(Json::parse(r#""\ud83d""#).is_err());
+        assert!(Json::parse(r#""\ud83dx""#).is_err());
+        assert!(Json::parse(r#""\ud83d\n""#).is_err());
+        assert!(Json::parse(r#""\ud83d\u0041""#).is_err());
+        assert!(Json::parse(r#""\ud83d\ud83d""#).is_err());
+        assert!(Json::parse(r#""\ude00""#).is_err());
+    }
+
+    #[test]
+    fn reports_where_errors_are() {
+        let error = Json::parse("{\n    \"a\": tru\n}").unwrap_err();
+        assert_eq!((error.line, error.column), (2, 13));
+        assert!(Json::parse("[1, 2").is_err());
+        assert!(Json::parse("{\"a\" 1}").is_err());
+        assert!(Json::parse("\"unterminated").is_err());
+        assert!(Json::parse("1 2").is_err());
+        assert!(Json::parse("").is_err());
+        assert!(Json::parse("-").is_err());
+    }
+
+    #[test]
+    fn limits_nesting_depth() {
+        let nested = |depth: usize| "[".repeat(depth) + &"]".repeat(depth);
+        assert!(Json::parse(&nested(MAX_DEPTH)).is_ok());
+        let error = Json::parse(&nested(MAX_DEPTH + 1)).unwrap_err();
+        assert_eq!(error.column, MAX_DEPTH + 1);
+        // deep enough to overflow the stack without the limit
+        assert!(Json::parse(&"{\"a\":".repeat(100_000)).is_err());
+        assert!(Json::parse(&nested(100_000)).is_err());
+    }
+}
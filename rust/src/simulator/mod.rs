@@ -8,53 +8,331 @@ pub use window::WindowBuilder;
 pub use window::color::Color;
 use window::Window;
 
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 
 mod renderer;
-use renderer::{Renderer, RenderMode};
-pub use renderer::OriginPosition;
-pub use renderer::linearalgebra::{Vector2D, Vector3D};
+use renderer::{Renderer, RenderMode, ProjectionBuffer};
+use renderer::rasterizer::{TileRasterizer, Primitive};
+pub use renderer::{OriginPosition, Camera, Transform};
+pub use renderer::linearalgebra::{MatrixError, Vector3D};
 
 pub mod objects;
-use objects::Object;
+use objects::{Object, Vertex};
+
+mod handle;
+pub use handle::ObjectHandle;
+
+pub mod world;
+pub use world::{World, Geometry, Material, Hidden, Layer, ZOrder};
+use world::{System, Entity};
 
 pub mod time;
-use time::{Time, Instant, Duration};
+use time::{Time, FixedTimestep, FramePacer, Instant, Duration};
+
+pub mod simd;
+
+pub mod dirty;
+use dirty::{DirtyRegions, Rect};
+
+pub mod input;
+pub use input::{Input, InputEvent};
+
+pub mod events;
+pub use events::Event;
+
+pub mod scaling;
+pub use scaling::ScalingMode;
+
+pub mod recorder;
+use recorder::Recorder;
+pub use recorder::RecordingFormat;
+
+pub mod random;
+pub use random::Random;
+
+pub mod session;
+use session::{SessionRecorder, SessionReplay, SessionFrame};
+
+pub mod json;
+pub mod scene;
+pub use scene::{SceneError, SceneEntity};
+
+// below this many objects projection happens on the calling thread
+// as spawning workers would cost more than it saves
+const PARALLEL_PROJECTION_THRESHOLD: usize = 512;
+
+//#region ObjectEntry
+// a registered object, or an entity with geometry, along with its projected
+// vertices from the last frame
+struct ObjectEntry {
+    handle: ObjectHandle,
+    // none for entities, which are drawn from their components
+    object: Option<Box<dyn Object>>,
+    projection: ProjectionBuffer,
+    // the transform used for the last projection
+    render_transform: Transform,
+    // where and how the object was last drawn, used to find the
+    // regions of the screen that need redrawing
+    drawn_bounds: Option<Rect>,
+    drawn_state: Option<(Transform, Color, (Layer, ZOrder))>,
+    force_redraw: bool,
+    // whether it is drawn this frame and where in the draw order
+    shown: bool,
+    draw_order: (Layer, ZOrder),
+    // when the entry was created, breaks ties between equal z-orders so the
+    // order doesn't depend on which slots were reused
+    sequence: u64
+}
+
+impl ObjectEntry {
+    fn new(handle: ObjectHandle, object: Option<Box<dyn Object>>, sequence: u64) -> Self {
+        Self {
+            handle,
+            object,
+            projection: ProjectionBuffer::new(),
+            render_transform: Transform::new(),
+            drawn_bounds: None,
+            drawn_state: None,
+            force_redraw: false,
+            shown: true,
+            draw_order: (Layer::World, ZOrder(0)),
+            sequence
+        }
+    }
+
+    // the cached and current transform
+    fn get_transforms(&self, world: &World) -> (Transform, Transform) {
+        match &self.object {
+            Some(object) => (*object.get_cached_transform(), *object.transform()),
+            None => {
+                let transform = world.get::<Transform>(self.handle).copied().unwrap_or_else(Transform::new);
+                (world.get_cached_transform(self.handle).copied().unwrap_or(transform), transform)
+            }
+        }
+    }
+
+    fn get_verticies<'a>(&'a self, world: &'a World) -> &'a [Vertex] {
+        match &self.object {
+            Some(object) => object.get_verticies(),
+            None => world.get::<Geometry>(self.handle).map_or(&[], |geometry| &geometry.verticies)
+        }
+    }
+
+    fn get_frame_color(&self, world: &World) -> Color {
+        match &self.object {
+            Some(object) => object.get_frame_color(),
+            None => world.get::<Material>(self.handle).map_or(Color::BLACK, |material| material.frame_color)
+        }
+    }
+
+    fn has_changed(&self, world: &World) -> bool {
+        self.force_redraw
+            || self.drawn_state != Some((self.render_transform, self.get_frame_color(world), self.draw_order))
+    }
+
+    // the area the object covered last frame combined with the area it covers now
+    fn get_dirty_bounds(&self) -> Option<Rect> {
+        match (self.drawn_bounds, self.projection.get_bounds()) {
+            (Some(old), Some(new)) => Some(old.union(&new)),
+            (old, new) => old.or(new)
+        }
+    }
+
+    fn mark_drawn(&mut self, world: &World) {
+        if self.shown {
+            self.drawn_bounds = self.projection.get_bounds();
+            self.drawn_state = Some((self.render_transform, self.get_frame_color(world), self.draw_order));
+        }
+        else {
+            self.drawn_bounds = None;
+            self.drawn_state = None;
+        }
+        self.force_redraw = false;
+    }
+
+    // with an interpolation alpha the object is drawn between its transform
+    // at the previous fixed update (the cached transform) and its current one
+    fn project(&mut self, renderer: &Renderer, world: &World, buffer_size: (i32, i32), interpolation: Option<f32>) -> Result<(), MatrixError> {
+        let (cached_transform, transform) = self.get_transforms(world);
+        self.render_transform = match interpolation {
+            Some(alpha) => cached_transform.interpolate(&transform, alpha),
+            None => transform
+        };
+        let transform_matrix = renderer.get_transform_matrix(&self.render_transform, buffer_size)?;
+        // taken out so the vertices can be borrowed from the entry meanwhile
+        let mut projection = std::mem::take(&mut self.projection);
+        let result = renderer.project_points(
+            &transform_matrix,
+            self.get_verticies(world).iter().map(|vertex| vertex.get_rel_pos()),
+            buffer_size,
+            &mut projection
+        );
+        if result.is_ok() {
+            projection.clip_edges(self.get_verticies(world).iter().enumerate().flat_map(|(start, vertex)| {
+                vertex.get_connections().iter().map(move |end| (start, *end))
+            }));
+        }
+        self.projection = projection;
+        result
+    }
+
+    // submits the points and edges from the last projection to the rasterizer
+    fn paint(&self, rasterizer: &mut TileRasterizer, world: &World) {
+        let frame_color: u32 = self.get_frame_color(world).into();
+
+        for point in self.projection.get_points() {
+            rasterizer.submit(Primitive::Point {
+                position: (point.x, point.y),
+                depth: point.depth,
+                color: frame_color
+            });
+        }
+        for (start, end) in self.projection.get_edges().iter() {
+            rasterizer.submit(Primitive::Line {
+                start: (start.x, start.y),
+                end: (end.x, end.y),
+                start_depth: start.depth,
+                end_depth: end.depth,
+                color: frame_color
+            });
+        }
+    }
+}
+//#endregion
+
+// called once per fixed step with the step length in seconds
+pub type FixedUpdate = Box<dyn FnMut(&mut Simulator, f32)>;
+
+// called for every event delivered by update
+pub type EventCallback = Box<dyn FnMut(&mut Simulator, &Event)>;
 
 //#region Simulator
 pub struct Simulator {
-    objects: HashMap<usize, Box<dyn Object>>,
+    // registered objects and entities with geometry, ordered by handle
+    objects: BTreeMap<ObjectHandle, ObjectEntry>,
+    // given to each entry as it is created, for the draw order
+    next_sequence: u64,
+    world: World,
+    systems: Vec<System>,
+    // indexed by layer
+    enabled_layers: [bool; 3],
     renderer: Renderer,
-    use_object_clearing: bool,
+    rasterizer: TileRasterizer,
+    use_dirty_rectangles: bool,
+    dirty_regions: DirtyRegions,
+    last_buffer_size: (i32, i32),
+    full_redraw: bool,
+    // when set frames are drawn at this size then scaled to the window
+    internal_resolution: Option<(i32, i32)>,
+    internal_buffer: Vec<u32>,
+    scaling_mode: ScalingMode,
     window: Window,
     pub time: Time,
+    input: Input,
+    // scripted input applied at the start of the next frame
+    injected_input: Vec<InputEvent>,
+    // events delivered this frame, and those raised since to be delivered next
+    events: Vec<Event>,
+    incoming_events: Vec<Event>,
+    subscribers: Vec<(usize, EventCallback)>,
+    // ids of the subscribers taken out while dispatching, and those of them
+    // unsubscribed since, removed once dispatching is done
+    dispatching: Vec<usize>,
+    unsubscribed: Vec<usize>,
+    next_subscription_id: usize,
+    window_closed: bool,
+    fixed_timestep: Option<FixedTimestep>,
+    fixed_updates: Vec<FixedUpdate>,
     restrict_frame_rate: bool,
-    frame_delay: Duration,
-    last_frame_start: Instant
+    frame_pacer: FramePacer,
+    last_frame_start: Instant,
+    recorder: Option<Recorder>,
+    // set while recording at a fixed frame rate, time then advances by one
+    // frame per update instead of following the wall clock
+    recording_frame_rate: Option<u32>,
+    random: Random,
+    session_recorder: Option<SessionRecorder>,
+    replay: Option<SessionReplay>,
+    // the recorded frame being replayed by the current update
+    replay_frame: Option<SessionFrame>,
+    // internal resolution to go back to once the replay ends
+    resolution_before_replay: Option<(i32, i32)>
 }
 
 impl Simulator {
     pub fn update(&mut self) -> Result<f32, ()> {
         if !self.window.is_running() {
+            if !self.window_closed {
+                self.window_closed = true;
+                if let Err(error) = self.stop_recording() {
+                    eprintln!("Failed to finish recording: {}", error);
+                }
+                if let Err(error) = self.stop_session_recording() {
+                    eprintln!("Failed to finish session recording: {}", error);
+                }
+                self.incoming_events.push(Event::Closed);
+                self.dispatch_events();
+            }
             return Err(());
         }
 
-        if self.restrict_frame_rate { // enter loop to maintain framerate if needed
-            std::thread::sleep(self.frame_delay - self.last_frame_start.elapsed());
+        // frames recorded at a fixed rate are rendered as fast as possible
+        if self.restrict_frame_rate && self.recording_frame_rate.is_none() { // wait to maintain the framerate if needed
+            let overrun = self.frame_pacer.wait();
+            self.time.record_pacing(overrun);
         }
 
         self.last_frame_start = Instant::now();
 
+        self.begin_replay_frame();
+
         let delta = self.time.update();
+        if let Some(recorder) = &mut self.session_recorder {
+            recorder.get_frame_mut().delta = self.time.get_unscaled_delta_time();
+        }
+
+        self.update_input();
+        self.dispatch_events();
+
+        let interpolation = self.run_fixed_updates(delta);
+        if self.fixed_timestep.is_none() {
+            self.run_systems(delta);
+        }
+        self.sync_entities();
+        self.update_draw_order();
 
         #[cfg(feature="simulator_profile")]
         let mut profile_timer = Instant::now();
 
-        if !self.use_object_clearing {
+        let (buffer_width, buffer_height) = self.get_resolution();
+        if let Some(recorder) = &mut self.session_recorder {
+            recorder.get_frame_mut().resolution = (buffer_width, buffer_height);
+        }
+
+        // the buffer is reallocated when the window changes size so nothing
+        // from previous frames survives
+        let internal_length = (buffer_width * buffer_height) as usize;
+        let internal_buffer_stale = self.internal_resolution.is_some() && self.internal_buffer.len() != internal_length;
+        if (buffer_width, buffer_height) != self.last_buffer_size || internal_buffer_stale {
+            self.last_buffer_size = (buffer_width, buffer_height);
+            self.full_redraw = true;
+            if self.internal_resolution.is_some() {
+                self.internal_buffer.clear();
+                self.internal_buffer.resize(
+                    internal_length,
+                    self.window.get_background_color().into()
+                );
+            }
+        }
+        self.renderer.set_resolution(buffer_width as f32, buffer_height as f32);
+
+        let partial_redraw = self.use_dirty_rectangles && !self.full_redraw;
+
+        if !partial_redraw {
             self.clear_screen();
 
             #[cfg(feature="simulator_profile")]
-            println!("Frame:\n\tScreen Clear Time: {}ms", profile_timer.elapsed().as_millis());
+            eprintln!("Frame:\n\tScreen Clear Time: {}ms", profile_timer.elapsed().as_millis());
         }
 
         #[cfg(feature="simulator_profile")]
@@ -62,97 +340,737 @@ impl Simulator {
             profile_timer = Instant::now();
         }
 
-        for obj in self.objects.values_mut() {
-            Simulator::paint_object(obj, &self.renderer, &mut self.window, false);
-            if self.use_object_clearing {
-                obj.cache_transform();
+        self.project_objects((buffer_width, buffer_height), interpolation);
+
+        #[cfg(feature="simulator_profile")]
+        {
+            eprintln!("\tObject Projection Time: {}ms", profile_timer.elapsed().as_millis());
+            profile_timer = Instant::now();
+        }
+
+        self.rasterizer.begin_frame(buffer_width, buffer_height);
+
+        if partial_redraw {
+            for entry in self.objects.values() {
+                if !entry.shown {
+                    // hidden since last drawn, so needs clearing
+                    if let Some(bounds) = entry.drawn_bounds {
+                        self.dirty_regions.add(bounds);
+                    }
+                }
+                else if entry.has_changed(&self.world) {
+                    if let Some(bounds) = entry.get_dirty_bounds() {
+                        self.dirty_regions.add(bounds);
+                    }
+                }
+            }
+            self.dirty_regions.clip(buffer_width, buffer_height);
+            let background_color = self.window.get_background_color();
+            for region in self.dirty_regions.get_regions() {
+                if self.internal_resolution.is_some() {
+                    scaling::fill_rect(&mut self.internal_buffer, buffer_width, *region, background_color.into());
+                }
+                else {
+                    self.window.fill_rect(*region, background_color);
+                }
             }
+            self.rasterizer.set_clip_regions(self.dirty_regions.get_regions());
+        }
+
+        let draw_list = self.get_draw_list();
+        let use_depth = self.uses_depth_buffer();
+        let dirty_regions = &self.dirty_regions;
+        let world = &self.world;
+        for layer in Layer::ALL.iter() {
+            for (_, handle) in draw_list.iter().filter(|(entry_layer, _)| entry_layer == layer) {
+                let entry = &self.objects[handle];
+                // untouched objects outside of the dirty regions are still on screen
+                let needs_paint = !partial_redraw || entry.projection
+                    .get_bounds()
+                    .is_some_and(|bounds| dirty_regions.intersects(&bounds));
+                if needs_paint {
+                    entry.paint(&mut self.rasterizer, world);
+                }
+            }
+            self.rasterizer.begin_layer();
+        }
+        let target = if self.internal_resolution.is_some() {
+            &mut self.internal_buffer[..]
+        }
+        else {
+            self.window.get_buffer_mut()
+        };
+        self.rasterizer.rasterize(target, use_depth);
+
+        for entry in self.objects.values_mut() {
+            entry.mark_drawn(world);
         }
 
         #[cfg(feature="simulator_profile")]
-        println!("\tObject Render Time: {}ms", profile_timer.elapsed().as_millis());
+        eprintln!("\tObject Render Time: {}ms", profile_timer.elapsed().as_millis());
 
         #[cfg(feature="simulator_profile")]
         {
             profile_timer = Instant::now();
         }
         
-        self.window.update();
+        if self.internal_resolution.is_some() {
+            self.scale_to_window();
+        }
+
+        self.record_frame();
+
+        if self.internal_resolution.is_some() || !partial_redraw {
+            self.window.update();
+        }
+        else {
+            self.window.update_regions(self.dirty_regions.get_regions());
+        }
+
+        self.dirty_regions.clear();
+        self.full_redraw = false;
+
+        self.end_session_frame();
 
         #[cfg(feature="simulator_profile")]
         {
-            println!("\tWindow Update Time: {}ms", profile_timer.elapsed().as_millis());
-            println!("\tFrame time: {}ms\nEnd Frame", self.last_frame_start.elapsed().as_millis());
+            eprintln!("\tWindow Update Time: {}ms", profile_timer.elapsed().as_millis());
+            eprintln!("\tFrame time: {}ms\nEnd Frame", self.last_frame_start.elapsed().as_millis());
         }
 
         Ok(delta)
     }
 
-    pub fn paint_background(&mut self) {
-        self.window.fill(self.window.get_background_color());
+    // scales the internal buffer into the window's buffer
+    fn scale_to_window(&mut self) {
+        let resolution = self.get_resolution();
+        let window_size = self.window.get_buffer_size();
+        let area = scaling::fit_rect(resolution, window_size, self.scaling_mode);
+        if self.scaling_mode == ScalingMode::Letterbox {
+            self.window.fill(self.window.get_background_color());
+        }
+        scaling::blit_scaled(
+            &self.internal_buffer,
+            resolution,
+            self.window.get_buffer_mut(),
+            window_size,
+            area
+        );
     }
 
-    // clears the currently rendered pixels from the screen ready to draw new ones
-    pub fn clear_from_screen(&mut self, object_id: &usize) {
-        #[cfg(feature="simulator_profile")]
-        let clear_timer = Instant::now();
+    // size of the frame objects are drawn into, the window size unless an
+    // internal resolution has been set
+    pub fn get_resolution(&self) -> (i32, i32) {
+        self.internal_resolution.unwrap_or_else(|| self.window.get_buffer_size())
+    }
 
-        let renderer = &self.renderer;
-        let window = &mut self.window;
-        let background_color = window.get_background_color();
-        let object = self.objects.get_mut(&object_id).unwrap();
-        let current_frame_color = object.get_frame_color();
-        let current_fill_color = object.get_fill_color();
-        object.set_frame_color(background_color);
-        object.set_fill_color(background_color);
-        Simulator::paint_object(object, renderer, window, true);
-        object.set_frame_color(current_frame_color);
-        object.set_fill_color(current_fill_color);
+    // draws at a fixed size which is then scaled to fit the window
+    pub fn set_internal_resolution(&mut self, width: i32, height: i32) -> &mut Self {
+        self.internal_resolution = Some((width.max(1), height.max(1)));
+        self
+    }
 
-        #[cfg(feature="simulator_profile")]
-        println!("\tClear Time: {}ms", clear_timer.elapsed().as_millis());
+    // draws straight into the window at its own size
+    pub fn use_window_resolution(&mut self) -> &mut Self {
+        self.internal_resolution = None;
+        self.internal_buffer = Vec::new();
+        self.full_redraw = true;
+        self
+    }
+
+    pub fn get_scaling_mode(&self) -> ScalingMode {
+        self.scaling_mode
+    }
+
+    pub fn set_scaling_mode(&mut self, mode: ScalingMode) -> &mut Self {
+        self.scaling_mode = mode;
+        self
+    }
+
+    // converts a position in the window, such as the mouse position, to the
+    // frame, none if it falls in the letterbox bars
+    pub fn window_to_frame(&self, position: (i32, i32)) -> Option<(i32, i32)> {
+        match self.internal_resolution {
+            Some(resolution) => {
+                let area = scaling::fit_rect(resolution, self.window.get_buffer_size(), self.scaling_mode);
+                scaling::to_source(position, resolution, area)
+            },
+            None => Some(position)
+        }
+    }
+
+    // applies the window events and input received while presenting the last
+    // frame, or those recorded for this frame when replaying
+    fn update_input(&mut self) {
+        self.input.begin_frame();
+        let mut window_events = self.window.take_events();
+        let mut input_events = self.window.take_input_events();
+        input_events.append(&mut self.injected_input);
+        // mouse positions are kept in frame coordinates, moves over the
+        // letterbox bars are dropped so the mouse stays where it left the frame
+        let mut input_events: Vec<InputEvent> = input_events
+            .into_iter()
+            .filter_map(|event| match event {
+                InputEvent::MouseMoved { x, y } => {
+                    self.window_to_frame((x, y)).map(|(x, y)| InputEvent::MouseMoved { x, y })
+                },
+                event => Some(event)
+            })
+            .collect();
+        if let Some(frame) = &mut self.replay_frame {
+            // the window can still be closed while replaying
+            let close_requested = window_events.contains(&Event::CloseRequested);
+            window_events = std::mem::take(&mut frame.window_events);
+            if close_requested {
+                window_events.push(Event::CloseRequested);
+            }
+            input_events = std::mem::take(&mut frame.input);
+        }
+        if let Some(recorder) = &mut self.session_recorder {
+            let frame = recorder.get_frame_mut();
+            frame.window_events = window_events.clone();
+            frame.input = input_events.clone();
+        }
+        for event in window_events {
+            self.incoming_events.push(event);
+            // the key and button ups will go to whichever window now has focus
+            if event == Event::FocusLost {
+                let released = self.input.release_all();
+                self.incoming_events.extend(released.into_iter().map(Event::from));
+            }
+        }
+        for event in input_events {
+            if self.input.handle_event(&event) {
+                self.incoming_events.push(Event::from(event));
+            }
+        }
+    }
+
+    pub fn get_input(&self) -> &Input {
+        &self.input
+    }
+
+    // queues an event as if it came from the window, for scripted input,
+    // ignored while replaying as the recorded session already includes it
+    pub fn inject_input(&mut self, event: InputEvent) -> &mut Self {
+        self.injected_input.push(event);
+        self
+    }
+
+    // replaces the last frame's events with those raised since, passing each
+    // to the subscribers, anything they raise is delivered next frame
+    fn dispatch_events(&mut self) {
+        self.events = std::mem::take(&mut self.incoming_events);
+        if self.subscribers.is_empty() {
+            return;
+        }
+        // the queue stays in place so callbacks can read or poll it
+        let events = self.events.clone();
+        let mut subscribers = std::mem::take(&mut self.subscribers);
+        self.dispatching = subscribers.iter().map(|(id, _)| *id).collect();
+        for event in events.iter() {
+            for (id, callback) in subscribers.iter_mut() {
+                if !self.unsubscribed.contains(id) {
+                    callback(self, event);
+                }
+            }
+        }
+        let unsubscribed = std::mem::take(&mut self.unsubscribed);
+        subscribers.retain(|(id, _)| !unsubscribed.contains(id));
+        self.dispatching.clear();
+        // keep any subscriptions made while dispatching
+        subscribers.append(&mut self.subscribers);
+        self.subscribers = subscribers;
+    }
+
+    // the events delivered this frame, removing them from the queue
+    pub fn poll_events(&mut self) -> std::vec::Drain<'_, Event> {
+        self.events.drain(..)
+    }
+
+    // the events delivered this frame
+    pub fn get_events(&self) -> &[Event] {
+        &self.events
+    }
+
+    // calls the closure with every event from now on, returning an id
+    // that can be used to unsubscribe
+    pub fn subscribe<F: FnMut(&mut Simulator, &Event) + 'static>(&mut self, callback: F) -> usize {
+        let id = self.next_subscription_id;
+        self.next_subscription_id += 1;
+        self.subscribers.push((id, Box::new(callback)));
+        id
+    }
+
+    // can be called from within any callback, including the subscription's
+    // own, it won't be called again after that
+    pub fn unsubscribe(&mut self, subscription_id: usize) -> bool {
+        if self.dispatching.contains(&subscription_id) {
+            if self.unsubscribed.contains(&subscription_id) {
+                return false;
+            }
+            self.unsubscribed.push(subscription_id);
+            return true;
+        }
+        let count = self.subscribers.len();
+        self.subscribers.retain(|(id, _)| *id != subscription_id);
+        self.subscribers.len() != count
+    }
+
+    // runs the fixed update callbacks once for every step that has built up,
+    // returning how far between the last two steps objects should be drawn
+    fn run_fixed_updates(&mut self, delta: f32) -> Option<f32> {
+        let timestep = self.fixed_timestep.as_mut()?;
+        let steps = timestep.advance(delta);
+        let step = timestep.get_step();
+        let mut callbacks = std::mem::take(&mut self.fixed_updates);
+        for _ in 0..steps {
+            self.cache_transforms();
+            self.run_systems(step);
+            for callback in callbacks.iter_mut() {
+                callback(self, step);
+            }
+        }
+        // keep any callbacks added while running
+        callbacks.append(&mut self.fixed_updates);
+        self.fixed_updates = callbacks;
+        self.fixed_timestep.as_ref().map(|timestep| timestep.get_alpha())
+    }
+
+    fn cache_transforms(&mut self) {
+        for object in self.objects.values_mut().filter_map(|entry| entry.object.as_mut()) {
+            object.cache_transform();
+        }
+        self.world.cache_transforms();
+    }
+
+    // systems run once per fixed step when using a fixed timestep, otherwise
+    // once per frame
+    pub fn add_system<F: FnMut(&mut World, f32) + 'static>(&mut self, system: F) -> &mut Self {
+        self.systems.push(Box::new(system));
+        self
+    }
+
+    pub fn clear_systems(&mut self) -> &mut Self {
+        self.systems.clear();
+        self
+    }
+
+    fn run_systems(&mut self, delta: f32) {
+        for system in self.systems.iter_mut() {
+            system(&mut self.world, delta);
+        }
+    }
+
+    pub fn get_world(&self) -> &World {
+        &self.world
+    }
+
+    pub fn get_world_mut(&mut self) -> &mut World {
+        &mut self.world
+    }
+
+    // entities gain an entry once they have geometry and lose it when they
+    // no longer do, objects whose entity was despawned are removed
+    fn sync_entities(&mut self) {
+        let world = &self.world;
+        let dirty_regions = &mut self.dirty_regions;
+        let incoming_events = &mut self.incoming_events;
+        self.objects.retain(|handle, entry| {
+            let keep = match entry.object {
+                Some(_) => world.is_alive(*handle),
+                None => world.has::<Geometry>(*handle)
+            };
+            if !keep {
+                if let Some(bounds) = entry.drawn_bounds {
+                    dirty_regions.add(bounds);
+                }
+                if entry.object.is_some() {
+                    incoming_events.push(Event::ObjectRemoved(*handle));
+                }
+            }
+            keep
+        });
+        let next_sequence = &mut self.next_sequence;
+        for (entity, _) in self.world.query::<Geometry>() {
+            self.objects.entry(entity).or_insert_with(|| {
+                *next_sequence += 1;
+                ObjectEntry::new(entity, None, *next_sequence)
+            });
+        }
+    }
+
+    fn update_draw_order(&mut self) {
+        let world = &self.world;
+        let enabled_layers = &self.enabled_layers;
+        for entry in self.objects.values_mut() {
+            let layer = world.get::<Layer>(entry.handle).copied().unwrap_or_default();
+            let z_order = world.get::<ZOrder>(entry.handle).copied().unwrap_or_default();
+            entry.draw_order = (layer, z_order);
+            entry.shown = enabled_layers[layer as usize] && !world.has::<Hidden>(entry.handle);
+        }
+    }
+
+    // each layer covers the ones before it, within a layer objects are
+    // drawn in z-order then the order they were added in
+    fn get_draw_list(&self) -> Vec<(Layer, ObjectHandle)> {
+        let mut draw_list: Vec<(Layer, ZOrder, u64, ObjectHandle)> = self.objects
+            .values()
+            .filter(|entry| entry.shown)
+            .map(|entry| (entry.draw_order.0, entry.draw_order.1, entry.sequence, entry.handle))
+            .collect();
+        draw_list.sort();
+        draw_list.into_iter().map(|(layer, _, _, handle)| (layer, handle)).collect()
+    }
+
+    // stored as components in the world, so these work for entities too
+    pub fn set_visible(&mut self, handle: &ObjectHandle, visible: bool) -> &mut Self {
+        if visible {
+            self.world.remove::<Hidden>(*handle);
+        }
+        else {
+            self.world.insert(*handle, Hidden);
+        }
+        self
+    }
+
+    pub fn is_visible(&self, handle: &ObjectHandle) -> bool {
+        self.world.is_alive(*handle) && !self.world.has::<Hidden>(*handle)
+    }
+
+    pub fn set_layer(&mut self, handle: &ObjectHandle, layer: Layer) -> &mut Self {
+        self.world.insert(*handle, layer);
+        self
+    }
+
+    pub fn get_layer(&self, handle: &ObjectHandle) -> Layer {
+        self.world.get::<Layer>(*handle).copied().unwrap_or_default()
+    }
+
+    pub fn set_z_order(&mut self, handle: &ObjectHandle, z_order: i32) -> &mut Self {
+        self.world.insert(*handle, ZOrder(z_order));
+        self
+    }
+
+    pub fn get_z_order(&self, handle: &ObjectHandle) -> i32 {
+        self.world.get::<ZOrder>(*handle).map_or(0, |z_order| z_order.0)
+    }
+
+    // objects in a disabled layer are kept but not drawn
+    pub fn set_layer_enabled(&mut self, layer: Layer, enabled: bool) -> &mut Self {
+        self.enabled_layers[layer as usize] = enabled;
+        self
+    }
+
+    pub fn is_layer_enabled(&self, layer: Layer) -> bool {
+        self.enabled_layers[layer as usize]
+    }
+
+    pub fn add_fixed_update<F: FnMut(&mut Simulator, f32) + 'static>(&mut self, callback: F) -> &mut Self {
+        self.fixed_updates.push(Box::new(callback));
+        self
+    }
+
+    pub fn clear_fixed_updates(&mut self) -> &mut Self {
+        self.fixed_updates.clear();
+        self
+    }
+
+    // steps per second of the fixed updates, none when objects are only
+    // moved once per frame
+    pub fn get_tick_rate(&self) -> Option<f32> {
+        self.fixed_timestep.as_ref().map(|timestep| timestep.get_tick_rate())
+    }
+
+    pub fn set_tick_rate(&mut self, tick_rate: f32) -> &mut Self {
+        match self.fixed_timestep.as_mut() {
+            Some(timestep) => timestep.set_tick_rate(tick_rate),
+            None => {
+                self.cache_transforms();
+                self.fixed_timestep = Some(FixedTimestep::new(tick_rate));
+            }
+        }
+        self
+    }
+
+    pub fn set_max_catch_up_steps(&mut self, max_steps: u32) -> &mut Self {
+        if let Some(timestep) = self.fixed_timestep.as_mut() {
+            timestep.set_max_steps(max_steps);
+        }
+        self
+    }
+
+    pub fn use_variable_timestep(&mut self) -> &mut Self {
+        self.fixed_timestep = None;
+        self
+    }
+
+    pub fn paint_background(&mut self) {
+        let background_color = self.window.get_background_color();
+        self.window.fill(background_color);
+        simd::fill_u32(&mut self.internal_buffer, background_color.into());
+        self.full_redraw = true;
+    }
+
+    // forces the object to be redrawn next frame when using dirty rectangles,
+    // needed when its vertices change as only transform and color are tracked
+    pub fn mark_dirty(&mut self, handle: &ObjectHandle) {
+        if let Some(entry) = self.objects.get_mut(handle) {
+            entry.force_redraw = true;
+        }
+    }
+
+    // kept for compatibility, clearing is now handled through dirty rectangles
+    pub fn clear_from_screen(&mut self, handle: &ObjectHandle) {
+        self.mark_dirty(handle);
     }
 
     pub fn clear_screen(&mut self) {
         self.paint_background();
     }
 
-    fn paint_object(obj: &Box<dyn Object>, renderer: &Renderer, window: &mut Window, use_cached_transform: bool) {
-        let mut projected_vertexs: Vec<(i32, i32)> = Vec::new();
-
-        let verticies = obj.get_verticies();
-        let frame_color = obj.get_frame_color();
+    fn uses_depth_buffer(&self) -> bool {
+        match self.renderer.get_mode() {
+            RenderMode::R2D => false,
+            RenderMode::R3D => true
+        }
+    }
 
-        let object_transform = if use_cached_transform {
-            obj.get_cached_transform()
+    // projects every object into its own buffer, splitting the objects
+    // between the worker threads when there are enough of them
+    fn project_objects(&mut self, buffer_size: (i32, i32), interpolation: Option<f32>) {
+        let renderer = &self.renderer;
+        let thread_count = self.rasterizer.get_thread_count();
+        let world = &self.world;
+        let mut entries: Vec<&mut ObjectEntry> = self.objects
+            .values_mut()
+            .filter(|entry| entry.shown)
+            .collect();
+
+        let errors: Vec<(ObjectHandle, MatrixError)> = if thread_count <= 1 || entries.len() < PARALLEL_PROJECTION_THRESHOLD {
+            project_entries(&mut entries, renderer, world, buffer_size, interpolation)
         }
         else {
-            obj.transform()
+            let chunk_size = entries.len().div_ceil(thread_count);
+            std::thread::scope(|scope| {
+                let workers: Vec<_> = entries
+                    .chunks_mut(chunk_size)
+                    .map(|chunk| scope.spawn(move || project_entries(chunk, renderer, world, buffer_size, interpolation)))
+                    .collect();
+                workers
+                    .into_iter()
+                    .flat_map(|worker| worker.join().expect("Projection worker panicked"))
+                    .collect()
+            })
         };
 
-        for i in 0..verticies.len() {
-            projected_vertexs.push((-1, -1));
-            projected_vertexs[i] = renderer.project_to_screen(object_transform, &verticies[i].get_rel_pos(), window.get_client_size());
+        for (handle, error) in errors {
+            eprintln!("Failed to project object {}: {}", handle, error);
         }
+    }
 
-        for i in 0..projected_vertexs.len() {
-            window.draw_point(
-                projected_vertexs[i].0, projected_vertexs[i].1, frame_color
-            );
-            for o in verticies[i].get_connections().iter() {
-                window.draw_line(
-                    (projected_vertexs[i].0, projected_vertexs[i].1), 
-                    (
-                        projected_vertexs[*o].0,
-                        projected_vertexs[*o].1
-                    ),
-                    frame_color
-                );
+    // records every frame presented from now on as it is drawn, formats that
+    // store a frame rate are given the target frame rate, so need the frame
+    // rate restricting for playback to match, otherwise use start_recording_at
+    pub fn start_recording(&mut self, format: RecordingFormat, path: &str) -> std::io::Result<()> {
+        if !format.is_sequence() && !self.restrict_frame_rate {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "the frame rate isn't restricted so frames aren't drawn at a known rate, use start_recording_at"
+            ));
+        }
+        let frame_rate = (1.0 / self.frame_pacer.get_frame_delay().as_secs_f64()).round() as u32;
+        self.begin_recording(format, path, frame_rate, false)
+    }
+
+    // records at exactly the given frame rate, each update advances time by
+    // one frame however long it takes to draw
+    pub fn start_recording_at(&mut self, format: RecordingFormat, path: &str, frame_rate: u32) -> std::io::Result<()> {
+        self.begin_recording(format, path, frame_rate.max(1), true)
+    }
+
+    fn begin_recording(&mut self, format: RecordingFormat, path: &str, frame_rate: u32, fixed_rate: bool) -> std::io::Result<()> {
+        self.stop_recording()?;
+        self.recorder = Some(Recorder::new(format, std::path::Path::new(path), frame_rate)?);
+        if fixed_rate {
+            self.recording_frame_rate = Some(frame_rate);
+            self.time.set_fixed_delta(Some(1.0 / frame_rate as f32));
+        }
+        Ok(())
+    }
+
+    // finishes the recording, returning how many frames were recorded
+    pub fn stop_recording(&mut self) -> std::io::Result<u64> {
+        if self.recording_frame_rate.take().is_some() {
+            self.reset_time_source();
+            self.frame_pacer.reset();
+        }
+        match self.recorder.take() {
+            Some(recorder) => recorder.finish(),
+            None => Ok(0)
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recorder.is_some()
+    }
+
+    pub fn get_recorded_frame_count(&self) -> u64 {
+        self.recorder.as_ref().map_or(0, |recorder| recorder.get_frame_count())
+    }
+
+    // writes the frame about to be presented, a failed write stops the recording
+    fn record_frame(&mut self) {
+        let result = match &mut self.recorder {
+            Some(recorder) => recorder.record_frame(self.window.get_buffer(), self.window.get_buffer_size()),
+            None => return
+        };
+        if let Err(error) = result {
+            eprintln!("Recording stopped, failed to write frame: {}", error);
+            let _ = self.stop_recording();
+        }
+    }
+
+    // time follows the wall clock unless frames are being recorded at a fixed
+    // rate, replays set the delta of each frame themselves
+    fn reset_time_source(&mut self) {
+        self.time.set_fixed_delta(self.recording_frame_rate.map(|frame_rate| 1.0 / frame_rate as f32));
+    }
+
+    // generator for anything random in the simulation, use it rather than
+    // another source of randomness so sessions can be replayed
+    pub fn get_random_mut(&mut self) -> &mut Random {
+        &mut self.random
+    }
+
+    // while replaying the seed that was set at this point in the recorded
+    // session is used instead
+    pub fn set_seed(&mut self, seed: u64) -> &mut Self {
+        let seed = match &mut self.replay {
+            Some(replay) => replay.next_seed().unwrap_or(seed),
+            None => seed
+        };
+        if let Some(recorder) = &mut self.session_recorder {
+            if let Err(error) = recorder.record_seed(seed) {
+                eprintln!("Failed to record seed: {}", error);
             }
         }
+        self.random.set_state(seed);
+        self
+    }
+
+    // clears everything carried over from earlier frames that a replay
+    // couldn't reproduce
+    fn reset_for_session(&mut self) {
+        self.input = Input::new();
+        self.injected_input.clear();
+        if let Some(timestep) = &mut self.fixed_timestep {
+            timestep.reset();
+        }
+        self.full_redraw = true;
+    }
+
+    // records the deltas, input and seeds of every frame from now on so the
+    // session can be replayed, best started before the first update
+    pub fn start_session_recording(&mut self, path: &str) -> std::io::Result<()> {
+        self.stop_session_recording()?;
+        self.session_recorder = Some(SessionRecorder::create(std::path::Path::new(path), self.random.get_state())?);
+        self.reset_for_session();
+        Ok(())
+    }
+
+    // returns how many frames were recorded
+    pub fn stop_session_recording(&mut self) -> std::io::Result<u64> {
+        match self.session_recorder.take() {
+            Some(recorder) => recorder.finish(),
+            None => Ok(0)
+        }
+    }
+
+    pub fn is_recording_session(&self) -> bool {
+        self.session_recorder.is_some()
+    }
+
+    // plays a recorded session back through update, starting from the same
+    // objects as the recording did gives identical frames
+    pub fn start_replay(&mut self, path: &str) -> std::io::Result<()> {
+        let replay = SessionReplay::load(std::path::Path::new(path))?;
+        self.stop_replay();
+        self.random.set_state(replay.get_random_state());
+        self.resolution_before_replay = self.internal_resolution;
+        self.replay = Some(replay);
+        self.reset_for_session();
+        Ok(())
+    }
+
+    pub fn stop_replay(&mut self) {
+        if self.replay.take().is_none() {
+            return;
+        }
+        self.replay_frame = None;
+        match self.resolution_before_replay.take() {
+            Some((width, height)) => {
+                self.set_internal_resolution(width, height);
+            },
+            None => {
+                self.use_window_resolution();
+            }
+        }
+        self.reset_time_source();
+    }
+
+    pub fn is_replaying(&self) -> bool {
+        self.replay.is_some()
+    }
+
+    // frames played and the total in the session
+    pub fn get_replay_progress(&self) -> Option<(usize, usize)> {
+        self.replay.as_ref().map(|replay| (
+            replay.get_frame_count() - replay.get_remaining_frames(),
+            replay.get_frame_count()
+        ))
+    }
+
+    // takes the next recorded frame and uses its delta and resolution, ending
+    // the replay once every frame has been played
+    fn begin_replay_frame(&mut self) {
+        let frame = match &mut self.replay {
+            Some(replay) => replay.next_frame(),
+            None => return
+        };
+        match frame {
+            Some(frame) => {
+                self.time.set_fixed_delta(Some(frame.delta));
+                // frames are drawn at the recorded size whatever the window's size
+                if self.get_resolution() != frame.resolution {
+                    self.set_internal_resolution(frame.resolution.0, frame.resolution.1);
+                }
+                self.replay_frame = Some(frame);
+            },
+            None => {
+                self.stop_replay();
+                self.incoming_events.push(Event::ReplayFinished);
+            }
+        }
+    }
+
+    // writes out the frame being recorded, a failed write stops the recording
+    fn end_session_frame(&mut self) {
+        self.replay_frame = None;
+        let result = match &mut self.session_recorder {
+            Some(recorder) => recorder.end_frame(),
+            None => return
+        };
+        if let Err(error) = result {
+            eprintln!("Session recording stopped, failed to write frame: {}", error);
+            let _ = self.stop_session_recording();
+        }
     }
 
     pub fn set_frame_rate_restriction(&mut self, restrict: bool) -> &mut Self {
+        if !self.restrict_frame_rate {
+            self.frame_pacer.reset();
+        }
         self.restrict_frame_rate = restrict;
         self
     }
@@ -166,7 +1084,23 @@ impl Simulator {
     }
 
     pub fn set_target_frame_rate(&mut self, target: u16) -> &mut Self {
-        self.frame_delay = Duration::from_nanos(1_000_000_000 / target as u64);
+        self.frame_pacer.set_target_frame_rate(target);
+        self
+    }
+
+    // how close to a frame's deadline the pacer stops sleeping and spins,
+    // higher is more accurate but uses more cpu
+    pub fn set_pacing_spin_threshold(&mut self, threshold: Duration) -> &mut Self {
+        self.frame_pacer.set_spin_threshold(threshold);
+        self
+    }
+
+    pub fn get_thread_count(&self) -> usize {
+        self.rasterizer.get_thread_count()
+    }
+
+    pub fn set_thread_count(&mut self, thread_count: usize) -> &mut Self {
+        self.rasterizer.set_thread_count(thread_count);
         self
     }
 
@@ -183,47 +1117,256 @@ impl Simulator {
         self.set_frame_rate_display(false)
     }
 
-    pub fn add_object(&mut self, object: Box<dyn Object>) -> usize {
-        let id = object.get_id();
-        self.objects.insert(id, object);
-        id
+    // the last presented frame as row major pixels, useful for effects
+    // such as motion blur or comparing frames
+    pub fn get_previous_frame(&self) -> &[u32] {
+        self.window.get_previous_frame()
     }
 
-    pub fn remove_object(&mut self, object_id: &usize) -> Option<Box<dyn Object>> {
-        self.objects.remove(object_id)
+    pub fn get_frame_size(&self) -> (i32, i32) {
+        self.window.get_buffer_size()
     }
 
-    pub fn get_object_by_id(&mut self, id: &usize) -> Option<&mut Box<dyn Object>> {
-        self.objects.get_mut(id)
+    pub fn get_camera(&self) -> &Camera {
+        self.renderer.get_camera()
     }
 
+    // moving the camera changes every object on screen
+    pub fn get_camera_mut(&mut self) -> &mut Camera {
+        self.full_redraw = true;
+        self.renderer.get_camera_mut()
+    }
+
+    // objects are entities in the world too, so components can be added to them
+    pub fn add_object(&mut self, mut object: Box<dyn Object>) -> ObjectHandle {
+        let handle = self.world.spawn();
+        // so interpolation starts from where the object was placed
+        object.cache_transform();
+        self.next_sequence += 1;
+        self.objects.insert(handle, ObjectEntry::new(handle, Some(object), self.next_sequence));
+        self.incoming_events.push(Event::ObjectAdded(handle));
+        handle
+    }
+
+    // the handle, and any copies of it, are invalid afterwards
+    pub fn remove_object(&mut self, handle: &ObjectHandle) -> Option<Box<dyn Object>> {
+        // entities without an object are removed through the world
+        match self.objects.get(handle) {
+            Some(entry) if entry.object.is_some() => {},
+            _ => return None
+        }
+        let entry = self.objects.remove(handle)?;
+        self.world.despawn(*handle);
+        if let Some(bounds) = entry.drawn_bounds {
+            self.dirty_regions.add(bounds);
+        }
+        self.incoming_events.push(Event::ObjectRemoved(*handle));
+        entry.object
+    }
+
+    // none once the object has been removed, even if its slot is reused
+    pub fn get_object_by_id(&mut self, handle: &ObjectHandle) -> Option<&mut Box<dyn Object>> {
+        if !self.world.is_alive(*handle) {
+            return None;
+        }
+        self.objects.get_mut(handle).and_then(|entry| entry.object.as_mut())
+    }
+
+    // the object as its concrete type, none if it was registered as another type
+    pub fn get_object<T: Object>(&mut self, handle: &ObjectHandle) -> Option<&mut T> {
+        self.get_object_by_id(handle).and_then(|object| object.downcast_mut::<T>())
+    }
+
+    pub fn contains_object(&self, handle: &ObjectHandle) -> bool {
+        self.world.is_alive(*handle)
+            && self.objects.get(handle).is_some_and(|entry| entry.object.is_some())
+    }
+
+    // registered objects, not counting entities drawn from components
     pub fn object_count(&self) -> usize {
-        self.objects.len()
+        self.objects.values().filter(|entry| entry.object.is_some()).count()
+    }
+
+    // every registered object in handle order, as slots are reused this
+    // isn't necessarily the order they were added in
+    pub fn objects(&self) -> impl Iterator<Item = (ObjectHandle, &dyn Object)> {
+        let world = &self.world;
+        self.objects
+            .iter()
+            .filter(move |(handle, _)| world.is_alive(**handle))
+            .filter_map(|(handle, entry)| entry.object.as_deref().map(|object| (*handle, object)))
+    }
+
+    pub fn objects_mut(&mut self) -> impl Iterator<Item = (ObjectHandle, &mut dyn Object)> {
+        let world = &self.world;
+        self.objects
+            .iter_mut()
+            .filter(move |(handle, _)| world.is_alive(**handle))
+            .filter_map(|(handle, entry)| entry.object.as_deref_mut().map(|object| (*handle, object)))
+    }
+
+    // names and tags are set through the world, see World::set_name and
+    // World::add_tag, these look up objects and entities alike
+    pub fn find_by_name(&self, name: &str) -> Option<ObjectHandle> {
+        self.world.find_by_name(name)
+    }
+
+    pub fn find_by_tag(&self, tag: &str) -> impl Iterator<Item = ObjectHandle> + '_ {
+        self.world.find_by_tag(tag)
+    }
+
+    pub fn find_objects<F: FnMut(ObjectHandle, &dyn Object) -> bool>(&self, mut predicate: F) -> Vec<ObjectHandle> {
+        self.objects()
+            .filter(|(handle, object)| predicate(*handle, *object))
+            .map(|(handle, _)| handle)
+            .collect()
+    }
+
+    // objects and entities with geometry whose vertices, placed in the
+    // world, overlap the box between the two corners
+    pub fn find_in_region(&self, min: Vector3D, max: Vector3D) -> Vec<ObjectHandle> {
+        let mut found = Vec::new();
+        for (handle, entry) in self.objects.iter() {
+            let (_, transform) = entry.get_transforms(&self.world);
+            let matrix = match transform.to_matrix() {
+                Ok(matrix) => matrix,
+                Err(_) => continue
+            };
+            let mut bounds: Option<(Vector3D, Vector3D)> = None;
+            for vertex in entry.get_verticies(&self.world).iter() {
+                let point = match matrix.transform_point(vertex.get_rel_pos()) {
+                    Ok((point, _)) => point,
+                    Err(_) => continue
+                };
+                bounds = Some(match bounds {
+                    Some((low, high)) => (
+                        Vector3D::new(low.x.min(point.x), low.y.min(point.y), low.z.min(point.z)),
+                        Vector3D::new(high.x.max(point.x), high.y.max(point.y), high.z.max(point.z))
+                    ),
+                    None => (point, point)
+                });
+            }
+            let overlaps = bounds.is_some_and(|(low, high)| {
+                low.x <= max.x && high.x >= min.x
+                    && low.y <= max.y && high.y >= min.y
+                    && low.z <= max.z && high.z >= min.z
+            });
+            if overlaps {
+                found.push(*handle);
+            }
+        }
+        found
+    }
+
+    // writes the objects, entities, camera and background color to a json
+    // file, see the scene module for what is saved of each
+    pub fn save_scene(&self, path: &str) -> Result<(), SceneError> {
+        let world = &self.world;
+        let objects = &self.objects;
+        let is_object = |handle: &ObjectHandle| objects.get(handle).is_some_and(|entry| entry.object.is_some());
+        let text = scene::write_scene(
+            self.window.get_background_color(),
+            self.get_camera(),
+            self.objects()
+                .map(|(handle, object)| (object, SceneEntity::from_world(world, handle))),
+            world.entities()
+                .filter(|entity| !is_object(entity))
+                .map(|entity| SceneEntity::from_world(world, entity))
+                .filter(|entity| !entity.is_empty())
+        );
+        std::fs::write(path, text)?;
+        Ok(())
+    }
+
+    // replaces every object and entity with those in the scene, returning the
+    // handles of the objects followed by those of the entities, the camera and
+    // background color are only changed if the scene has them
+    pub fn load_scene(&mut self, path: &str) -> Result<Vec<ObjectHandle>, SceneError> {
+        let loaded = scene::read_scene(&std::fs::read_to_string(path)?)?;
+        if loaded.version > scene::SCENE_VERSION {
+            eprintln!(
+                "Scene was saved by a newer version ({} > {}), anything not understood is skipped",
+                loaded.version,
+                scene::SCENE_VERSION
+            );
+        }
+        let entities: Vec<Entity> = self.world.entities().collect();
+        for entity in entities.iter() {
+            if self.remove_object(entity).is_none() {
+                // entities drawn from their components are removed from the
+                // draw list next frame
+                self.world.despawn(*entity);
+            }
+        }
+        if let Some(camera) = loaded.camera {
+            *self.get_camera_mut() = camera;
+        }
+        if let Some(background_color) = loaded.background_color {
+            self.window.set_background_color(background_color);
+        }
+        self.paint_background();
+        let mut handles = Vec::with_capacity(loaded.objects.len() + loaded.entities.len());
+        for (object, components) in loaded.objects {
+            let handle = self.add_object(object);
+            components.insert_into(&mut self.world, handle);
+            handles.push(handle);
+        }
+        for components in loaded.entities {
+            let entity = self.world.spawn();
+            components.insert_into(&mut self.world, entity);
+            handles.push(entity);
+        }
+        Ok(handles)
     }
 }
 //#endregion
 
+// projects a group of entries, returning the handles of any that failed
+fn project_entries(entries: &mut [&mut ObjectEntry], renderer: &Renderer, world: &World, buffer_size: (i32, i32), interpolation: Option<f32>) -> Vec<(ObjectHandle, MatrixError)> {
+    let mut errors = Vec::new();
+    for entry in entries.iter_mut() {
+        if let Err(error) = entry.project(renderer, world, buffer_size, interpolation) {
+            errors.push((entry.handle, error));
+        }
+    }
+    errors
+}
+
 //#region SimulationBuilder
 pub struct SimulationBuilder {
     restrict_frame_rate: bool,
-    use_object_clearing: bool,
+    use_dirty_rectangles: bool,
     target_frame_rate: u16,
     render_mode: RenderMode,
     width: u32,
     height: u32,
     origin: OriginPosition,
+    thread_count: usize,
+    tick_rate: Option<f32>,
+    max_catch_up_steps: u32,
+    internal_resolution: Option<(i32, i32)>,
+    scaling_mode: ScalingMode,
+    seed: Option<u64>,
 }
 
 impl SimulationBuilder {
     pub fn new() -> Self {
         Self {
             restrict_frame_rate: false,
-            use_object_clearing: false,
+            use_dirty_rectangles: false,
             target_frame_rate: 60,
             render_mode: RenderMode::R2D,
             width: 512,
             height: 512,
-            origin: OriginPosition::MIDDLEMIDDLE
+            origin: OriginPosition::MIDDLEMIDDLE,
+            thread_count: std::thread::available_parallelism()
+                .map(|count| count.get())
+                .unwrap_or(1),
+            tick_rate: None,
+            max_catch_up_steps: time::DEFAULT_MAX_STEPS,
+            internal_resolution: None,
+            scaling_mode: ScalingMode::Letterbox,
+            seed: None
         }
     }
 
@@ -298,23 +1441,33 @@ impl SimulationBuilder {
         self
     }
 
-    pub fn use_object_clearing(mut self) -> Self {
-        self.ref_use_object_clearing();
+    // only redraw and present the parts of the screen that changed each frame
+    pub fn use_dirty_rectangles(mut self) -> Self {
+        self.ref_use_dirty_rectangles();
         self
     }
 
-    pub fn ref_use_object_clearing(&mut self) -> &mut Self {
-        self.use_object_clearing = true;
+    pub fn ref_use_dirty_rectangles(&mut self) -> &mut Self {
+        self.use_dirty_rectangles = true;
         self
     }
 
+    // kept for compatibility, object clearing is now done with dirty rectangles
+    pub fn use_object_clearing(self) -> Self {
+        self.use_dirty_rectangles()
+    }
+
+    pub fn ref_use_object_clearing(&mut self) -> &mut Self {
+        self.ref_use_dirty_rectangles()
+    }
+
     pub fn use_background_fill(mut self) -> Self {
         self.ref_use_background_fill();
         self
     }
 
     pub fn ref_use_background_fill(&mut self) -> &mut Self {
-        self.use_object_clearing = false;
+        self.use_dirty_rectangles = false;
         self
     }
 
@@ -328,6 +1481,84 @@ impl SimulationBuilder {
         self
     }
 
+    // number of threads used to rasterize each frame, defaults to the
+    // number of cores available
+    pub fn set_thread_count(mut self, thread_count: usize) -> Self {
+        self.ref_set_thread_count(thread_count);
+        self
+    }
+
+    pub fn ref_set_thread_count(&mut self, thread_count: usize) -> &mut Self {
+        self.thread_count = thread_count;
+        self
+    }
+
+    // draws every frame at this size and scales it to fit the window
+    pub fn set_internal_resolution(mut self, width: i32, height: i32) -> Self {
+        self.ref_set_internal_resolution(width, height);
+        self
+    }
+
+    pub fn ref_set_internal_resolution(&mut self, width: i32, height: i32) -> &mut Self {
+        self.internal_resolution = Some((width.max(1), height.max(1)));
+        self
+    }
+
+    // how an internal resolution is fitted to the window, letterboxed by default
+    pub fn set_scaling_mode(mut self, mode: ScalingMode) -> Self {
+        self.ref_set_scaling_mode(mode);
+        self
+    }
+
+    pub fn ref_set_scaling_mode(&mut self, mode: ScalingMode) -> &mut Self {
+        self.scaling_mode = mode;
+        self
+    }
+
+    // runs the fixed update callbacks tick_rate times a second, drawing
+    // objects interpolated between the last two ticks
+    pub fn use_fixed_timestep(mut self, tick_rate: f32) -> Self {
+        self.ref_use_fixed_timestep(tick_rate);
+        self
+    }
+
+    pub fn ref_use_fixed_timestep(&mut self, tick_rate: f32) -> &mut Self {
+        self.tick_rate = Some(tick_rate);
+        self
+    }
+
+    pub fn use_variable_timestep(mut self) -> Self {
+        self.ref_use_variable_timestep();
+        self
+    }
+
+    pub fn ref_use_variable_timestep(&mut self) -> &mut Self {
+        self.tick_rate = None;
+        self
+    }
+
+    // most fixed updates run in a single frame when catching up
+    pub fn set_max_catch_up_steps(mut self, max_steps: u32) -> Self {
+        self.ref_set_max_catch_up_steps(max_steps);
+        self
+    }
+
+    pub fn ref_set_max_catch_up_steps(&mut self, max_steps: u32) -> &mut Self {
+        self.max_catch_up_steps = max_steps;
+        self
+    }
+
+    // seeds the simulator's random number generator, from the clock by default
+    pub fn set_seed(mut self, seed: u64) -> Self {
+        self.ref_set_seed(seed);
+        self
+    }
+
+    pub fn ref_set_seed(&mut self, seed: u64) -> &mut Self {
+        self.seed = Some(seed);
+        self
+    }
+
     pub fn build(self, window_builder: WindowBuilder) -> Simulator {
         self.ref_build(window_builder)
     }
@@ -335,15 +1566,254 @@ impl SimulationBuilder {
     // consume the windowbuilder used for constructing the window
     pub fn ref_build(&self, window_builder: WindowBuilder) -> Simulator {
         Simulator {
-            objects: HashMap::new(),
+            objects: BTreeMap::new(),
+            next_sequence: 0,
+            world: World::new(),
+            systems: Vec::new(),
+            enabled_layers: [true; 3],
             renderer: Renderer::new(self.render_mode, self.origin),
-            use_object_clearing: self.use_object_clearing,
+            rasterizer: TileRasterizer::new(self.thread_count),
+            use_dirty_rectangles: self.use_dirty_rectangles,
+            dirty_regions: DirtyRegions::new(),
+            last_buffer_size: (0, 0),
+            full_redraw: true,
+            internal_resolution: self.internal_resolution,
+            internal_buffer: Vec::new(),
+            scaling_mode: self.scaling_mode,
             time: Time::new(),
+            input: Input::new(),
+            injected_input: Vec::new(),
+            events: Vec::new(),
+            incoming_events: Vec::new(),
+            subscribers: Vec::new(),
+            dispatching: Vec::new(),
+            unsubscribed: Vec::new(),
+            next_subscription_id: 0,
+            window_closed: false,
+            fixed_timestep: self.tick_rate.map(|tick_rate| {
+                let mut timestep = FixedTimestep::new(tick_rate);
+                timestep.set_max_steps(self.max_catch_up_steps);
+                timestep
+            }),
+            fixed_updates: Vec::new(),
             window: window_builder.build(),
             restrict_frame_rate: self.restrict_frame_rate,
-            frame_delay: Duration::from_nanos(1_000_000_000 / self.target_frame_rate as u64),
-            last_frame_start: Instant::now()
+            frame_pacer: FramePacer::new(self.target_frame_rate),
+            last_frame_start: Instant::now(),
+            recorder: None,
+            recording_frame_rate: None,
+            random: self.seed.map_or_else(Random::from_time, Random::new),
+            session_recorder: None,
+            replay: None,
+            replay_frame: None,
+            resolution_before_replay: None
         }
     }
 }
-//#endregion
\ No newline at end of file
+//#endregion
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use objects::{Circle, Cube};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    fn simulator() -> Simulator {
+        SimulationBuilder::new().build(WindowBuilder::new().set_size(64, 64).use_headless())
+    }
+
+    type Received = Rc<RefCell<Vec<Event>>>;
+
+    fn recorder() -> (Received, impl FnMut(&mut Simulator, &Event)) {
+        let received = Rc::new(RefCell::new(Vec::new()));
+        let sink = Rc::clone(&received);
+        (received, move |_: &mut Simulator, event: &Event| sink.borrow_mut().push(*event))
+    }
+
+    #[test]
+    fn delivers_object_events_to_subscribers() {
+        let mut simulator = simulator();
+        let (received, callback) = recorder();
+        simulator.subscribe(callback);
+        let handle = simulator.add_object(Box::new(Cube::new()));
+        simulator.update().unwrap();
+        assert_eq!(*received.borrow(), vec![Event::ObjectAdded(handle)]);
+
+        simulator.remove_object(&handle);
+        simulator.update().unwrap();
+        assert_eq!(*received.borrow(), vec![Event::ObjectAdded(handle), Event::ObjectRemoved(handle)]);
+    }
+
+    #[test]
+    fn polls_the_events_delivered_this_frame() {
+        let mut simulator = simulator();
+        let handle = simulator.add_object(Box::new(Cube::new()));
+        assert!(simulator.get_events().is_empty());
+        simulator.update().unwrap();
+        assert_eq!(simulator.get_events(), &[Event::ObjectAdded(handle)]);
+        assert_eq!(simulator.poll_events().collect::<Vec<_>>(), vec![Event::ObjectAdded(handle)]);
+        assert!(simulator.get_events().is_empty());
+        // replaced by the next frame's events
+        simulator.update().unwrap();
+        assert!(simulator.poll_events().next().is_none());
+    }
+
+    #[test]
+    fn unsubscribes_outside_of_dispatch() {
+        let mut simulator = simulator();
+        let (received, callback) = recorder();
+        let id = simulator.subscribe(callback);
+        assert!(simulator.unsubscribe(id));
+        assert!(!simulator.unsubscribe(id));
+        simulator.add_object(Box::new(Cube::new()));
+        simulator.update().unwrap();
+        assert!(received.borrow().is_empty());
+    }
+
+    #[test]
+    fn unsubscribes_from_within_callbacks() {
+        let mut simulator = simulator();
+        let (received, callback) = recorder();
+        let other = simulator.subscribe(callback);
+        let results = Rc::new(RefCell::new(Vec::new()));
+        let sink = Rc::clone(&results);
+        let own = Rc::new(RefCell::new(None));
+        let own_id = Rc::clone(&own);
+        let id = simulator.subscribe(move |simulator, _| {
+            let own = own_id.borrow().unwrap();
+            sink.borrow_mut().push((simulator.unsubscribe(own), simulator.unsubscribe(other)));
+        });
+        *own.borrow_mut() = Some(id);
+
+        // both are removed on the first event, neither sees the second
+        simulator.add_object(Box::new(Cube::new()));
+        simulator.add_object(Box::new(Cube::new()));
+        simulator.update().unwrap();
+        assert_eq!(*results.borrow(), vec![(true, true)]);
+        assert_eq!(received.borrow().len(), 1);
+
+        simulator.add_object(Box::new(Cube::new()));
+        simulator.update().unwrap();
+        assert_eq!(results.borrow().len(), 1);
+        assert_eq!(received.borrow().len(), 1);
+        assert!(!simulator.unsubscribe(id));
+        assert!(!simulator.unsubscribe(other));
+    }
+
+    #[test]
+    fn callbacks_see_the_frames_events() {
+        let mut simulator = simulator();
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let sink = Rc::clone(&seen);
+        simulator.subscribe(move |simulator, _| sink.borrow_mut().push(simulator.get_events().len()));
+        simulator.add_object(Box::new(Cube::new()));
+        simulator.add_object(Box::new(Cube::new()));
+        simulator.update().unwrap();
+        assert_eq!(*seen.borrow(), vec![2, 2]);
+        assert_eq!(simulator.get_events().len(), 2);
+    }
+
+    #[test]
+    fn subscriptions_made_while_dispatching_start_next_frame() {
+        let mut simulator = simulator();
+        let (received, callback) = recorder();
+        let callback = RefCell::new(Some(callback));
+        simulator.subscribe(move |simulator, _| {
+            if let Some(callback) = callback.borrow_mut().take() {
+                simulator.subscribe(callback);
+            }
+        });
+        let first = simulator.add_object(Box::new(Cube::new()));
+        simulator.update().unwrap();
+        assert!(received.borrow().is_empty());
+        simulator.remove_object(&first);
+        simulator.update().unwrap();
+        assert_eq!(*received.borrow(), vec![Event::ObjectRemoved(first)]);
+    }
+
+    fn drawn(simulator: &Simulator) -> Vec<ObjectHandle> {
+        simulator.get_draw_list().into_iter().map(|(_, handle)| handle).collect()
+    }
+
+    #[test]
+    fn draws_by_layer_then_z_order_then_insertion() {
+        let mut simulator = simulator();
+        let first = simulator.add_object(Box::new(Cube::new()));
+        let second = simulator.add_object(Box::new(Cube::new()));
+        let third = simulator.add_object(Box::new(Cube::new()));
+        simulator.remove_object(&first);
+        // takes the first slot, but was added last
+        let fourth = simulator.add_object(Box::new(Cube::new()));
+        assert_eq!(fourth.get_index(), first.get_index());
+        simulator.update().unwrap();
+        assert_eq!(drawn(&simulator), vec![second, third, fourth]);
+
+        simulator.set_z_order(&second, 1).set_layer(&fourth, Layer::Background);
+        assert_eq!(simulator.get_z_order(&second), 1);
+        assert_eq!(simulator.get_layer(&fourth), Layer::Background);
+        assert_eq!(simulator.get_layer(&third), Layer::World);
+        simulator.update().unwrap();
+        assert_eq!(drawn(&simulator), vec![fourth, third, second]);
+
+        simulator.set_layer(&third, Layer::Overlay).set_z_order(&fourth, -5).set_z_order(&second, -1);
+        simulator.update().unwrap();
+        assert_eq!(drawn(&simulator), vec![fourth, second, third]);
+    }
+
+    #[test]
+    fn skips_hidden_objects_and_disabled_layers() {
+        let mut simulator = simulator();
+        let first = simulator.add_object(Box::new(Cube::new()));
+        let second = simulator.add_object(Box::new(Cube::new()));
+        simulator.set_layer(&second, Layer::Overlay).set_visible(&first, false);
+        assert!(!simulator.is_visible(&first));
+        simulator.update().unwrap();
+        assert_eq!(drawn(&simulator), vec![second]);
+
+        simulator.set_layer_enabled(Layer::Overlay, false);
+        assert!(!simulator.is_layer_enabled(Layer::Overlay));
+        simulator.update().unwrap();
+        assert!(drawn(&simulator).is_empty());
+        // hidden objects are still registered
+        assert!(simulator.contains_object(&first));
+
+        simulator.set_visible(&first, true).set_layer_enabled(Layer::Overlay, true);
+        assert!(simulator.is_visible(&first));
+        simulator.update().unwrap();
+        assert_eq!(drawn(&simulator), vec![first, second]);
+        simulator.remove_object(&first);
+        assert!(!simulator.is_visible(&first));
+    }
+
+    #[test]
+    fn converts_mouse_positions_to_the_frame() {
+        let mut simulator = simulator();
+        // drawn at twice the size, with bars above and below
+        simulator.set_internal_resolution(32, 16);
+        simulator.inject_input(InputEvent::MouseMoved { x: 10, y: 20 });
+        simulator.update().unwrap();
+        assert_eq!(simulator.get_input().get_mouse_position(), (5, 2));
+        assert_eq!(simulator.get_events(), &[Event::MouseMoved { x: 5, y: 2 }]);
+
+        // moves over the bars leave the mouse where it was
+        simulator.inject_input(InputEvent::MouseMoved { x: 10, y: 5 });
+        simulator.inject_input(InputEvent::MouseMoved { x: 20, y: 24 });
+        simulator.update().unwrap();
+        assert_eq!(simulator.get_input().get_mouse_position(), (10, 4));
+        assert_eq!(simulator.get_input().get_mouse_delta(), (5, 2));
+    }
+
+    #[test]
+    fn gets_objects_as_their_concrete_type() {
+        let mut simulator = simulator();
+        let circle = Circle::new(Color::BLACK, Color::WHITE).register(&mut simulator);
+        let cube = simulator.add_object(Box::new(Cube::new()));
+        simulator.get_object::<Circle>(&circle).unwrap().ref_set_radius(2.5);
+        assert_eq!(simulator.get_object::<Circle>(&circle).unwrap().get_radius(), 2.5);
+        // the wrong type, or a removed object, gives none
+        assert!(simulator.get_object::<Cube>(&circle).is_none());
+        assert!(simulator.get_object::<Circle>(&cube).is_none());
+        simulator.remove_object(&circle);
+        assert!(simulator.get_object::<Circle>(&circle).is_none());
+    }
+}
@@ -1,16 +1,37 @@
 use super::renderer::{Transform};
 use super::renderer::linearalgebra::{Vector3D};
-use super::{Simulator, Color};
+use super::{Simulator, Color, ObjectHandle};
 
-// used for ID generation
-use std::sync::atomic::{AtomicUsize, Ordering};
-
-static OBJECTID: AtomicUsize = AtomicUsize::new(0);
+use std::any::Any;
 
 //#region Object and Vertex
-pub trait Object {
-    fn get_id(&self) -> usize;
-    fn register(self, registrar: &mut Simulator) -> usize;
+// lets a registered object be turned back into its concrete type,
+// implemented for every type so objects don't have to
+pub trait AsAny {
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: Any> AsAny for T {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+// objects are shared with the projection worker threads
+pub trait Object: AsAny + Send + Sync + 'static {
+    // identifies the kind of object in saved scenes, the type's name without
+    // its module path by default, override it to keep saved scenes loading
+    // if the type is renamed
+    fn get_type_name(&self) -> &'static str {
+        let name = std::any::type_name::<Self>();
+        let path = name.split('<').next().unwrap_or(name);
+        path.rsplit("::").next().unwrap_or(path)
+    }
+    fn register(self, registrar: &mut Simulator) -> ObjectHandle;
     fn transform(&self) -> &Transform;
     fn transform_mut(&mut self) -> &mut Transform;
     fn get_verticies(&self) -> &Vec<Vertex>;
@@ -23,12 +44,16 @@ pub trait Object {
 }
 
 impl dyn Object {
-    fn new_id() -> usize {
-        let id = OBJECTID.fetch_add(1, Ordering::SeqCst);
-        if id == usize::MAX {
-            OBJECTID.store(0, Ordering::SeqCst);
-        }
-        id
+    pub fn is<T: Object>(&self) -> bool {
+        self.as_any().is::<T>()
+    }
+
+    pub fn downcast_ref<T: Object>(&self) -> Option<&T> {
+        self.as_any().downcast_ref::<T>()
+    }
+
+    pub fn downcast_mut<T: Object>(&mut self) -> Option<&mut T> {
+        self.as_any_mut().downcast_mut::<T>()
     }
 }
 
@@ -75,12 +100,11 @@ pub struct Cube {
     verticies: Vec<Vertex>,
     frame_color: Color,
     fill_color: Color,
-    id: usize,
 }
 
 impl Object for Cube {
-    fn get_id(&self) -> usize {
-        self.id
+    fn get_type_name(&self) -> &'static str {
+        "Cube"
     }
     fn transform(&self) -> &Transform {
         &self.transform
@@ -103,7 +127,7 @@ impl Object for Cube {
     fn set_fill_color(&mut self, color: Color) {
         self.fill_color = color;
     }
-    fn register(self, registrar: &mut Simulator) -> usize {
+    fn register(self, registrar: &mut Simulator) -> ObjectHandle {
         registrar.add_object(Box::new(self))
     }
     fn get_cached_transform(&self) -> &Transform {
@@ -117,7 +141,6 @@ impl Object for Cube {
 impl Cube {
     pub fn new() -> Self {
         Self {
-            id: Object::new_id(),
             transform: Transform::new(),
             cached_transform: Transform::new(),
             verticies: vec![
@@ -181,15 +204,14 @@ pub struct Spot {
     transform: Transform,
     cached_transform: Transform,
     vertex: Vec<Vertex>,
-    color: Color,
-    id: usize
+    color: Color
 }
 
 impl Object for Spot {
-    fn get_id(&self) -> usize {
-        self.id
+    fn get_type_name(&self) -> &'static str {
+        "Spot"
     }
-    fn register(self, registrar: &mut Simulator) -> usize {
+    fn register(self, registrar: &mut Simulator) -> ObjectHandle {
         registrar.add_object(Box::new(self))
     }
     fn transform(&self) -> &Transform {
@@ -233,7 +255,6 @@ impl Spot {
                 }
             },
             color,
-            id: Object::new_id(),
         }
     }
 
@@ -261,15 +282,14 @@ pub struct Circle {
     frame_color: Color,
     fill_color: Color,
     radius: f32,
-    verticies: Vec<Vertex>,
-    id: usize
+    verticies: Vec<Vertex>
 }
 
 impl Object for Circle {
-    fn get_id(&self) -> usize {
-        self.id
+    fn get_type_name(&self) -> &'static str {
+        "Circle"
     }
-    fn register(self, registrar: &mut Simulator) -> usize {
+    fn register(self, registrar: &mut Simulator) -> ObjectHandle {
         registrar.add_object(Box::new(self))
     }
     fn transform(&self) -> &Transform {
@@ -311,15 +331,23 @@ impl Circle {
         Self {
             transform: Transform::new(),
             cached_transform: Transform::new(),
-            frame_color: frame_color,
-            fill_color: fill_color,
+            frame_color,
+            fill_color,
             radius: 1.0,
             verticies: Vec::new(),
-            id: Object::new_id()
         }
     }
 
+    pub fn get_radius(&self) -> f32 {
+        self.radius
+    }
+
     pub fn set_radius(mut self, radius: f32) -> Self {
+        self.ref_set_radius(radius);
+        self
+    }
+
+    pub fn ref_set_radius(&mut self, radius: f32) -> &mut Self {
         self.radius = radius;
         self
     }
@@ -339,4 +367,135 @@ impl Circle {
         self
     }
 }
-//#endregion
\ No newline at end of file
+//#endregion
+
+//#region Mesh
+// an object made of any vertices, used for objects loaded from a scene
+// whose type isn't known
+pub struct Mesh {
+    transform: Transform,
+    cached_transform: Transform,
+    verticies: Vec<Vertex>,
+    frame_color: Color,
+    fill_color: Color
+}
+
+impl Object for Mesh {
+    fn get_type_name(&self) -> &'static str {
+        "Mesh"
+    }
+    fn register(self, registrar: &mut Simulator) -> ObjectHandle {
+        registrar.add_object(Box::new(self))
+    }
+    fn transform(&self) -> &Transform {
+        &self.transform
+    }
+    fn transform_mut(&mut self) -> &mut Transform {
+        &mut self.transform
+    }
+    fn get_verticies(&self) -> &Vec<Vertex> {
+        &self.verticies
+    }
+    fn get_frame_color(&self) -> Color {
+        self.frame_color
+    }
+    fn set_frame_color(&mut self, color: Color) {
+        self.frame_color = color;
+    }
+    fn get_fill_color(&self) -> Color {
+        self.fill_color
+    }
+    fn set_fill_color(&mut self, color: Color) {
+        self.fill_color = color;
+    }
+    fn get_cached_transform(&self) -> &Transform {
+        &self.cached_transform
+    }
+    fn cache_transform(&mut self) {
+        self.cached_transform = self.transform;
+    }
+}
+
+impl Mesh {
+    // connections are indices into the given vertices
+    pub fn new(verticies: Vec<Vertex>, frame_color: Color) -> Self {
+        Self {
+            transform: Transform::new(),
+            cached_transform: Transform::new(),
+            verticies,
+            frame_color,
+            fill_color: frame_color,
+        }
+    }
+
+    pub fn set_position(mut self, x: f32, y: f32, z: f32) -> Self {
+        self.transform.set_position(x, y, z);
+        self
+    }
+
+    pub fn set_rotation(mut self, x: f32, y: f32, z: f32) -> Self {
+        self.transform.set_rotation(x, y, z);
+        self
+    }
+
+    pub fn set_scale(mut self, x: f32, y: f32, z: f32) -> Self {
+        self.transform.set_scale(x, y, z);
+        self
+    }
+}
+//#endregion
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // only what's needed to be an object, to check the defaults
+    struct Marker {
+        transform: Transform,
+        verticies: Vec<Vertex>
+    }
+
+    impl Object for Marker {
+        fn register(self, registrar: &mut Simulator) -> ObjectHandle {
+            registrar.add_object(Box::new(self))
+        }
+        fn transform(&self) -> &Transform {
+            &self.transform
+        }
+        fn transform_mut(&mut self) -> &mut Transform {
+            &mut self.transform
+        }
+        fn get_verticies(&self) -> &Vec<Vertex> {
+            &self.verticies
+        }
+        fn get_frame_color(&self) -> Color {
+            Color::BLACK
+        }
+        fn set_frame_color(&mut self, _color: Color) {}
+        fn get_fill_color(&self) -> Color {
+            Color::BLACK
+        }
+        fn set_fill_color(&mut self, _color: Color) {}
+        fn get_cached_transform(&self) -> &Transform {
+            &self.transform
+        }
+        fn cache_transform(&mut self) {}
+    }
+
+    #[test]
+    fn names_types_without_their_path() {
+        let marker: Box<dyn Object> = Box::new(Marker { transform: Transform::new(), verticies: Vec::new() });
+        assert_eq!(marker.get_type_name(), "Marker");
+        assert_eq!(Circle::new(Color::BLACK, Color::BLACK).get_type_name(), "Circle");
+    }
+
+    #[test]
+    fn downcasts_to_the_registered_type() {
+        let mut object: Box<dyn Object> = Box::new(Circle::new(Color::BLACK, Color::BLACK));
+        assert!(object.is::<Circle>());
+        assert!(!object.is::<Cube>());
+        object.downcast_mut::<Circle>().unwrap().ref_set_radius(4.0);
+        assert_eq!(object.downcast_ref::<Circle>().map(Circle::get_radius), Some(4.0));
+        assert!(object.downcast_ref::<Cube>().is_none());
+    }
+}
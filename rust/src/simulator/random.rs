@@ -0,0 +1,122 @@
+// a small seedable random number generator (splitmix64), the same seed
+// always gives the same sequence on every platform so runs can be replayed
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const INCREMENT: u64 = 0x9E37_79B9_7F4A_7C15;
+
+#[derive(Clone, Debug)]
+pub struct Random {
+    state: u64
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    // seeded from the system clock
+    pub fn from_time() -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_nanos() as u64);
+        Self::new(nanos)
+    }
+
+    // the state fully describes the generator, restoring it with set_state
+    // repeats everything generated since
+    pub fn get_state(&self) -> u64 {
+        self.state
+    }
+
+    pub fn set_state(&mut self, state: u64) {
+        self.state = state;
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(INCREMENT);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    // uniform in [0, 1)
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    // uniform in [0, 1)
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    pub fn next_bool(&mut self) -> bool {
+        self.next_u64() >> 63 == 1
+    }
+
+    // uniform in [min, max)
+    pub fn range_f32(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.next_f32()
+    }
+
+    // uniform in [min, max), min if the range is empty
+    pub fn range_i32(&mut self, min: i32, max: i32) -> i32 {
+        if max <= min {
+            return min;
+        }
+        let span = (max as i64 - min as i64) as u64;
+        (min as i64 + (self.next_u64() % span) as i64) as i32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_the_reference_sequence() {
+        // first splitmix64 outputs for a seed of 0
+        let mut random = Random::new(0);
+        assert_eq!(random.next_u64(), 0xE220_A839_7B1D_CDAF);
+        assert_eq!(random.next_u64(), 0x6E78_9E6A_A1B9_65F4);
+    }
+
+    #[test]
+    fn same_seed_gives_the_same_sequence() {
+        let mut a = Random::new(1234);
+        let mut b = Random::new(1234);
+        for _ in 0..100 {
+            assert_eq!(a.next_u64(), b.next_u64());
+        }
+        let state = a.get_state();
+        let first: Vec<u32> = (0..10).map(|_| a.next_u32()).collect();
+        b.set_state(state);
+        let second: Vec<u32> = (0..10).map(|_| b.next_u32()).collect();
+        assert_eq!(first, second);
+        assert_ne!(Random::new(1).next_u64(), Random::new(2).next_u64());
+    }
+
+    #[test]
+    fn stays_within_ranges() {
+        let mut random = Random::new(42);
+        for _ in 0..1000 {
+            let value = random.next_f32();
+            assert!((0.0..1.0).contains(&value));
+            let value = random.next_f64();
+            assert!((0.0..1.0).contains(&value));
+            let value = random.range_f32(-2.0, 3.0);
+            assert!((-2.0..3.0).contains(&value));
+            let value = random.range_i32(i32::MIN, i32::MAX);
+            assert!(value < i32::MAX);
+            let value = random.range_i32(-3, 4);
+            assert!((-3..4).contains(&value));
+        }
+        assert_eq!(random.range_i32(5, 5), 5);
+        assert_eq!(random.range_i32(5, -5), 5);
+    }
+}
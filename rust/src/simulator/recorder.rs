@@ -0,0 +1,593 @@
+// records presented frames to disk, either as a numbered image sequence, an
+// animated gif or a y4m stream that can be piped into ffmpeg
+//
+// every frame is recorded at the size of the first, later frames of a
+// different size are scaled to fit
+
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use super::dirty::Rect;
+use super::scaling;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RecordingFormat {
+    // numbered binary ppm images in a directory
+    Ppm,
+    // numbered png images in a directory
+    Png,
+    // a single animated gif, each frame reduced to 256 colors
+    Gif,
+    // a single yuv4mpeg2 stream
+    Y4m
+}
+
+impl RecordingFormat {
+    // sequences store no frame rate, gifs and y4m streams do
+    pub fn is_sequence(&self) -> bool {
+        match self {
+            RecordingFormat::Ppm | RecordingFormat::Png => true,
+            RecordingFormat::Gif | RecordingFormat::Y4m => false
+        }
+    }
+
+    fn get_extension(&self) -> &'static str {
+        match self {
+            RecordingFormat::Ppm => "ppm",
+            RecordingFormat::Png => "png",
+            RecordingFormat::Gif => "gif",
+            RecordingFormat::Y4m => "y4m"
+        }
+    }
+}
+
+pub struct Recorder {
+    format: RecordingFormat,
+    // the directory for sequences, otherwise the file
+    path: PathBuf,
+    frame_rate: u32,
+    size: Option<(i32, i32)>,
+    frame_count: u64,
+    // open for gif and y4m once the first frame has been written
+    writer: Option<BufWriter<File>>,
+    // frames of a different size are scaled into this
+    scaled_frame: Vec<u32>,
+}
+
+impl Recorder {
+    // frame rate is only used by the formats that store one, sequences
+    // always number every frame
+    pub fn new(format: RecordingFormat, path: &Path, frame_rate: u32) -> io::Result<Self> {
+        if format.is_sequence() {
+            fs::create_dir_all(path)?;
+        }
+        else if let Some(parent) = path.parent() {
+            if !parent.as_os_str().is_empty() {
+                fs::create_dir_all(parent)?;
+            }
+        }
+        Ok(Self {
+            format,
+            path: path.to_path_buf(),
+            frame_rate: frame_rate.max(1),
+            size: None,
+            frame_count: 0,
+            writer: None,
+            scaled_frame: Vec::new(),
+        })
+    }
+
+    pub fn get_format(&self) -> RecordingFormat {
+        self.format
+    }
+
+    pub fn get_path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn get_frame_rate(&self) -> u32 {
+        self.frame_rate
+    }
+
+    pub fn get_frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    // the path of a frame in a sequence
+    pub fn get_frame_path(&self, frame: u64) -> PathBuf {
+        self.path.join(format!("frame_{:06}.{}", frame, self.format.get_extension()))
+    }
+
+    pub fn record_frame(&mut self, frame: &[u32], size: (i32, i32)) -> io::Result<()> {
+        if size.0 <= 0 || size.1 <= 0 {
+            return Ok(());
+        }
+        let recording_size = *self.size.get_or_insert(size);
+        let frame = if size != recording_size {
+            self.scaled_frame.clear();
+            self.scaled_frame.resize((recording_size.0 * recording_size.1) as usize, 0);
+            scaling::blit_scaled(
+                frame,
+                size,
+                &mut self.scaled_frame,
+                recording_size,
+                Rect::new(0, 0, recording_size.0, recording_size.1)
+            );
+            &self.scaled_frame[..]
+        }
+        else {
+            frame
+        };
+        let (width, height) = (recording_size.0 as usize, recording_size.1 as usize);
+        match self.format {
+            RecordingFormat::Ppm => {
+                let mut writer = BufWriter::new(File::create(self.get_frame_path(self.frame_count))?);
+                write!(writer, "P6\n{} {}\n255\n", width, height)?;
+                writer.write_all(&to_rgb(frame))?;
+                writer.flush()?;
+            },
+            RecordingFormat::Png => {
+                let mut writer = BufWriter::new(File::create(self.get_frame_path(self.frame_count))?);
+                write_png(&mut writer, frame, width, height)?;
+                writer.flush()?;
+            },
+            RecordingFormat::Gif => {
+                if self.writer.is_none() {
+                    let mut writer = BufWriter::new(File::create(&self.path)?);
+                    write_gif_header(&mut writer, width, height)?;
+                    self.writer = Some(writer);
+                }
+                // delays are in hundredths of a second so are spread to keep
+                // the total in step with the frame rate
+                let frame_rate = self.frame_rate as u64;
+                let delay = ((self.frame_count + 1) * 100 + frame_rate / 2) / frame_rate
+                    - (self.frame_count * 100 + frame_rate / 2) / frame_rate;
+                if let Some(writer) = &mut self.writer {
+                    write_gif_frame(writer, frame, width, height, delay as u16)?;
+                }
+            },
+            RecordingFormat::Y4m => {
+                if self.writer.is_none() {
+                    let mut writer = BufWriter::new(File::create(&self.path)?);
+                    writeln!(writer, "YUV4MPEG2 W{} H{} F{}:1 Ip A1:1 C420jpeg", width, height, self.frame_rate)?;
+                    self.writer = Some(writer);
+                }
+                if let Some(writer) = &mut self.writer {
+                    writer.write_all(b"FRAME\n")?;
+                    writer.write_all(&to_yuv420(frame, width, height))?;
+                }
+            }
+        }
+        self.frame_count += 1;
+        Ok(())
+    }
+
+    // finishes and closes the file, returns how many frames were recorded
+    pub fn finish(mut self) -> io::Result<u64> {
+        if let Some(mut writer) = self.writer.take() {
+            if self.format == RecordingFormat::Gif {
+                // trailer
+                writer.write_all(&[0x3B])?;
+            }
+            writer.flush()?;
+        }
+        Ok(self.frame_count)
+    }
+}
+
+fn to_rgb(frame: &[u32]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(frame.len() * 3);
+    for pixel in frame.iter() {
+        bytes.extend_from_slice(&[(pixel >> 16) as u8, (pixel >> 8) as u8, *pixel as u8]);
+    }
+    bytes
+}
+
+//#region PNG
+fn crc32(bytes: &[u8], crc: u32) -> u32 {
+    let mut crc = !crc;
+    for byte in bytes.iter() {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+fn write_png_chunk<W: Write>(writer: &mut W, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    writer.write_all(&(data.len() as u32).to_be_bytes())?;
+    writer.write_all(kind)?;
+    writer.write_all(data)?;
+    writer.write_all(&crc32(data, crc32(kind, 0)).to_be_bytes())
+}
+
+// the image data is stored without compression to avoid needing a deflate
+// implementation, use ppm or y4m where size matters
+fn write_png<W: Write>(writer: &mut W, frame: &[u32], width: usize, height: usize) -> io::Result<()> {
+    writer.write_all(b"\x89PNG\r\n\x1a\n")?;
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&(width as u32).to_be_bytes());
+    header.extend_from_slice(&(height as u32).to_be_bytes());
+    // 8 bit rgb, default compression, filtering and no interlacing
+    header.extend_from_slice(&[8, 2, 0, 0, 0]);
+    write_png_chunk(writer, b"IHDR", &header)?;
+
+    // every row starts with its filter type, none
+    let mut scanlines = Vec::with_capacity(height * (width * 3 + 1));
+    for row in frame.chunks(width) {
+        scanlines.push(0);
+        scanlines.extend_from_slice(&to_rgb(row));
+    }
+    // zlib stream made of stored deflate blocks
+    let mut data = vec![0x78, 0x01];
+    let block_count = scanlines.len().div_ceil(0xFFFF);
+    for (index, block) in scanlines.chunks(0xFFFF).enumerate() {
+        data.push(if index + 1 == block_count { 1 } else { 0 });
+        data.extend_from_slice(&(block.len() as u16).to_le_bytes());
+        data.extend_from_slice(&(!(block.len() as u16)).to_le_bytes());
+        data.extend_from_slice(block);
+    }
+    let (mut a, mut b) = (1u32, 0u32);
+    for byte in scanlines.iter() {
+        a = (a + *byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    data.extend_from_slice(&((b << 16) | a).to_be_bytes());
+    write_png_chunk(writer, b"IDAT", &data)?;
+    write_png_chunk(writer, b"IEND", &[])
+}
+//#endregion
+
+//#region GIF
+fn write_gif_header<W: Write>(writer: &mut W, width: usize, height: usize) -> io::Result<()> {
+    writer.write_all(b"GIF89a")?;
+    writer.write_all(&(width as u16).to_le_bytes())?;
+    writer.write_all(&(height as u16).to_le_bytes())?;
+    // no global color table, every frame has its own
+    writer.write_all(&[0x00, 0, 0])?;
+    // loop forever
+    writer.write_all(&[0x21, 0xFF, 0x0B])?;
+    writer.write_all(b"NETSCAPE2.0")?;
+    writer.write_all(&[0x03, 0x01, 0x00, 0x00, 0x00])
+}
+
+fn write_gif_frame<W: Write>(writer: &mut W, frame: &[u32], width: usize, height: usize, delay: u16) -> io::Result<()> {
+    let (palette, indices) = quantize(frame);
+    // graphic control extension with the frame delay
+    writer.write_all(&[0x21, 0xF9, 0x04, 0x00])?;
+    writer.write_all(&delay.to_le_bytes())?;
+    writer.write_all(&[0x00, 0x00])?;
+    // image descriptor with a local color table of 256 entries
+    writer.write_all(&[0x2C, 0, 0, 0, 0])?;
+    writer.write_all(&(width as u16).to_le_bytes())?;
+    writer.write_all(&(height as u16).to_le_bytes())?;
+    writer.write_all(&[0x87])?;
+    for index in 0..256 {
+        let color = palette.get(index).copied().unwrap_or(0);
+        writer.write_all(&[(color >> 16) as u8, (color >> 8) as u8, color as u8])?;
+    }
+    writer.write_all(&[8])?;
+    for block in lzw_encode(&indices).chunks(255) {
+        writer.write_all(&[block.len() as u8])?;
+        writer.write_all(block)?;
+    }
+    writer.write_all(&[0])
+}
+
+// reduces a frame to at most 256 colors with median cut over a 15 bit
+// histogram, returning the palette and each pixel's index into it
+fn quantize(frame: &[u32]) -> (Vec<u32>, Vec<u8>) {
+    let key = |pixel: u32| (((pixel >> 19) & 0x1F) << 10 | ((pixel >> 11) & 0x1F) << 5 | ((pixel >> 3) & 0x1F)) as usize;
+    let mut histogram = vec![0u32; 1 << 15];
+    for pixel in frame.iter() {
+        histogram[key(*pixel)] += 1;
+    }
+    let colors: Vec<usize> = (0..histogram.len()).filter(|color| histogram[*color] > 0).collect();
+    let channel = |color: usize, channel: usize| (color >> (10 - channel * 5)) & 0x1F;
+
+    // boxes of colors, the one with the widest channel is split at its median
+    let mut boxes = vec![colors];
+    while boxes.len() < 256 {
+        let widest = boxes.iter().enumerate()
+            .filter(|(_, colors)| colors.len() > 1)
+            .map(|(index, colors)| {
+                let (channel_index, range) = (0..3).map(|c| {
+                    let low = colors.iter().map(|color| channel(*color, c)).min().unwrap_or(0);
+                    let high = colors.iter().map(|color| channel(*color, c)).max().unwrap_or(0);
+                    (c, high - low)
+                }).max_by_key(|(_, range)| *range).unwrap_or((0, 0));
+                (index, channel_index, range)
+            })
+            .max_by_key(|(_, _, range)| *range);
+        let (index, channel_index) = match widest {
+            Some((index, channel_index, range)) if range > 0 => (index, channel_index),
+            _ => break
+        };
+        let mut colors = boxes.swap_remove(index);
+        colors.sort_by_key(|color| channel(*color, channel_index));
+        let total: u32 = colors.iter().map(|color| histogram[*color]).sum();
+        let mut count = 0;
+        let mut split = 1;
+        for (position, color) in colors.iter().enumerate() {
+            count += histogram[*color];
+            if count * 2 >= total {
+                split = (position + 1).max(1).min(colors.len() - 1);
+                break;
+            }
+        }
+        let upper = colors.split_off(split);
+        boxes.push(colors);
+        boxes.push(upper);
+    }
+
+    let mut palette = Vec::with_capacity(boxes.len());
+    let mut lookup = vec![0u8; 1 << 15];
+    for (index, colors) in boxes.iter().enumerate() {
+        let (mut sums, mut count) = ([0u64; 3], 0u64);
+        for color in colors.iter() {
+            let weight = histogram[*color] as u64;
+            for (c, sum) in sums.iter_mut().enumerate() {
+                *sum += channel(*color, c) as u64 * weight;
+            }
+            count += weight;
+            lookup[*color] = index as u8;
+        }
+        let average = |c: usize| match sums[c].checked_div(count) {
+            Some(value) => (value << 3 | value >> 2) as u32,
+            None => 0
+        };
+        palette.push(average(0) << 16 | average(1) << 8 | average(2));
+    }
+    let indices = frame.iter().map(|pixel| lookup[key(*pixel)]).collect();
+    (palette, indices)
+}
+
+// variable width lzw with a minimum code size of 8 bits
+fn lzw_encode(indices: &[u8]) -> Vec<u8> {
+    const CLEAR: u16 = 256;
+    const END: u16 = 257;
+    const MAX_CODE: u16 = 4095;
+    let mut output = Vec::new();
+    let (mut bit_buffer, mut bit_count) = (0u32, 0u32);
+    let mut emit = |code: u16, width: u32, output: &mut Vec<u8>| {
+        bit_buffer |= (code as u32) << bit_count;
+        bit_count += width;
+        while bit_count >= 8 {
+            output.push(bit_buffer as u8);
+            bit_buffer >>= 8;
+            bit_count -= 8;
+        }
+    };
+
+    // (prefix code, next index) to code
+    let mut table: std::collections::HashMap<(u16, u8), u16> = std::collections::HashMap::new();
+    let mut next_code = END + 1;
+    let mut width = 9;
+    emit(CLEAR, width, &mut output);
+    let mut current: Option<u16> = None;
+    for index in indices.iter() {
+        let prefix = match current {
+            Some(prefix) => prefix,
+            None => {
+                current = Some(*index as u16);
+                continue;
+            }
+        };
+        if let Some(code) = table.get(&(prefix, *index)) {
+            current = Some(*code);
+            continue;
+        }
+        emit(prefix, width, &mut output);
+        if next_code <= MAX_CODE {
+            table.insert((prefix, *index), next_code);
+            // the decoder widens a code later than the encoder adds it
+            if next_code == 1 << width && width < 12 {
+                width += 1;
+            }
+            next_code += 1;
+        }
+        else {
+            emit(CLEAR, width, &mut output);
+            table.clear();
+            next_code = END + 1;
+            width = 9;
+        }
+        current = Some(*index as u16);
+    }
+    if let Some(code) = current {
+        emit(code, width, &mut output);
+    }
+    emit(END, width, &mut output);
+    if bit_count > 0 {
+        output.push(bit_buffer as u8);
+    }
+    output
+}
+//#endregion
+
+// full range bt.601 with the chroma averaged over every 2x2 block
+fn to_yuv420(frame: &[u32], width: usize, height: usize) -> Vec<u8> {
+    let (chroma_width, chroma_height) = (width.div_ceil(2), height.div_ceil(2));
+    let mut bytes = Vec::with_capacity(width * height + chroma_width * chroma_height * 2);
+    let rgb = |pixel: u32| (((pixel >> 16) & 0xFF) as f32, ((pixel >> 8) & 0xFF) as f32, (pixel & 0xFF) as f32);
+    for pixel in frame.iter() {
+        let (r, g, b) = rgb(*pixel);
+        bytes.push((0.299 * r + 0.587 * g + 0.114 * b).round().min(255.0) as u8);
+    }
+    let mut cb = Vec::with_capacity(chroma_width * chroma_height);
+    let mut cr = Vec::with_capacity(chroma_width * chroma_height);
+    for chroma_y in 0..chroma_height {
+        for chroma_x in 0..chroma_width {
+            let (mut r, mut g, mut b, mut count) = (0.0, 0.0, 0.0, 0.0);
+            for y in (chroma_y * 2)..(chroma_y * 2 + 2).min(height) {
+                for x in (chroma_x * 2)..(chroma_x * 2 + 2).min(width) {
+                    let (pr, pg, pb) = rgb(frame[y * width + x]);
+                    r += pr;
+                    g += pg;
+                    b += pb;
+                    count += 1.0;
+                }
+            }
+            let (r, g, b) = (r / count, g / count, b / count);
+            cb.push((128.0 - 0.168736 * r - 0.331264 * g + 0.5 * b).round().clamp(0.0, 255.0) as u8);
+            cr.push((128.0 + 0.5 * r - 0.418688 * g - 0.081312 * b).round().clamp(0.0, 255.0) as u8);
+        }
+    }
+    bytes.extend_from_slice(&cb);
+    bytes.extend_from_slice(&cr);
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_frame(width: usize, height: usize) -> Vec<u32> {
+        (0..width * height).map(|i| ((i * 7919) as u32 ^ (i as u32) << 9) & 0xFFFFFF).collect()
+    }
+
+    // reads back the variable width codes written by lzw_encode
+    fn lzw_decode(data: &[u8]) -> Vec<u8> {
+        let reset = || -> Vec<Vec<u8>> {
+            let mut table: Vec<Vec<u8>> = (0..256).map(|index| vec![index as u8]).collect();
+            table.push(Vec::new());
+            table.push(Vec::new());
+            table
+        };
+        let mut table = reset();
+        let (mut width, mut position) = (9, 0);
+        let mut previous: Option<Vec<u8>> = None;
+        let mut output = Vec::new();
+        loop {
+            let mut code = 0usize;
+            for bit in 0..width {
+                let byte = data[(position + bit) / 8];
+                code |= ((byte >> ((position + bit) % 8)) as usize & 1) << bit;
+            }
+            position += width;
+            if code == 256 {
+                table = reset();
+                width = 9;
+                previous = None;
+                continue;
+            }
+            if code == 257 {
+                return output;
+            }
+            let entry = match (table.get(code), &previous) {
+                (Some(entry), _) => entry.clone(),
+                (None, Some(previous)) if code == table.len() => {
+                    let mut entry = previous.clone();
+                    entry.push(previous[0]);
+                    entry
+                },
+                _ => panic!("invalid code {}", code)
+            };
+            output.extend_from_slice(&entry);
+            if let Some(mut previous) = previous.take() {
+                if table.len() < 4096 {
+                    previous.push(entry[0]);
+                    table.push(previous);
+                }
+            }
+            if table.len() == 1 << width && width < 12 {
+                width += 1;
+            }
+            previous = Some(entry);
+        }
+    }
+
+    #[test]
+    fn crc32_matches_the_check_value() {
+        assert_eq!(crc32(b"123456789", 0), 0xCBF4_3926);
+        // continuing a crc gives the same result as one pass
+        assert_eq!(crc32(b"56789", crc32(b"1234", 0)), 0xCBF4_3926);
+        assert_eq!(crc32(&[], 0), 0);
+    }
+
+    #[test]
+    fn writes_valid_png_chunks() {
+        let (width, height) = (200, 120);
+        let frame = test_frame(width, height);
+        let mut bytes = Vec::new();
+        write_png(&mut bytes, &frame, width, height).unwrap();
+        assert_eq!(&bytes[..8], b"\x89PNG\r\n\x1a\n");
+
+        let mut chunks = Vec::new();
+        let mut rest = &bytes[8..];
+        while !rest.is_empty() {
+            let length = u32::from_be_bytes([rest[0], rest[1], rest[2], rest[3]]) as usize;
+            let (kind, data) = (&rest[4..8], &rest[8..8 + length]);
+            let crc = u32::from_be_bytes([rest[8 + length], rest[9 + length], rest[10 + length], rest[11 + length]]);
+            assert_eq!(crc, crc32(data, crc32(kind, 0)));
+            chunks.push((kind.to_vec(), data.to_vec()));
+            rest = &rest[12 + length..];
+        }
+        let kinds: Vec<&[u8]> = chunks.iter().map(|(kind, _)| kind.as_slice()).collect();
+        assert_eq!(kinds, vec![&b"IHDR"[..], &b"IDAT"[..], &b"IEND"[..]]);
+        assert_eq!(&chunks[0].1[..8], &[0, 0, 0, 200, 0, 0, 0, 120]);
+
+        // undo the stored deflate blocks and compare against the scanlines
+        let data = &chunks[1].1;
+        assert_eq!(&data[..2], &[0x78, 0x01]);
+        let mut scanlines = Vec::new();
+        let mut position = 2;
+        loop {
+            let last = data[position] == 1;
+            let length = u16::from_le_bytes([data[position + 1], data[position + 2]]);
+            let complement = u16::from_le_bytes([data[position + 3], data[position + 4]]);
+            assert_eq!(length, !complement);
+            position += 5;
+            scanlines.extend_from_slice(&data[position..position + length as usize]);
+            position += length as usize;
+            if last {
+                break;
+            }
+        }
+        assert!(position > 0xFFFF);
+        let mut expected = Vec::new();
+        for row in frame.chunks(width) {
+            expected.push(0);
+            expected.extend_from_slice(&to_rgb(row));
+        }
+        assert_eq!(scanlines, expected);
+        assert_eq!(data.len(), position + 4);
+    }
+
+    #[test]
+    fn lzw_round_trips() {
+        let inputs = [
+            vec![],
+            vec![7],
+            vec![1; 10_000],
+            // enough distinct sequences to fill the table and force a clear
+            test_frame(300, 200).iter().map(|pixel| *pixel as u8).collect::<Vec<u8>>(),
+            (0..20_000).map(|i| (i % 3 + i / 700) as u8).collect::<Vec<u8>>()
+        ];
+        for input in inputs.iter() {
+            assert_eq!(&lzw_decode(&lzw_encode(input)), input);
+        }
+    }
+
+    #[test]
+    fn quantizes_few_colors_exactly() {
+        let frame = vec![0x000000, 0xFFFFFF, 0xFF0000, 0xFFFFFF, 0x0000FF];
+        let (palette, indices) = quantize(&frame);
+        assert!(palette.len() <= 256);
+        for (pixel, index) in frame.iter().zip(indices.iter()) {
+            assert_eq!(palette[*index as usize], *pixel);
+        }
+        let (palette, indices) = quantize(&test_frame(64, 64));
+        assert!(palette.len() <= 256);
+        assert!(indices.iter().all(|index| (*index as usize) < palette.len()));
+    }
+
+    #[test]
+    fn converts_to_yuv420() {
+        let bytes = to_yuv420(&[0xFFFFFF, 0x000000, 0xFFFFFF], 3, 1);
+        // three luma samples then one cb and one cr sample for each 2x2 block
+        assert_eq!(bytes, vec![255, 0, 255, 128, 128, 128, 128]);
+    }
+}
@@ -1,17 +1,77 @@
 use std::fmt;
 use std::cmp::{PartialEq};
-use std::ops::{Add, Index, IndexMut, Mul, Sub, Neg};
+use std::convert::TryFrom;
+use std::ops::{Add, AddAssign, Div, DivAssign, Index, IndexMut, Mul, MulAssign, Sub, SubAssign, Neg};
 
 pub trait MatrixBase {
     fn get_width(&self) -> usize;
     fn get_height(&self) -> usize;
-    fn combine(&self, other: &Matrix, sub: bool) -> Result<Matrix, ()>;
-    fn dot(&self, other: &Matrix) -> Result<Matrix, ()>;
+    fn combine(&self, other: &Matrix, sub: bool) -> MatrixResult;
+    fn dot(&self, other: &Matrix) -> MatrixResult;
     fn scale(&self, other: f32) -> Self;
 }
 
+pub type MatrixResult = Result<Matrix, MatrixError>;
+
+//#region MatrixError
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Dimensions {
+    pub height: usize,
+    pub width: usize
+}
+
+impl Dimensions {
+    pub fn new(height: usize, width: usize) -> Self {
+        Self { height, width }
+    }
+}
+
+impl fmt::Display for Dimensions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Height: {}, Width: {}", self.height, self.width)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum MatrixError {
+    // the supplied contents do not fit inside the requested dimensions
+    InvalidLength { dimensions: Dimensions, length: usize },
+    // the operands of an operation do not have compatible dimensions
+    DimensionMismatch { operation: &'static str, expected: Dimensions, actual: Dimensions },
+    // determinants and inverses only exist for square matrices
+    NotSquare { dimensions: Dimensions },
+    // the matrix has no inverse (determinant of zero)
+    Singular,
+    IndexOutOfBounds { index: (usize, usize), dimensions: Dimensions }
+}
+
+impl fmt::Display for MatrixError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MatrixError::InvalidLength { dimensions, length } => write!(
+                f, "Cannot construct matrix of {} from {} values", dimensions, length
+            ),
+            MatrixError::DimensionMismatch { operation, expected, actual } => write!(
+                f, "Cannot {}, expected {} but found {}", operation, expected, actual
+            ),
+            MatrixError::NotSquare { dimensions } => write!(
+                f, "Matrix of {} is not square", dimensions
+            ),
+            MatrixError::Singular => write!(
+                f, "Matrix is singular and cannot be inverted"
+            ),
+            MatrixError::IndexOutOfBounds { index, dimensions } => write!(
+                f, "Index (row: {}, column: {}) is out of bounds for matrix of {}", index.0, index.1, dimensions
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MatrixError {}
+//#endregion
+
 //#region Matrix
-#[derive(Clone, PartialEq)]
+#[derive(Clone, PartialEq, Debug)]
 pub struct Matrix {
     width: usize,
     height: usize,
@@ -22,7 +82,7 @@ impl fmt::Display for Matrix {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let mut output = String::new();
         for row in 0..self.height {
-            output.push_str("[");
+            output.push('[');
             for col in 0..self.width {
                 output.push_str(&format!("{}", self[row][col]));
                 if col != self.width - 1 {
@@ -44,16 +104,265 @@ impl Matrix {
         }
     }
 
-    pub fn from_vec(width: usize, height: usize, mut contents: Vec<f32>) -> Self {
-        if width * height < contents.len() {
-            panic!("Cannot construct matrix where width and height are less than the supplied vector");
+    // contents are row major and must fill the matrix exactly
+    pub fn from_vec(width: usize, height: usize, contents: Vec<f32>) -> MatrixResult {
+        if width * height != contents.len() {
+            return Err(MatrixError::InvalidLength {
+                dimensions: Dimensions::new(height, width),
+                length: contents.len()
+            });
         }
-        contents.resize(width * height, 0.0);
-        Self {
+        Ok(Self {
             width,
             height,
             contents
+        })
+    }
+
+    // row major 4x4 matrix, the dimensions are known so construction cannot fail
+    fn homogeneous(contents: [f32; 16]) -> Self {
+        Self {
+            width: 4,
+            height: 4,
+            contents: contents.to_vec()
+        }
+    }
+
+    // row major copy of a 4x4 matrix, used by the simd transform paths
+    pub fn to_array(&self) -> Result<[f32; 16], MatrixError> {
+        if self.width != 4 || self.height != 4 {
+            return Err(MatrixError::DimensionMismatch {
+                operation: "convert matrix to a 4x4 array",
+                expected: Dimensions::new(4, 4),
+                actual: self.get_dimensions()
+            });
+        }
+        let mut result = [0.0; 16];
+        result.copy_from_slice(&self.contents);
+        Ok(result)
+    }
+
+    pub fn get_dimensions(&self) -> Dimensions {
+        Dimensions::new(self.height, self.width)
+    }
+
+    pub fn is_square(&self) -> bool {
+        self.width == self.height
+    }
+
+    pub fn get(&self, row: usize, col: usize) -> Result<f32, MatrixError> {
+        if row >= self.height || col >= self.width {
+            return Err(MatrixError::IndexOutOfBounds {
+                index: (row, col),
+                dimensions: self.get_dimensions()
+            });
+        }
+        Ok(self[row][col])
+    }
+
+    pub fn set(&mut self, row: usize, col: usize, value: f32) -> Result<(), MatrixError> {
+        if row >= self.height || col >= self.width {
+            return Err(MatrixError::IndexOutOfBounds {
+                index: (row, col),
+                dimensions: self.get_dimensions()
+            });
+        }
+        self[row][col] = value;
+        Ok(())
+    }
+
+    pub fn row(&self, row: usize) -> Result<Vec<f32>, MatrixError> {
+        if row >= self.height {
+            return Err(MatrixError::IndexOutOfBounds {
+                index: (row, 0),
+                dimensions: self.get_dimensions()
+            });
+        }
+        Ok(self[row].to_vec())
+    }
+
+    pub fn column(&self, col: usize) -> Result<Vec<f32>, MatrixError> {
+        if col >= self.width {
+            return Err(MatrixError::IndexOutOfBounds {
+                index: (0, col),
+                dimensions: self.get_dimensions()
+            });
+        }
+        Ok((0..self.height).map(|row| self[row][col]).collect())
+    }
+
+    pub fn transpose(&self) -> Self {
+        let mut result = Matrix::new(self.height, self.width);
+        for row in 0..self.height {
+            for col in 0..self.width {
+                result[col][row] = self[row][col];
+            }
+        }
+        result
+    }
+
+    // calculated through gaussian elimination with partial pivoting
+    pub fn determinant(&self) -> Result<f32, MatrixError> {
+        if !self.is_square() {
+            return Err(MatrixError::NotSquare { dimensions: self.get_dimensions() });
+        }
+        let size = self.width;
+        let tolerance = self.pivot_tolerance();
+        let mut reduced = self.clone();
+        let mut determinant = 1.0;
+        for col in 0..size {
+            let pivot = (col..size)
+                .max_by(|a, b| reduced[*a][col].abs().total_cmp(&reduced[*b][col].abs()))
+                .unwrap();
+            if reduced[pivot][col].abs() <= tolerance {
+                return Ok(0.0);
+            }
+            if pivot != col {
+                reduced.swap_rows(pivot, col);
+                determinant = -determinant;
+            }
+            determinant *= reduced[col][col];
+            for row in (col + 1)..size {
+                let factor = reduced[row][col] / reduced[col][col];
+                for c in col..size {
+                    reduced[row][c] -= factor * reduced[col][c];
+                }
+            }
+        }
+        Ok(determinant)
+    }
+
+    // calculated through gauss-jordan elimination with partial pivoting
+    pub fn inverse(&self) -> MatrixResult {
+        if !self.is_square() {
+            return Err(MatrixError::NotSquare { dimensions: self.get_dimensions() });
+        }
+        let size = self.width;
+        let tolerance = self.pivot_tolerance();
+        let mut reduced = self.clone();
+        let mut result = Matrix::identity(size);
+        for col in 0..size {
+            let pivot = (col..size)
+                .max_by(|a, b| reduced[*a][col].abs().total_cmp(&reduced[*b][col].abs()))
+                .unwrap();
+            if reduced[pivot][col].abs() <= tolerance {
+                return Err(MatrixError::Singular);
+            }
+            reduced.swap_rows(pivot, col);
+            result.swap_rows(pivot, col);
+            let pivot_value = reduced[col][col];
+            for c in 0..size {
+                reduced[col][c] /= pivot_value;
+                result[col][c] /= pivot_value;
+            }
+            for row in 0..size {
+                if row != col {
+                    let factor = reduced[row][col];
+                    for c in 0..size {
+                        reduced[row][c] -= factor * reduced[col][c];
+                        result[row][c] -= factor * result[col][c];
+                    }
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    // pivots this small are treated as zero, relative to the largest entry so
+    // the check doesn't depend on the scale of the matrix
+    fn pivot_tolerance(&self) -> f32 {
+        let largest = self.contents.iter().fold(0.0f32, |largest, value| largest.max(value.abs()));
+        largest * self.width as f32 * f32::EPSILON
+    }
+
+    fn swap_rows(&mut self, a: usize, b: usize) {
+        if a != b {
+            for col in 0..self.width {
+                self.contents.swap(a * self.width + col, b * self.width + col);
+            }
+        }
+    }
+
+    pub fn identity(size: usize) -> Self {
+        let mut result = Matrix::new(size, size);
+        for i in 0..size {
+            result[i][i] = 1.0;
         }
+        result
+    }
+
+    // homogeneous 4x4 transformation matrices, angles are in radians
+    pub fn translation(offset: Vector3D) -> Self {
+        Matrix::homogeneous([
+            1.0, 0.0, 0.0, offset.x,
+            0.0, 1.0, 0.0, offset.y,
+            0.0, 0.0, 1.0, offset.z,
+            0.0, 0.0, 0.0, 1.0
+        ])
+    }
+
+    pub fn scaling(scale: Vector3D) -> Self {
+        Matrix::homogeneous([
+            scale.x, 0.0, 0.0, 0.0,
+            0.0, scale.y, 0.0, 0.0,
+            0.0, 0.0, scale.z, 0.0,
+            0.0, 0.0, 0.0, 1.0
+        ])
+    }
+
+    pub fn rotation_x(angle: f32) -> Self {
+        Matrix::homogeneous([
+            1.0, 0.0, 0.0, 0.0,
+            0.0, angle.cos(), -angle.sin(), 0.0,
+            0.0, angle.sin(), angle.cos(), 0.0,
+            0.0, 0.0, 0.0, 1.0
+        ])
+    }
+
+    pub fn rotation_y(angle: f32) -> Self {
+        Matrix::homogeneous([
+            angle.cos(), 0.0, angle.sin(), 0.0,
+            0.0, 1.0, 0.0, 0.0,
+            -angle.sin(), 0.0, angle.cos(), 0.0,
+            0.0, 0.0, 0.0, 1.0
+        ])
+    }
+
+    pub fn rotation_z(angle: f32) -> Self {
+        Matrix::homogeneous([
+            angle.cos(), -angle.sin(), 0.0, 0.0,
+            angle.sin(), angle.cos(), 0.0, 0.0,
+            0.0, 0.0, 1.0, 0.0,
+            0.0, 0.0, 0.0, 1.0
+        ])
+    }
+
+    // right handed perspective projection looking down -z, mapping the
+    // view frustum to the -1..1 cube
+    pub fn perspective(fov_y: f32, aspect: f32, near: f32, far: f32) -> Self {
+        let f = 1.0 / (fov_y / 2.0).tan();
+        Matrix::homogeneous([
+            f / aspect, 0.0, 0.0, 0.0,
+            0.0, f, 0.0, 0.0,
+            0.0, 0.0, (far + near) / (near - far), (2.0 * far * near) / (near - far),
+            0.0, 0.0, -1.0, 0.0
+        ])
+    }
+
+    pub fn orthographic(left: f32, right: f32, bottom: f32, top: f32, near: f32, far: f32) -> Self {
+        Matrix::homogeneous([
+            2.0 / (right - left), 0.0, 0.0, -(right + left) / (right - left),
+            0.0, 2.0 / (top - bottom), 0.0, -(top + bottom) / (top - bottom),
+            0.0, 0.0, -2.0 / (far - near), -(far + near) / (far - near),
+            0.0, 0.0, 0.0, 1.0
+        ])
+    }
+
+    // multiplies a point by a 4x4 matrix using homogeneous coordinates,
+    // returning the transformed point and its w component
+    pub fn transform_point(&self, point: Vector3D) -> Result<(Vector3D, f32), MatrixError> {
+        let result = Vector4D::try_from(self.dot(&Matrix::from(point.extend(1.0)))?)?;
+        Ok((result.truncate(), result.w))
     }
 }
 
@@ -66,7 +375,7 @@ impl MatrixBase for Matrix {
         self.height
     }
 
-    fn combine(&self, other: &Matrix, sub: bool) -> Result<Self, ()> {
+    fn combine(&self, other: &Matrix, sub: bool) -> MatrixResult {
         if self.width == other.width && self.height == other.height {
             let mut result = Matrix::new(self.width, self.height);
             for row in 0..self.height {
@@ -81,10 +390,14 @@ impl MatrixBase for Matrix {
             }
             return Ok(result);
         }
-        Err(())
+        Err(MatrixError::DimensionMismatch {
+            operation: if sub { "subtract matrices" } else { "add matrices" },
+            expected: self.get_dimensions(),
+            actual: other.get_dimensions()
+        })
     }
 
-    fn dot(&self, other: &Matrix) -> Result<Self, ()> {
+    fn dot(&self, other: &Matrix) -> MatrixResult {
         let left: &Matrix;
         let right: &Matrix;
         // check for presence of column vectors
@@ -121,7 +434,11 @@ impl MatrixBase for Matrix {
                 }
             }
         } else {
-            return Err(());
+            return Err(MatrixError::DimensionMismatch {
+                operation: "multiply matrices",
+                expected: Dimensions::new(left.width, right.width),
+                actual: right.get_dimensions()
+            });
         }
 
         Ok(result)
@@ -139,70 +456,76 @@ impl MatrixBase for Matrix {
 }
 
 impl Add<Self> for Matrix {
-    type Output = Self;
+    type Output = MatrixResult;
 
     fn add(self, rhs: Self) -> Self::Output {
-        self.combine(&rhs, false).expect(
-            &format!(
-                "Cannot add matrix (l) of Height: {}, Width: {} with matrix (r) of dimensions Height: {}, Width: {}",
-                self.height, self.width,
-                rhs.height, rhs.width
-            ).to_string()
-        )
+        self.combine(&rhs, false)
     }
 }
 
 impl Sub<Self> for Matrix {
-    type Output = Self;
+    type Output = MatrixResult;
 
     fn sub(self, rhs: Self) -> Self::Output {
-        self.combine(&rhs, true).expect(
-            &format!(
-                "Cannot subtract matrix (l) of Height: {}, Width: {} with matrix (r) of dimensions Height: {}, Width: {}",
-                self.height, self.width,
-                rhs.height, rhs.width
-            ).to_string()
-        )
+        self.combine(&rhs, true)
     }
 }
 
 impl Mul<Self> for Matrix {
-    type Output = Self;
+    type Output = MatrixResult;
 
     fn mul(self, rhs: Self) -> Self::Output {
-        self.dot(&rhs).expect(
-            &format!(
-                "Cannot multiply matrix (l) of Height: {}, Width: {} with matrix (r) of dimensions Height: {}, Width: {}",
-                self.height, self.width,
-                rhs.height, rhs.width
-            ).to_string()
-        )
+        self.dot(&rhs)
+    }
+}
+
+// allows chaining checked operations, e.g. a * b * c, where the
+// first error encountered is carried through to the result
+impl Add<Matrix> for MatrixResult {
+    type Output = MatrixResult;
+
+    fn add(self, rhs: Matrix) -> Self::Output {
+        self?.combine(&rhs, false)
+    }
+}
+
+impl Sub<Matrix> for MatrixResult {
+    type Output = MatrixResult;
+
+    fn sub(self, rhs: Matrix) -> Self::Output {
+        self?.combine(&rhs, true)
+    }
+}
+
+impl Mul<Matrix> for MatrixResult {
+    type Output = MatrixResult;
+
+    fn mul(self, rhs: Matrix) -> Self::Output {
+        self?.dot(&rhs)
     }
 }
 
 impl Mul<Vector3D> for Matrix {
-    type Output = Self;
+    type Output = MatrixResult;
 
     fn mul(self, rhs: Vector3D) -> Self::Output {
-        self.dot(&Matrix::from(rhs)).expect(
-            &format!(
-                "Cannot multiply matrix of height {} with vector of height 3",
-                self.height
-            ).to_string()
-        )
+        self.dot(&Matrix::from(rhs))
     }
 }
 
 impl Mul<Vector2D> for Matrix {
-    type Output = Self;
+    type Output = MatrixResult;
 
     fn mul(self, rhs: Vector2D) -> Self::Output {
-        self.dot(&Matrix::from(rhs)).expect(
-            &format!(
-                "Cannot multiply matrix of height {} with vector of height 2",
-                self.height
-            ).to_string()
-        )
+        self.dot(&Matrix::from(rhs))
+    }
+}
+
+impl Mul<Vector4D> for Matrix {
+    type Output = MatrixResult;
+
+    fn mul(self, rhs: Vector4D) -> Self::Output {
+        self.dot(&Matrix::from(rhs))
     }
 }
 
@@ -238,17 +561,97 @@ impl IndexMut<usize> for Matrix {
 
 impl From<Vector3D> for Matrix {
     fn from(vector: Vector3D) -> Self {
-        Matrix::from_vec(1, 3, vec![vector.x, vector.y, vector.z])
+        Self {
+            width: 1,
+            height: 3,
+            contents: vec![vector.x, vector.y, vector.z]
+        }
     }
 }
 
 impl From<Vector2D> for Matrix {
     fn from(vector: Vector2D) -> Self {
-        Matrix::from_vec(1, 2, vec![vector.x, vector.y])
+        Self {
+            width: 1,
+            height: 2,
+            contents: vec![vector.x, vector.y]
+        }
+    }
+}
+
+impl From<Vector4D> for Matrix {
+    fn from(vector: Vector4D) -> Self {
+        Self {
+            width: 1,
+            height: 4,
+            contents: vec![vector.x, vector.y, vector.z, vector.w]
+        }
     }
 }
 //#endregion
 
+// implements the component-wise division and compound assignment
+// operators shared by all vector types
+macro_rules! vector_ops {
+    ($tp:ident, $($field:ident),+) => (
+        impl Div<Self> for $tp {
+            type Output = Self;
+
+            fn div(self, rhs: Self) -> Self::Output {
+                Self {
+                    $($field: self.$field / rhs.$field),+
+                }
+            }
+        }
+
+        impl Div<f32> for $tp {
+            type Output = Self;
+
+            fn div(self, rhs: f32) -> Self::Output {
+                Self {
+                    $($field: self.$field / rhs),+
+                }
+            }
+        }
+
+        impl AddAssign<Self> for $tp {
+            fn add_assign(&mut self, rhs: Self) {
+                *self = *self + rhs;
+            }
+        }
+
+        impl SubAssign<Self> for $tp {
+            fn sub_assign(&mut self, rhs: Self) {
+                *self = *self - rhs;
+            }
+        }
+
+        impl MulAssign<Self> for $tp {
+            fn mul_assign(&mut self, rhs: Self) {
+                *self = *self * rhs;
+            }
+        }
+
+        impl MulAssign<f32> for $tp {
+            fn mul_assign(&mut self, rhs: f32) {
+                *self = *self * rhs;
+            }
+        }
+
+        impl DivAssign<Self> for $tp {
+            fn div_assign(&mut self, rhs: Self) {
+                *self = *self / rhs;
+            }
+        }
+
+        impl DivAssign<f32> for $tp {
+            fn div_assign(&mut self, rhs: f32) {
+                *self = *self / rhs;
+            }
+        }
+    )
+}
+
 //#region Vector3D
 // vector3d is a special case of a matrix
 // where the width is 1 and the height is 3
@@ -262,10 +665,79 @@ pub struct Vector3D {
 impl Vector3D {
     pub const ZERO: Self = Self { x: 0.0, y: 0.0, z: 0.0 };
     pub const ONE: Self = Self { x: 1.0, y: 1.0, z: 1.0 };
+    pub const X: Self = Self { x: 1.0, y: 0.0, z: 0.0 };
+    pub const Y: Self = Self { x: 0.0, y: 1.0, z: 0.0 };
+    pub const Z: Self = Self { x: 0.0, y: 0.0, z: 1.0 };
 
     pub fn new(x: f32, y: f32, z: f32) -> Self {
         Self { x, y, z }
     }
+
+    pub fn dot(&self, other: &Vector3D) -> f32 {
+        self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    pub fn cross(&self, other: &Vector3D) -> Self {
+        Self {
+            x: self.y * other.z - self.z * other.y,
+            y: self.z * other.x - self.x * other.z,
+            z: self.x * other.y - self.y * other.x
+        }
+    }
+
+    pub fn length_squared(&self) -> f32 {
+        self.dot(self)
+    }
+
+    pub fn length(&self) -> f32 {
+        self.length_squared().sqrt()
+    }
+
+    // returns the zero vector if the length is zero
+    pub fn normalize(&self) -> Self {
+        let length = self.length();
+        if length == 0.0 {
+            return Vector3D::ZERO;
+        }
+        *self / length
+    }
+
+    pub fn distance(&self, other: &Vector3D) -> f32 {
+        (*other - *self).length()
+    }
+
+    pub fn distance_squared(&self, other: &Vector3D) -> f32 {
+        (*other - *self).length_squared()
+    }
+
+    // reflects the vector off a surface with the given (unit) normal
+    pub fn reflect(&self, normal: &Vector3D) -> Self {
+        *self - *normal * (2.0 * self.dot(normal))
+    }
+
+    pub fn lerp(&self, other: &Vector3D, t: f32) -> Self {
+        *self + (*other - *self) * t
+    }
+
+    pub fn min(&self, other: &Vector3D) -> Self {
+        Self {
+            x: self.x.min(other.x),
+            y: self.y.min(other.y),
+            z: self.z.min(other.z)
+        }
+    }
+
+    pub fn max(&self, other: &Vector3D) -> Self {
+        Self {
+            x: self.x.max(other.x),
+            y: self.y.max(other.y),
+            z: self.z.max(other.z)
+        }
+    }
+
+    pub fn extend(&self, w: f32) -> Vector4D {
+        Vector4D::new(self.x, self.y, self.z, w)
+    }
 }
 
 impl fmt::Display for Vector3D {
@@ -274,24 +746,15 @@ impl fmt::Display for Vector3D {
     }
 }
 
-macro_rules! vector3d_combine {
-    ($self:ident, $rhs:ident, $sub:literal) => (
-        {
-            let result = Matrix::from($self).combine(&Matrix::from($rhs), $sub).unwrap();
-            Self {
-                x: result[0][0],
-                y: result[1][0],
-                z: result[2][0]
-            }
-        }
-    )
-}
-
 impl Add<Self> for Vector3D {
     type Output = Self;
 
     fn add(self, rhs: Self) -> Self::Output {
-        vector3d_combine!(self, rhs, false)
+        Self {
+            x: self.x + rhs.x,
+            y: self.y + rhs.y,
+            z: self.z + rhs.z
+        }
     }
 }
 
@@ -299,7 +762,11 @@ impl Sub<Self> for Vector3D {
     type Output = Self;
 
     fn sub(self, rhs: Self) -> Self::Output {
-        vector3d_combine!(self, rhs, true)
+        Self {
+            x: self.x - rhs.x,
+            y: self.y - rhs.y,
+            z: self.z - rhs.z
+        }
     }
 }
 
@@ -307,17 +774,16 @@ impl Mul<Self> for Vector3D {
     type Output = Self;
 
     fn mul(self, rhs: Self) -> Self::Output {
-        let result = Matrix::from(self).dot(&Matrix::from(rhs)).unwrap();
         Self {
-            x: result[0][0],
-            y: result[1][0],
-            z: result[2][0]
+            x: self.x * rhs.x,
+            y: self.y * rhs.y,
+            z: self.z * rhs.z
         }
     }
 }
 
 impl Mul<Matrix> for Vector3D {
-    type Output = Matrix;
+    type Output = MatrixResult;
 
     fn mul(self, rhs: Matrix) -> Self::Output {
         rhs * self
@@ -356,20 +822,22 @@ impl Neg for Vector3D {
     }
 }
 
-impl From<Matrix> for Vector3D {
-    fn from(other: Matrix) -> Self {
+impl TryFrom<Matrix> for Vector3D {
+    type Error = MatrixError;
+
+    fn try_from(other: Matrix) -> Result<Self, Self::Error> {
         if other.width != 1 || other.height != 3 {
-            panic!(
-                "Cannot cast Matrix to Vector3D with Height {} and Width {}",
-                other.height,
-                other.width
-            );
+            return Err(MatrixError::DimensionMismatch {
+                operation: "cast matrix to Vector3D",
+                expected: Dimensions::new(3, 1),
+                actual: other.get_dimensions()
+            });
         }
-        Self {
+        Ok(Self {
             x: other[0][0],
             y: other[1][0],
             z: other[2][0]
-        }
+        })
     }
 }
 
@@ -378,6 +846,8 @@ impl From<Vector2D> for Vector3D {
         Vector3D::new(other.x, other.y, 0.0)
     }
 }
+
+vector_ops!(Vector3D, x, y, z);
 //#endregion
 
 //#region Vector2D
@@ -392,10 +862,77 @@ pub struct Vector2D {
 impl Vector2D {
     pub const ZERO: Self = Self { x: 0.0, y: 0.0 };
     pub const ONE: Self = Self { x: 1.0, y: 1.0 };
+    pub const X: Self = Self { x: 1.0, y: 0.0 };
+    pub const Y: Self = Self { x: 0.0, y: 1.0 };
 
     pub fn new(x: f32, y: f32) -> Self {
         Self { x, y }
     }
+
+    pub fn dot(&self, other: &Vector2D) -> f32 {
+        self.x * other.x + self.y * other.y
+    }
+
+    // z component of the 3d cross product, positive when other is
+    // anticlockwise of self
+    pub fn cross(&self, other: &Vector2D) -> f32 {
+        self.x * other.y - self.y * other.x
+    }
+
+    pub fn perpendicular(&self) -> Self {
+        Self {
+            x: -self.y,
+            y: self.x
+        }
+    }
+
+    pub fn length_squared(&self) -> f32 {
+        self.dot(self)
+    }
+
+    pub fn length(&self) -> f32 {
+        self.length_squared().sqrt()
+    }
+
+    // returns the zero vector if the length is zero
+    pub fn normalize(&self) -> Self {
+        let length = self.length();
+        if length == 0.0 {
+            return Vector2D::ZERO;
+        }
+        *self / length
+    }
+
+    pub fn distance(&self, other: &Vector2D) -> f32 {
+        (*other - *self).length()
+    }
+
+    pub fn distance_squared(&self, other: &Vector2D) -> f32 {
+        (*other - *self).length_squared()
+    }
+
+    // reflects the vector off a surface with the given (unit) normal
+    pub fn reflect(&self, normal: &Vector2D) -> Self {
+        *self - *normal * (2.0 * self.dot(normal))
+    }
+
+    pub fn lerp(&self, other: &Vector2D, t: f32) -> Self {
+        *self + (*other - *self) * t
+    }
+
+    pub fn min(&self, other: &Vector2D) -> Self {
+        Self {
+            x: self.x.min(other.x),
+            y: self.y.min(other.y)
+        }
+    }
+
+    pub fn max(&self, other: &Vector2D) -> Self {
+        Self {
+            x: self.x.max(other.x),
+            y: self.y.max(other.y)
+        }
+    }
 }
 
 impl fmt::Display for Vector2D {
@@ -404,23 +941,14 @@ impl fmt::Display for Vector2D {
     }
 }
 
-macro_rules! vector2d_combine {
-    ($self:ident, $rhs:ident, $sub:literal) => (
-        {
-            let result = Matrix::from($self).combine(&Matrix::from($rhs), $sub).unwrap();
-            Self {
-                x: result[0][0],
-                y: result[1][0]
-            }
-        }
-    )
-}
-
 impl Add<Self> for Vector2D {
     type Output = Self;
 
     fn add(self, rhs: Self) -> Self::Output {
-        vector2d_combine!(self, rhs, false)
+        Self {
+            x: self.x + rhs.x,
+            y: self.y + rhs.y
+        }
     }
 }
 
@@ -428,7 +956,10 @@ impl Sub<Self> for Vector2D {
     type Output = Self;
 
     fn sub(self, rhs: Self) -> Self::Output {
-        vector2d_combine!(self, rhs, true)
+        Self {
+            x: self.x - rhs.x,
+            y: self.y - rhs.y
+        }
     }
 }
 
@@ -436,16 +967,15 @@ impl Mul<Self> for Vector2D {
     type Output = Self;
 
     fn mul(self, rhs: Self) -> Self::Output {
-        let result = Matrix::from(self).dot(&Matrix::from(rhs)).unwrap();
         Self {
-            x: result[0][0],
-            y: result[1][0]
+            x: self.x * rhs.x,
+            y: self.y * rhs.y
         }
     }
 }
 
 impl Mul<Matrix> for Vector2D {
-    type Output = Matrix;
+    type Output = MatrixResult;
 
     fn mul(self, rhs: Matrix) -> Self::Output {
         rhs * self
@@ -482,19 +1012,21 @@ impl Neg for Vector2D {
     }
 }
 
-impl From<Matrix> for Vector2D {
-    fn from(other: Matrix) -> Self {
+impl TryFrom<Matrix> for Vector2D {
+    type Error = MatrixError;
+
+    fn try_from(other: Matrix) -> Result<Self, Self::Error> {
         if other.width != 1 || other.height != 2 {
-            panic!(
-                "Cannot cast Matrix to Vector2D with Height {} and Width {}",
-                other.height,
-                other.width
-            );
+            return Err(MatrixError::DimensionMismatch {
+                operation: "cast matrix to Vector2D",
+                expected: Dimensions::new(2, 1),
+                actual: other.get_dimensions()
+            });
         }
-        Self {
+        Ok(Self {
             x: other[0][0],
             y: other[1][0]
-        }
+        })
     }
 }
 
@@ -511,4 +1043,658 @@ macro_rules! vector2d_into_t {
 vector2d_into_t!(u32);
 vector2d_into_t!(i32);
 vector2d_into_t!(f32);
-//#endregion
\ No newline at end of file
+
+vector_ops!(Vector2D, x, y);
+//#endregion
+
+//#region Vector4D
+// vector4d is used for homogeneous coordinates, where w is 1 for
+// points and 0 for directions
+#[derive(Copy, Clone, PartialEq, Default, Debug)]
+pub struct Vector4D {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub w: f32
+}
+
+impl Vector4D {
+    pub const ZERO: Self = Self { x: 0.0, y: 0.0, z: 0.0, w: 0.0 };
+    pub const ONE: Self = Self { x: 1.0, y: 1.0, z: 1.0, w: 1.0 };
+
+    pub fn new(x: f32, y: f32, z: f32, w: f32) -> Self {
+        Self { x, y, z, w }
+    }
+
+    pub fn dot(&self, other: &Vector4D) -> f32 {
+        self.x * other.x + self.y * other.y + self.z * other.z + self.w * other.w
+    }
+
+    pub fn length_squared(&self) -> f32 {
+        self.dot(self)
+    }
+
+    pub fn length(&self) -> f32 {
+        self.length_squared().sqrt()
+    }
+
+    // returns the zero vector if the length is zero
+    pub fn normalize(&self) -> Self {
+        let length = self.length();
+        if length == 0.0 {
+            return Vector4D::ZERO;
+        }
+        *self / length
+    }
+
+    pub fn distance(&self, other: &Vector4D) -> f32 {
+        (*other - *self).length()
+    }
+
+    pub fn lerp(&self, other: &Vector4D, t: f32) -> Self {
+        *self + (*other - *self) * t
+    }
+
+    pub fn min(&self, other: &Vector4D) -> Self {
+        Self {
+            x: self.x.min(other.x),
+            y: self.y.min(other.y),
+            z: self.z.min(other.z),
+            w: self.w.min(other.w)
+        }
+    }
+
+    pub fn max(&self, other: &Vector4D) -> Self {
+        Self {
+            x: self.x.max(other.x),
+            y: self.y.max(other.y),
+            z: self.z.max(other.z),
+            w: self.w.max(other.w)
+        }
+    }
+
+    // drops the w component without dividing by it
+    pub fn truncate(&self) -> Vector3D {
+        Vector3D::new(self.x, self.y, self.z)
+    }
+
+    // performs the perspective divide
+    pub fn to_cartesian(self) -> Vector3D {
+        if self.w == 0.0 {
+            return self.truncate();
+        }
+        self.truncate() / self.w
+    }
+}
+
+impl fmt::Display for Vector4D {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "(X: {}, Y: {}, Z: {}, W: {})", self.x, self.y, self.z, self.w)
+    }
+}
+
+impl Add<Self> for Vector4D {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Self {
+            x: self.x + rhs.x,
+            y: self.y + rhs.y,
+            z: self.z + rhs.z,
+            w: self.w + rhs.w
+        }
+    }
+}
+
+impl Sub<Self> for Vector4D {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self {
+            x: self.x - rhs.x,
+            y: self.y - rhs.y,
+            z: self.z - rhs.z,
+            w: self.w - rhs.w
+        }
+    }
+}
+
+impl Mul<Self> for Vector4D {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        Self {
+            x: self.x * rhs.x,
+            y: self.y * rhs.y,
+            z: self.z * rhs.z,
+            w: self.w * rhs.w
+        }
+    }
+}
+
+impl Mul<Matrix> for Vector4D {
+    type Output = MatrixResult;
+
+    fn mul(self, rhs: Matrix) -> Self::Output {
+        rhs * self
+    }
+}
+
+impl Mul<f32> for Vector4D {
+    type Output = Vector4D;
+
+    fn mul(self, rhs: f32) -> Self::Output {
+        Self {
+            x: self.x * rhs,
+            y: self.y * rhs,
+            z: self.z * rhs,
+            w: self.w * rhs
+        }
+    }
+}
+
+impl Mul<Vector4D> for f32 {
+    type Output = Vector4D;
+
+    fn mul(self, rhs: Vector4D) -> Self::Output {
+        rhs * self
+    }
+}
+
+impl Neg for Vector4D {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        Self {
+            x: -self.x,
+            y: -self.y,
+            z: -self.z,
+            w: -self.w
+        }
+    }
+}
+
+impl TryFrom<Matrix> for Vector4D {
+    type Error = MatrixError;
+
+    fn try_from(other: Matrix) -> Result<Self, Self::Error> {
+        if other.width != 1 || other.height != 4 {
+            return Err(MatrixError::DimensionMismatch {
+                operation: "cast matrix to Vector4D",
+                expected: Dimensions::new(4, 1),
+                actual: other.get_dimensions()
+            });
+        }
+        Ok(Self {
+            x: other[0][0],
+            y: other[1][0],
+            z: other[2][0],
+            w: other[3][0]
+        })
+    }
+}
+
+vector_ops!(Vector4D, x, y, z, w);
+//#endregion
+
+//#region Quaternion
+// unit quaternions represent orientations without the gimbal lock
+// inherent to euler angles, angles are in radians
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Quaternion {
+    pub w: f32,
+    pub x: f32,
+    pub y: f32,
+    pub z: f32
+}
+
+impl Quaternion {
+    pub const IDENTITY: Self = Self { w: 1.0, x: 0.0, y: 0.0, z: 0.0 };
+
+    pub fn new(w: f32, x: f32, y: f32, z: f32) -> Self {
+        Self { w, x, y, z }
+    }
+
+    pub fn from_axis_angle(axis: Vector3D, angle: f32) -> Self {
+        let length = axis.length();
+        if length == 0.0 {
+            return Quaternion::IDENTITY;
+        }
+        let half_sin = (angle / 2.0).sin() / length;
+        Self {
+            w: (angle / 2.0).cos(),
+            x: axis.x * half_sin,
+            y: axis.y * half_sin,
+            z: axis.z * half_sin
+        }
+    }
+
+    // applies the rotations in the same order as Matrix::rotation_x * rotation_y * rotation_z
+    pub fn from_euler(euler: Vector3D) -> Self {
+        Quaternion::from_axis_angle(Vector3D::X, euler.x)
+            * Quaternion::from_axis_angle(Vector3D::Y, euler.y)
+            * Quaternion::from_axis_angle(Vector3D::Z, euler.z)
+    }
+
+    // inverse of from_euler, when y is +-90 degrees z is folded into x
+    pub fn to_euler(self) -> Vector3D {
+        let q = self.normalize();
+        let sin_y = (2.0 * (q.x * q.z + q.w * q.y)).clamp(-1.0, 1.0);
+        let y = sin_y.asin();
+        if sin_y.abs() > 0.9999 {
+            let x = (2.0 * (q.y * q.z + q.w * q.x)).atan2(1.0 - 2.0 * (q.x * q.x + q.z * q.z));
+            Vector3D::new(x, y, 0.0)
+        }
+        else {
+            let x = (-2.0 * (q.y * q.z - q.w * q.x)).atan2(1.0 - 2.0 * (q.x * q.x + q.y * q.y));
+            let z = (-2.0 * (q.x * q.y - q.w * q.z)).atan2(1.0 - 2.0 * (q.y * q.y + q.z * q.z));
+            Vector3D::new(x, y, z)
+        }
+    }
+
+    pub fn dot(&self, other: &Quaternion) -> f32 {
+        self.w * other.w + self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    pub fn magnitude(&self) -> f32 {
+        self.dot(self).sqrt()
+    }
+
+    pub fn normalize(&self) -> Self {
+        let magnitude = self.magnitude();
+        if magnitude == 0.0 {
+            return Quaternion::IDENTITY;
+        }
+        Self {
+            w: self.w / magnitude,
+            x: self.x / magnitude,
+            y: self.y / magnitude,
+            z: self.z / magnitude
+        }
+    }
+
+    pub fn conjugate(&self) -> Self {
+        Self {
+            w: self.w,
+            x: -self.x,
+            y: -self.y,
+            z: -self.z
+        }
+    }
+
+    pub fn inverse(&self) -> Self {
+        let magnitude_squared = self.dot(self);
+        if magnitude_squared == 0.0 {
+            return Quaternion::IDENTITY;
+        }
+        let conjugate = self.conjugate();
+        Self {
+            w: conjugate.w / magnitude_squared,
+            x: conjugate.x / magnitude_squared,
+            y: conjugate.y / magnitude_squared,
+            z: conjugate.z / magnitude_squared
+        }
+    }
+
+    pub fn rotate_vector(&self, vector: Vector3D) -> Vector3D {
+        let point = Quaternion::new(0.0, vector.x, vector.y, vector.z);
+        let rotated = *self * point * self.inverse();
+        Vector3D::new(rotated.x, rotated.y, rotated.z)
+    }
+
+    // spherical linear interpolation, taking the shortest path between the two orientations
+    pub fn slerp(&self, other: &Quaternion, t: f32) -> Self {
+        let start = self.normalize();
+        let mut end = other.normalize();
+        let mut cos_theta = start.dot(&end);
+        if cos_theta < 0.0 {
+            end = Quaternion::new(-end.w, -end.x, -end.y, -end.z);
+            cos_theta = -cos_theta;
+        }
+        // fall back to linear interpolation when the orientations are nearly equal
+        // to avoid dividing by sin(theta) ~ 0
+        let (start_scale, end_scale) = if cos_theta > 0.9995 {
+            (1.0 - t, t)
+        }
+        else {
+            let theta = cos_theta.acos();
+            let sin_theta = theta.sin();
+            (
+                ((1.0 - t) * theta).sin() / sin_theta,
+                (t * theta).sin() / sin_theta
+            )
+        };
+        Quaternion::new(
+            start.w * start_scale + end.w * end_scale,
+            start.x * start_scale + end.x * end_scale,
+            start.y * start_scale + end.y * end_scale,
+            start.z * start_scale + end.z * end_scale
+        ).normalize()
+    }
+
+    // homogeneous 4x4 rotation matrix
+    pub fn to_matrix(self) -> Matrix {
+        let q = self.normalize();
+        let (xx, yy, zz) = (q.x * q.x, q.y * q.y, q.z * q.z);
+        let (xy, xz, yz) = (q.x * q.y, q.x * q.z, q.y * q.z);
+        let (wx, wy, wz) = (q.w * q.x, q.w * q.y, q.w * q.z);
+        Matrix::homogeneous([
+            1.0 - 2.0 * (yy + zz), 2.0 * (xy - wz), 2.0 * (xz + wy), 0.0,
+            2.0 * (xy + wz), 1.0 - 2.0 * (xx + zz), 2.0 * (yz - wx), 0.0,
+            2.0 * (xz - wy), 2.0 * (yz + wx), 1.0 - 2.0 * (xx + yy), 0.0,
+            0.0, 0.0, 0.0, 1.0
+        ])
+    }
+}
+
+impl Default for Quaternion {
+    fn default() -> Self {
+        Quaternion::IDENTITY
+    }
+}
+
+impl fmt::Display for Quaternion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "(W: {}, X: {}, Y: {}, Z: {})", self.w, self.x, self.y, self.z)
+    }
+}
+
+// hamilton product, the result applies rhs first then self
+impl Mul<Self> for Quaternion {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        Self {
+            w: self.w * rhs.w - self.x * rhs.x - self.y * rhs.y - self.z * rhs.z,
+            x: self.w * rhs.x + self.x * rhs.w + self.y * rhs.z - self.z * rhs.y,
+            y: self.w * rhs.y - self.x * rhs.z + self.y * rhs.w + self.z * rhs.x,
+            z: self.w * rhs.z + self.x * rhs.y - self.y * rhs.x + self.z * rhs.w
+        }
+    }
+}
+
+impl Mul<Vector3D> for Quaternion {
+    type Output = Vector3D;
+
+    fn mul(self, rhs: Vector3D) -> Self::Output {
+        self.rotate_vector(rhs)
+    }
+}
+//#endregion
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOLERANCE: f32 = 1e-4;
+
+    fn assert_matrix_eq(actual: &Matrix, expected: &Matrix) {
+        assert_eq!(actual.get_dimensions(), expected.get_dimensions());
+        for row in 0..expected.height {
+            for col in 0..expected.width {
+                assert!(
+                    (actual[row][col] - expected[row][col]).abs() < TOLERANCE,
+                    "mismatch at ({}, {}): {} != {}", row, col, actual, expected
+                );
+            }
+        }
+    }
+
+    fn assert_vector_eq(actual: Vector3D, expected: Vector3D) {
+        assert!((actual - expected).length() < TOLERANCE, "{} != {}", actual, expected);
+    }
+
+    #[test]
+    fn from_vec_requires_exact_length() {
+        let short = Matrix::from_vec(2, 2, vec![1.0, 2.0, 3.0]);
+        assert_eq!(short, Err(MatrixError::InvalidLength { dimensions: Dimensions::new(2, 2), length: 3 }));
+        let long = Matrix::from_vec(2, 2, vec![1.0; 5]);
+        assert_eq!(long, Err(MatrixError::InvalidLength { dimensions: Dimensions::new(2, 2), length: 5 }));
+        assert!(Matrix::from_vec(3, 2, vec![0.0; 6]).is_ok());
+    }
+
+    #[test]
+    fn multiplies_and_transposes() {
+        let a = Matrix::from_vec(3, 2, vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]).unwrap();
+        let b = Matrix::from_vec(2, 3, vec![7.0, 8.0, 9.0, 10.0, 11.0, 12.0]).unwrap();
+        let product = (a.clone() * b.clone()).unwrap();
+        assert_matrix_eq(&product, &Matrix::from_vec(2, 2, vec![58.0, 64.0, 139.0, 154.0]).unwrap());
+        assert_eq!(a.transpose().get_dimensions(), Dimensions::new(3, 2));
+        assert_eq!(a.transpose().row(0).unwrap(), vec![1.0, 4.0]);
+        assert_eq!(a.transpose().transpose(), a);
+        // chained operations carry the first error through
+        let chained = a.clone() * a.clone() * b;
+        assert!(matches!(chained, Err(MatrixError::DimensionMismatch { .. })));
+        assert!(matches!(a.clone() + a.transpose(), Err(MatrixError::DimensionMismatch { .. })));
+    }
+
+    #[test]
+    fn reports_out_of_bounds_access() {
+        let mut matrix = Matrix::identity(2);
+        let expected = MatrixError::IndexOutOfBounds { index: (2, 0), dimensions: Dimensions::new(2, 2) };
+        assert_eq!(matrix.get(2, 0), Err(expected));
+        assert_eq!(matrix.set(2, 0, 1.0), Err(expected));
+        assert!(matrix.column(2).is_err());
+        assert_eq!(matrix.set(1, 0, 3.0), Ok(()));
+        assert_eq!(matrix.get(1, 0), Ok(3.0));
+    }
+
+    #[test]
+    fn determinant_and_inverse() {
+        let matrix = Matrix::from_vec(3, 3, vec![2.0, 0.0, 1.0, 1.0, 3.0, 2.0, 1.0, 1.0, 2.0]).unwrap();
+        assert!((matrix.determinant().unwrap() - 6.0).abs() < TOLERANCE);
+        let inverse = matrix.inverse().unwrap();
+        assert_matrix_eq(&(matrix * inverse).unwrap(), &Matrix::identity(3));
+
+        // requires a row swap to find a pivot
+        let swapped = Matrix::from_vec(2, 2, vec![0.0, 1.0, 1.0, 0.0]).unwrap();
+        assert!((swapped.determinant().unwrap() + 1.0).abs() < TOLERANCE);
+        assert_matrix_eq(&swapped.inverse().unwrap(), &swapped);
+
+        let transform = (Matrix::translation(Vector3D::new(1.0, 2.0, 3.0)) * Matrix::rotation_y(0.7)).unwrap();
+        let (point, _) = transform.inverse().unwrap().transform_point(Vector3D::new(1.0, 2.0, 3.0)).unwrap();
+        assert_vector_eq(point, Vector3D::ZERO);
+    }
+
+    #[test]
+    fn rejects_non_square_and_singular_matrices() {
+        let rectangle = Matrix::new(3, 2);
+        assert_eq!(rectangle.determinant(), Err(MatrixError::NotSquare { dimensions: Dimensions::new(2, 3) }));
+        assert_eq!(rectangle.inverse(), Err(MatrixError::NotSquare { dimensions: Dimensions::new(2, 3) }));
+
+        let singular = Matrix::from_vec(2, 2, vec![1.0, 2.0, 2.0, 4.0]).unwrap();
+        assert_eq!(singular.determinant(), Ok(0.0));
+        assert_eq!(singular.inverse(), Err(MatrixError::Singular));
+    }
+
+    #[test]
+    fn pivot_tolerance_is_relative_to_scale() {
+        // tiny but well conditioned matrices are still invertible
+        let small = Matrix::identity(3) * 1e-6;
+        let inverse = small.inverse().unwrap();
+        assert_matrix_eq(&(inverse * 1e-6), &Matrix::identity(3));
+
+        // rounding leaves a small residue in the last pivot of a scaled singular matrix
+        let singular = Matrix::from_vec(3, 3, vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0]).unwrap() * 1e6;
+        assert_eq!(singular.determinant(), Ok(0.0));
+        assert_eq!(singular.inverse(), Err(MatrixError::Singular));
+    }
+
+    #[test]
+    fn transforms_points() {
+        let transform = (Matrix::translation(Vector3D::new(1.0, 0.0, 0.0))
+            * Matrix::scaling(Vector3D::new(2.0, 2.0, 2.0))
+            * Matrix::rotation_z(std::f32::consts::FRAC_PI_2)).unwrap();
+        let (point, w) = transform.transform_point(Vector3D::X).unwrap();
+        assert_vector_eq(point, Vector3D::new(1.0, 2.0, 0.0));
+        assert_eq!(w, 1.0);
+        assert!(Matrix::identity(3).transform_point(Vector3D::X).is_err());
+        assert!(Matrix::identity(3).to_array().is_err());
+    }
+
+    #[test]
+    fn converts_vectors_through_matrices() {
+        let vector = Vector4D::new(1.0, 2.0, 3.0, 4.0);
+        assert_eq!(Vector4D::try_from(Matrix::from(vector)), Ok(vector));
+        assert!(Vector4D::try_from(Matrix::new(1, 3)).is_err());
+    }
+
+    #[test]
+    fn quaternion_matches_rotation_matrices() {
+        let euler = Vector3D::new(0.3, -0.5, 1.1);
+        let expected = (Matrix::rotation_x(euler.x) * Matrix::rotation_y(euler.y) * Matrix::rotation_z(euler.z)).unwrap();
+        let rotation = Quaternion::from_euler(euler);
+        assert_matrix_eq(&rotation.to_matrix(), &expected);
+
+        let point = Vector3D::new(1.0, -2.0, 0.5);
+        let (transformed, _) = expected.transform_point(point).unwrap();
+        assert_vector_eq(rotation * point, transformed);
+    }
+
+    #[test]
+    fn quaternion_euler_round_trip() {
+        let euler = Vector3D::new(0.3, -0.5, 1.1);
+        assert_vector_eq(Quaternion::from_euler(euler).to_euler(), euler);
+
+        // at gimbal lock the orientation survives even though the angles change
+        let locked = Vector3D::new(0.2, std::f32::consts::FRAC_PI_2, 0.4);
+        let round_trip = Quaternion::from_euler(Quaternion::from_euler(locked).to_euler());
+        assert_matrix_eq(&round_trip.to_matrix(), &Quaternion::from_euler(locked).to_matrix());
+    }
+
+    #[test]
+    fn quaternion_inverse_and_slerp() {
+        let rotation = Quaternion::from_axis_angle(Vector3D::new(1.0, 1.0, 0.0), 0.8);
+        let point = Vector3D::new(0.5, 2.0, -1.0);
+        assert_vector_eq(rotation.inverse() * (rotation * point), point);
+        let identity = rotation * rotation.inverse();
+        assert!((identity.w - 1.0).abs() < TOLERANCE);
+
+        let start = Quaternion::IDENTITY;
+        let end = Quaternion::from_axis_angle(Vector3D::Z, std::f32::consts::FRAC_PI_2);
+        assert_eq!(start.slerp(&end, 0.0), start);
+        let halfway = start.slerp(&end, 0.5);
+        assert_vector_eq(halfway * Vector3D::X, Vector3D::new(0.5f32.sqrt(), 0.5f32.sqrt(), 0.0));
+        // the negated quaternion is the same orientation, slerp takes the short path
+        let negated = Quaternion::new(-end.w, -end.x, -end.y, -end.z);
+        assert_vector_eq(start.slerp(&negated, 0.5) * Vector3D::X, halfway * Vector3D::X);
+    }
+
+    #[test]
+    fn vector3d_products_and_lengths() {
+        let a = Vector3D::new(1.0, 2.0, 3.0);
+        let b = Vector3D::new(4.0, -5.0, 6.0);
+        assert_eq!(a.dot(&b), 12.0);
+        assert_eq!(Vector3D::X.cross(&Vector3D::Y), Vector3D::Z);
+        let cross = a.cross(&b);
+        assert_eq!(cross, Vector3D::new(27.0, 6.0, -13.0));
+        // perpendicular to both operands
+        assert_eq!(cross.dot(&a), 0.0);
+        assert_eq!(cross.dot(&b), 0.0);
+        assert_eq!(Vector3D::new(2.0, 3.0, 6.0).length(), 7.0);
+        assert_eq!(a.length_squared(), 14.0);
+        assert_eq!(a.distance(&(a + Vector3D::new(2.0, 3.0, 6.0))), 7.0);
+        assert_eq!(a.distance_squared(&b), 67.0);
+    }
+
+    #[test]
+    fn vector3d_normalize() {
+        let unit = Vector3D::new(0.0, 3.0, -4.0).normalize();
+        assert_vector_eq(unit, Vector3D::new(0.0, 0.6, -0.8));
+        assert!((unit.length() - 1.0).abs() < TOLERANCE);
+        // the zero vector has no direction, it stays zero rather than becoming NaN
+        assert_eq!(Vector3D::ZERO.normalize(), Vector3D::ZERO);
+    }
+
+    #[test]
+    fn vector3d_reflect_lerp_min_max() {
+        let incoming = Vector3D::new(1.0, -1.0, 0.5);
+        assert_eq!(incoming.reflect(&Vector3D::Y), Vector3D::new(1.0, 1.0, 0.5));
+        let a = Vector3D::new(0.0, 10.0, -2.0);
+        let b = Vector3D::new(4.0, 0.0, 2.0);
+        assert_eq!(a.lerp(&b, 0.0), a);
+        assert_eq!(a.lerp(&b, 1.0), b);
+        assert_eq!(a.lerp(&b, 0.25), Vector3D::new(1.0, 7.5, -1.0));
+        assert_eq!(a.min(&b), Vector3D::new(0.0, 0.0, -2.0));
+        assert_eq!(a.max(&b), Vector3D::new(4.0, 10.0, 2.0));
+    }
+
+    #[test]
+    fn vector3d_operators() {
+        let mut v = Vector3D::new(1.0, 2.0, 3.0);
+        assert_eq!(v + Vector3D::ONE, Vector3D::new(2.0, 3.0, 4.0));
+        assert_eq!(v - Vector3D::ONE, Vector3D::new(0.0, 1.0, 2.0));
+        assert_eq!(v * Vector3D::new(2.0, 0.5, -1.0), Vector3D::new(2.0, 1.0, -3.0));
+        assert_eq!(2.0 * v, v * 2.0);
+        assert_eq!(v / 2.0, Vector3D::new(0.5, 1.0, 1.5));
+        assert_eq!(-v, Vector3D::new(-1.0, -2.0, -3.0));
+        v += Vector3D::ONE;
+        v *= 2.0;
+        v -= Vector3D::new(0.0, 0.0, 8.0);
+        v /= Vector3D::new(4.0, 2.0, 1.0);
+        assert_eq!(v, Vector3D::new(1.0, 3.0, 0.0));
+        assert_eq!(Vector3D::from(Vector2D::new(1.0, 2.0)), Vector3D::new(1.0, 2.0, 0.0));
+        assert_eq!(v.extend(1.0), Vector4D::new(1.0, 3.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn vector2d_products_and_lengths() {
+        let a = Vector2D::new(3.0, 4.0);
+        let b = Vector2D::new(-2.0, 1.0);
+        assert_eq!(a.dot(&b), -2.0);
+        // positive when the second vector is anticlockwise of the first
+        assert_eq!(Vector2D::X.cross(&Vector2D::Y), 1.0);
+        assert_eq!(Vector2D::Y.cross(&Vector2D::X), -1.0);
+        assert_eq!(a.cross(&b), 11.0);
+        assert_eq!(a.perpendicular(), Vector2D::new(-4.0, 3.0));
+        assert_eq!(a.perpendicular().dot(&a), 0.0);
+        assert_eq!(a.length(), 5.0);
+        assert_eq!(a.length_squared(), 25.0);
+        assert_eq!(a.distance(&Vector2D::ZERO), 5.0);
+        assert_eq!(a.distance_squared(&b), 34.0);
+    }
+
+    #[test]
+    fn vector2d_normalize_reflect_lerp() {
+        let unit = Vector2D::new(3.0, 4.0).normalize();
+        assert!((unit.x - 0.6).abs() < TOLERANCE && (unit.y - 0.8).abs() < TOLERANCE);
+        assert_eq!(Vector2D::ZERO.normalize(), Vector2D::ZERO);
+        assert_eq!(Vector2D::new(2.0, -3.0).reflect(&Vector2D::Y), Vector2D::new(2.0, 3.0));
+        assert_eq!(Vector2D::new(1.0, 1.0).reflect(&Vector2D::X), Vector2D::new(-1.0, 1.0));
+        let (a, b) = (Vector2D::new(-4.0, 2.0), Vector2D::new(4.0, 6.0));
+        assert_eq!(a.lerp(&b, 0.5), Vector2D::new(0.0, 4.0));
+        assert_eq!(a.lerp(&b, 1.0), b);
+        assert_eq!(a.min(&b), a);
+        assert_eq!(a.max(&Vector2D::ZERO), Vector2D::new(0.0, 2.0));
+    }
+
+    #[test]
+    fn vector2d_operators() {
+        let mut v = Vector2D::new(6.0, -2.0);
+        assert_eq!(v + Vector2D::ONE, Vector2D::new(7.0, -1.0));
+        assert_eq!(v - Vector2D::ONE, Vector2D::new(5.0, -3.0));
+        assert_eq!(v * Vector2D::new(0.5, 2.0), Vector2D::new(3.0, -4.0));
+        assert_eq!(v * 0.5, Vector2D::new(3.0, -1.0));
+        assert_eq!(-v, Vector2D::new(-6.0, 2.0));
+        v /= 2.0;
+        v -= Vector2D::ONE;
+        assert_eq!(v, Vector2D::new(2.0, -2.0));
+    }
+
+    #[test]
+    fn vector4d_api() {
+        let a = Vector4D::new(1.0, 2.0, 3.0, 4.0);
+        let b = Vector4D::new(4.0, 3.0, 2.0, 1.0);
+        assert_eq!(a + b, Vector4D::new(5.0, 5.0, 5.0, 5.0));
+        assert_eq!(a - b, Vector4D::new(-3.0, -1.0, 1.0, 3.0));
+        assert_eq!(a * 2.0, Vector4D::new(2.0, 4.0, 6.0, 8.0));
+        assert_eq!(a.dot(&b), 20.0);
+        assert_eq!(Vector4D::new(1.0, 1.0, 1.0, 1.0).length(), 2.0);
+        assert_eq!(Vector4D::ZERO.normalize(), Vector4D::ZERO);
+        assert_eq!(a.lerp(&b, 0.5), Vector4D::new(2.5, 2.5, 2.5, 2.5));
+        assert_eq!(a.truncate(), Vector3D::new(1.0, 2.0, 3.0));
+        assert_eq!(Vector4D::new(2.0, 4.0, 6.0, 2.0).to_cartesian(), Vector3D::new(1.0, 2.0, 3.0));
+        // points at infinity are left undivided
+        assert_eq!(Vector4D::new(2.0, 4.0, 6.0, 0.0).to_cartesian(), Vector3D::new(2.0, 4.0, 6.0));
+    }
+}
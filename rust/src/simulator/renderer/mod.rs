@@ -1,6 +1,11 @@
 pub mod linearalgebra;
-use linearalgebra::{Matrix, Vector2D, Vector3D};
+pub mod rasterizer;
+use linearalgebra::{Matrix, MatrixError, MatrixResult, Quaternion, Vector2D, Vector3D, Vector4D};
 
+use super::simd;
+use super::dirty::Rect;
+
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Copy, Clone, Debug)]
 pub enum OriginPosition {
     TOPLEFT,
@@ -21,20 +26,174 @@ pub enum RenderMode {
     R3D
 }
 
+#[derive(Copy, Clone, Debug)]
+pub enum Projection {
+    // vertical field of view in degrees
+    Perspective { fov: f32, near: f32, far: f32 },
+    // height of the visible area in world units
+    Orthographic { height: f32, near: f32, far: f32 }
+}
+
+// a vertex after projection, depth is the normalised device z in 3D
+// (smaller is closer) and the model z in 2D
+#[derive(Copy, Clone, Debug, Default)]
+pub struct ProjectedVertex {
+    pub x: i32,
+    pub y: i32,
+    pub depth: f32
+}
+
+impl ProjectedVertex {
+    // perspective divide of a clip space point in front of the near plane
+    fn from_clip(point: Vector4D) -> Self {
+        Self {
+            x: (point.x / point.w) as i32,
+            y: (point.y / point.w) as i32,
+            depth: point.z / point.w
+        }
+    }
+}
+
+// reusable storage for projecting an object, kept between frames so
+// projection does not allocate once the buffers have grown to size
+#[derive(Default)]
+pub struct ProjectionBuffer {
+    positions: Vec<Vector3D>,
+    transformed: Vec<Vector4D>,
+    vertices: Vec<ProjectedVertex>,
+    // whether each vertex is in front of the near plane, always true in 2D
+    visible: Vec<bool>,
+    edges: Vec<(ProjectedVertex, ProjectedVertex)>,
+    // size of the buffer projected onto
+    viewport: (i32, i32)
+}
+
+impl ProjectionBuffer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // the vertices in front of the near plane
+    pub fn get_points(&self) -> impl Iterator<Item = &ProjectedVertex> {
+        self.vertices.iter().zip(self.visible.iter()).filter(|(_, visible)| **visible).map(|(vertex, _)| vertex)
+    }
+
+    // the edges from the last call to clip_edges
+    pub fn get_edges(&self) -> &[(ProjectedVertex, ProjectedVertex)] {
+        &self.edges
+    }
+
+    // builds the screen space edges between pairs of projected vertices,
+    // edges crossing the near plane are cut where they cross it in clip
+    // space and edges entirely behind it are dropped
+    pub fn clip_edges<I>(&mut self, edges: I)
+        where I: Iterator<Item = (usize, usize)>
+    {
+        self.edges.clear();
+        for (start, end) in edges {
+            let edge = match (self.visible[start], self.visible[end]) {
+                (true, true) => (self.vertices[start], self.vertices[end]),
+                (true, false) => (
+                    self.vertices[start],
+                    ProjectedVertex::from_clip(near_intersection(self.transformed[start], self.transformed[end]))
+                ),
+                (false, true) => (
+                    ProjectedVertex::from_clip(near_intersection(self.transformed[end], self.transformed[start])),
+                    self.vertices[end]
+                ),
+                (false, false) => continue
+            };
+            self.edges.push(edge);
+        }
+    }
+
+    // screen space rectangle covering every visible vertex and edge, and
+    // so every pixel drawn for the object, clamped to the buffer
+    pub fn get_bounds(&self) -> Option<Rect> {
+        let edge_points = self.edges
+            .iter()
+            .flat_map(|(start, end)| std::iter::once(start).chain(std::iter::once(end)));
+        let mut points = self.get_points().chain(edge_points);
+        let first = points.next()?;
+        let (mut min_x, mut min_y, mut max_x, mut max_y) = (first.x, first.y, first.x, first.y);
+        for vertex in points {
+            min_x = min_x.min(vertex.x);
+            min_y = min_y.min(vertex.y);
+            max_x = max_x.max(vertex.x);
+            max_y = max_y.max(vertex.y);
+        }
+        let (width, height) = self.viewport;
+        if max_x < 0 || max_y < 0 || min_x >= width || min_y >= height {
+            return None;
+        }
+        Some(Rect::from_bounds(min_x.max(0), min_y.max(0), max_x.min(width - 1), max_y.min(height - 1)))
+    }
+}
+
+// in clip space a point is in front of the near plane when z >= -w
+fn in_front_of_near_plane(point: &Vector4D) -> bool {
+    point.z + point.w >= 0.0
+}
+
+// where the edge from a point in front of the near plane to one behind it
+// crosses the plane
+fn near_intersection(inside: Vector4D, outside: Vector4D) -> Vector4D {
+    let inside_distance = inside.z + inside.w;
+    let outside_distance = outside.z + outside.w;
+    let t = inside_distance / (inside_distance - outside_distance);
+    inside + (outside - inside) * t
+}
+
 pub struct Camera {
-    transform: Transform,
-    view_plane_top_left: Vector2D,
-    view_plane_bottom_right: Vector2D,
-    view_plane_z: f32
+    pub transform: Transform,
+    projection: Projection
 }
 
 impl Camera {
     pub fn new() -> Self {
         Self {
-            transform: Transform::default(),
-            view_plane_top_left: Vector2D::new(-1.0, 1.0),
-            view_plane_bottom_right: Vector2D::new(1.0, -1.0),
-            view_plane_z: -1.0
+            transform: Transform::new(),
+            projection: Projection::Perspective {
+                fov: 90.0,
+                near: 0.1,
+                far: 1000.0
+            }
+        }
+    }
+
+    pub fn get_projection(&self) -> Projection {
+        self.projection
+    }
+
+    pub fn set_projection(&mut self, projection: Projection) {
+        self.projection = projection;
+    }
+
+    pub fn use_perspective(&mut self, fov: f32, near: f32, far: f32) {
+        self.set_projection(Projection::Perspective { fov, near, far });
+    }
+
+    pub fn use_orthographic(&mut self, height: f32, near: f32, far: f32) {
+        self.set_projection(Projection::Orthographic { height, near, far });
+    }
+
+    // the inverse of the camera transform, moves the world so the camera
+    // sits at the origin looking down -z
+    pub fn view_matrix(&self) -> MatrixResult {
+        self.transform.get_orientation().conjugate().to_matrix()
+            * Matrix::translation(-self.transform.position)
+    }
+
+    pub fn projection_matrix(&self, aspect: f32) -> Matrix {
+        match self.projection {
+            Projection::Perspective { fov, near, far } => {
+                Matrix::perspective(fov * (std::f32::consts::PI / 180.0), aspect, near, far)
+            },
+            Projection::Orthographic { height, near, far } => {
+                let half_height = height / 2.0;
+                let half_width = half_height * aspect;
+                Matrix::orthographic(-half_width, half_width, -half_height, half_height, near, far)
+            }
         }
     }
 }
@@ -58,6 +217,10 @@ impl Renderer {
         }
     }
 
+    pub fn get_mode(&self) -> RenderMode {
+        self.mode
+    }
+
     pub fn get_resolution(&self) -> (f32, f32) {
         (self.resolution_x, self.resolution_y)
     }
@@ -67,103 +230,135 @@ impl Renderer {
         self.resolution_y = resolution_y;
     }
 
-    fn get_origin(&self, window_size: (i32, i32)) -> (i32, i32) {
-        match self.origin {
-            OriginPosition::TOPLEFT => (0, 0),
-            OriginPosition::TOPRIGHT => (window_size.0, 0),
-            OriginPosition::BOTTOMLEFT => (0, window_size.1),
-            OriginPosition::BOTTOMRIGHT => window_size,
-            OriginPosition::TOPMIDDLE => (window_size.0 / 2, 0),
-            OriginPosition::BOTTOMMIDDLE => (window_size.0 / 2, window_size.1),
-            OriginPosition::MIDDLELEFT => (0, window_size.1 / 2),
-            OriginPosition::MIDDLERIGHT => (window_size.0, window_size.1 / 2),
-            OriginPosition::MIDDLEMIDDLE => (window_size.0 / 2, window_size.1 / 2),
+    pub fn get_camera(&self) -> &Camera {
+        &self.camera
+    }
+
+    pub fn get_camera_mut(&mut self) -> &mut Camera {
+        &mut self.camera
+    }
+
+    pub fn get_aspect_ratio(&self) -> f32 {
+        self.resolution_x / self.resolution_y
+    }
+
+    pub fn get_view_matrix(&self) -> MatrixResult {
+        self.camera.view_matrix()
+    }
+
+    pub fn get_projection_matrix(&self) -> Matrix {
+        self.camera.projection_matrix(self.get_aspect_ratio())
+    }
+
+    // maps normalised device coordinates (3D) or world coordinates (2D)
+    // to pixel coordinates relative to the chosen origin
+    pub fn get_viewport_matrix(&self, window_size: (i32, i32)) -> MatrixResult {
+        let (width, height) = (window_size.0 as f32, window_size.1 as f32);
+        let origin = match self.origin {
+            OriginPosition::TOPLEFT => Vector2D::new(0.0, 0.0),
+            OriginPosition::TOPRIGHT => Vector2D::new(width, 0.0),
+            OriginPosition::BOTTOMLEFT => Vector2D::new(0.0, height),
+            OriginPosition::BOTTOMRIGHT => Vector2D::new(width, height),
+            OriginPosition::TOPMIDDLE => Vector2D::new(width / 2.0, 0.0),
+            OriginPosition::BOTTOMMIDDLE => Vector2D::new(width / 2.0, height),
+            OriginPosition::MIDDLELEFT => Vector2D::new(0.0, height / 2.0),
+            OriginPosition::MIDDLERIGHT => Vector2D::new(width, height / 2.0),
+            OriginPosition::MIDDLEMIDDLE => Vector2D::new(width / 2.0, height / 2.0),
+        };
+        let translation = Matrix::translation(Vector3D::from(origin));
+        match self.mode {
+            RenderMode::R2D => Ok(translation),
+            // screen y grows downwards whereas ndc y grows upwards
+            RenderMode::R3D => translation * Matrix::scaling(Vector3D::new(width / 2.0, -height / 2.0, 1.0))
         }
     }
 
-    pub fn project_to_screen(&self, transform: &Transform, vertex: &Vector3D, window_size: (i32, i32)) -> (i32, i32) {
+    // the full model -> screen transform for an object, vertices multiplied
+    // by this only need the perspective divide applying
+    pub fn get_transform_matrix(&self, transform: &Transform, window_size: (i32, i32)) -> MatrixResult {
         match self.mode {
+            // the whole transform is applied in 2D as well, vertices used to
+            // all be drawn at the object's position ignoring rotation and scale
             RenderMode::R2D => {
-                let (x_origin, y_origin) = self.get_origin(window_size);
-                (
-                    transform.position.x as i32 + x_origin,
-                    transform.position.y as i32 + y_origin
-                )
+                self.get_viewport_matrix(window_size)? * transform.to_matrix()?
             },
             RenderMode::R3D => {
-                self.calculate_3d_projection(transform, vertex, self.get_origin(window_size))
+                self.get_viewport_matrix(window_size)?
+                    * self.get_projection_matrix()
+                    * self.get_view_matrix()?
+                    * transform.to_matrix()?
             }
         }
     }
 
-    pub fn calculate_3d_projection(&self, transform: &Transform, vertex: &Vector3D, origin_pos: (i32, i32)) -> (i32, i32) {
+    fn clips_near_plane(&self) -> bool {
+        match self.mode {
+            RenderMode::R2D => false,
+            RenderMode::R3D => true
+        }
+    }
+
+    // none if the point is behind the near plane
+    pub fn project_point(&self, transform_matrix: &Matrix, vertex: &Vector3D) -> Result<Option<(i32, i32)>, MatrixError> {
         #[cfg(feature="renderer_profile")]
-        let projection_calculation_timer = Instant::now();
+        let projection_calculation_timer = std::time::Instant::now();
 
-        let point = Vector3D::new(
-            vertex.x,
-            vertex.y,
-            vertex.z
-        );
+        let (point, w) = transform_matrix.transform_point(*vertex)?;
+        let point = Vector4D::new(point.x, point.y, point.z, w);
 
-        let rotation = transform.rotation * (std::f32::consts::PI / 180.0);
-    
-        // scale the initial point
-        let projected_point = Matrix::from(point * transform.scale);
-
-        let x_rotation_matrix = Matrix::from_vec(3, 3, vec![
-            1.0, 0.0, 0.0,
-            0.0, rotation.x.cos(), -rotation.x.sin(),
-            0.0, rotation.x.sin(), rotation.x.cos()
-        ]);
-
-        let y_rotation_matrix = Matrix::from_vec(3, 3, vec![
-            rotation.y.cos(), 0.0, -rotation.y.sin(),
-            0.0, 1.0, 0.0,
-            rotation.y.sin(), 0.0, rotation.y.cos()
-        ]);
-
-        let z_rotation_matrix = Matrix::from_vec(3, 3, vec![
-            rotation.z.cos(), -rotation.z.sin(), 0.0,
-            rotation.z.sin(), rotation.z.cos(), 0.0,
-            0.0, 0.0, 1.0
-        ]);
-
-        let rotation_matrix = x_rotation_matrix * y_rotation_matrix * z_rotation_matrix;
-
-        // multiplying rotation matrix with 3d column vector 
-        // produces another 3d column vector
-        let rotated_projection = Vector3D::from(rotation_matrix * projected_point)
-            + transform.position
-            - self.camera.transform.position;
-
-        let recording_screen_size = self.camera.view_plane_bottom_right
-            - self.camera.view_plane_top_left;
-
-        let mut x: f32 = -1.0;
-        let mut y: f32 = -1.0;
-
-        if rotated_projection.z != 0.0 {
-            x = (rotated_projection.x * self.resolution_x) / (rotated_projection.z / recording_screen_size.x) * self.camera.view_plane_z;
-            y = (rotated_projection.y * self.resolution_y) / (rotated_projection.z / recording_screen_size.y) * self.camera.view_plane_z;
+        let projected = if !self.clips_near_plane() || in_front_of_near_plane(&point) {
+            let vertex = ProjectedVertex::from_clip(point);
+            Some((vertex.x, vertex.y))
         }
+        else {
+            None
+        };
 
         #[cfg(feature="renderer_profile")]
-        println!("Projection Calculation Time: {}ms", projection_calculation_timer.elapsed().as_millis());
+        eprintln!("Projection Calculation Time: {}ms", projection_calculation_timer.elapsed().as_millis());
+
+        Ok(projected)
+    }
+
+    // projects a batch of vertices at once through the simd transform path
+    pub fn project_points<I>(&self, transform_matrix: &Matrix, vertices: I, window_size: (i32, i32), buffer: &mut ProjectionBuffer) -> Result<(), MatrixError>
+        where I: Iterator<Item = Vector3D>
+    {
+        let matrix = transform_matrix.to_array()?;
+        buffer.positions.clear();
+        buffer.positions.extend(vertices);
+        buffer.transformed.resize(buffer.positions.len(), Vector4D::ZERO);
+        simd::transform_points(&matrix, &buffer.positions, &mut buffer.transformed);
+        let clip_near = self.clips_near_plane();
+        buffer.visible.clear();
+        buffer.visible.extend(buffer.transformed.iter().map(|point| !clip_near || in_front_of_near_plane(point)));
+        buffer.vertices.clear();
+        buffer.vertices.extend(buffer.transformed.iter().zip(buffer.visible.iter()).map(|(point, visible)| {
+            if *visible {
+                ProjectedVertex::from_clip(*point)
+            }
+            else {
+                ProjectedVertex { x: -1, y: -1, depth: f32::INFINITY }
+            }
+        }));
+        buffer.edges.clear();
+        buffer.viewport = window_size;
+        Ok(())
+    }
 
-        (
-            x as i32 + origin_pos.0,
-            y as i32 + origin_pos.1,
-        )
-    
+    pub fn project_to_screen(&self, transform: &Transform, vertex: &Vector3D, window_size: (i32, i32)) -> Result<Option<(i32, i32)>, MatrixError> {
+        self.project_point(&self.get_transform_matrix(transform, window_size)?, vertex)
     }
 }
 
-#[derive(Default, Copy, Clone)]
+#[derive(Default, Copy, Clone, PartialEq)]
 pub struct Transform {
     pub position: Vector3D,
-    pub rotation: Vector3D,
-    pub scale: Vector3D
+    // euler angles in degrees, kept in sync with the orientation once the
+    // transform has been given one, set through set_rotation
+    rotation: Vector3D,
+    pub scale: Vector3D,
+    orientation: Option<Quaternion>
 }
 
 impl Transform {
@@ -171,8 +366,69 @@ impl Transform {
         Self {
             position: Vector3D::ZERO,
             rotation: Vector3D::ZERO,
-            scale: Vector3D::ONE
+            scale: Vector3D::ONE,
+            orientation: None
+        }
+    }
+
+    // scale, then rotate, then translate
+    pub fn to_matrix(self) -> MatrixResult {
+        Matrix::translation(self.position)
+            * self.get_orientation().to_matrix()
+            * Matrix::scaling(self.scale)
+    }
+
+    // euler angles in degrees
+    pub fn get_rotation(&self) -> Vector3D {
+        self.rotation
+    }
+
+    pub fn uses_quaternion(&self) -> bool {
+        self.orientation.is_some()
+    }
+
+    pub fn get_orientation(&self) -> Quaternion {
+        match self.orientation {
+            Some(orientation) => orientation,
+            None => Quaternion::from_euler(self.rotation * (std::f32::consts::PI / 180.0))
+        }
+    }
+
+    // orientations that are already unit length are kept as given so
+    // setting a saved orientation doesn't drift it
+    pub fn set_orientation(&mut self, orientation: Quaternion) {
+        let orientation = if (orientation.magnitude() - 1.0).abs() < 1e-6 {
+            orientation
         }
+        else {
+            orientation.normalize()
+        };
+        self.rotation = orientation.to_euler() * (180.0 / std::f32::consts::PI);
+        self.orientation = Some(orientation);
+    }
+
+    // rotation applied in world space
+    pub fn rotate_by(&mut self, rotation: Quaternion) {
+        self.set_orientation(rotation * self.get_orientation());
+    }
+
+    // rotation applied in the transforms local space, angle in degrees
+    pub fn rotate_around(&mut self, axis: Vector3D, angle: f32) {
+        self.set_orientation(
+            self.get_orientation() * Quaternion::from_axis_angle(axis, angle * (std::f32::consts::PI / 180.0))
+        );
+    }
+
+    // position and scale are linearly interpolated, orientation is slerped
+    pub fn interpolate(&self, other: &Transform, t: f32) -> Transform {
+        let mut result = Transform {
+            position: self.position.lerp(&other.position, t),
+            rotation: self.rotation,
+            scale: self.scale.lerp(&other.scale, t),
+            orientation: None
+        };
+        result.set_orientation(self.get_orientation().slerp(&other.get_orientation(), t));
+        result
     }
 
     pub fn set_position(&mut self, x: f32, y: f32, z: f32) {
@@ -181,10 +437,12 @@ impl Transform {
         self.position.z = z;
     }
 
+    // switches the transform back to euler angles
     pub fn set_rotation(&mut self, x: f32, y: f32, z: f32) {
         self.rotation.x = x;
         self.rotation.y = y;
         self.rotation.z = z;
+        self.orientation = None;
     }
 
     pub fn set_scale(&mut self, x: f32, y: f32, z: f32) {
@@ -218,6 +476,10 @@ impl Transform {
     }
 
     pub fn rotate_x(&mut self, x: f32) {
+        if self.uses_quaternion() {
+            self.rotate_around(Vector3D::X, x);
+            return;
+        }
         self.rotation.x += x;
         if self.rotation.x < 0.0 {
             self.rotation.x += 360.0;
@@ -228,6 +490,10 @@ impl Transform {
     }
 
     pub fn rotate_y(&mut self, y: f32) {
+        if self.uses_quaternion() {
+            self.rotate_around(Vector3D::Y, y);
+            return;
+        }
         self.rotation.y += y;
         if self.rotation.y < 0.0 {
             self.rotation.y += 360.0;
@@ -238,6 +504,10 @@ impl Transform {
     }
 
     pub fn rotate_z(&mut self, z: f32) {
+        if self.uses_quaternion() {
+            self.rotate_around(Vector3D::Z, z);
+            return;
+        }
         self.rotation.z += z;
         if self.rotation.z < 0.0 {
             self.rotation.z += 360.0;
@@ -272,6 +542,112 @@ impl std::fmt::Debug for Transform {
             .field("Position", &format!("{}", self.position))
             .field("Rotation", &format!("{}", self.rotation))
             .field("Scale", &format!("{}", self.scale))
+            .field("Orientation", &self.orientation.map(|orientation| format!("{}", orientation)))
             .finish()
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WINDOW: (i32, i32) = (100, 50);
+
+    fn setup(mode: RenderMode) -> (Renderer, Matrix) {
+        // in 3D the origin is where the centre of the view is drawn
+        let mut renderer = Renderer::new(mode, OriginPosition::MIDDLEMIDDLE);
+        renderer.set_resolution(WINDOW.0 as f32, WINDOW.1 as f32);
+        let matrix = renderer.get_transform_matrix(&Transform::new(), WINDOW).unwrap();
+        (renderer, matrix)
+    }
+
+    // projection truncates to whole pixels, so allow for rounding either way
+    fn assert_near(actual: (i32, i32), expected: (i32, i32)) {
+        assert!(
+            (actual.0 - expected.0).abs() <= 1 && (actual.1 - expected.1).abs() <= 1,
+            "{:?} != {:?}", actual, expected
+        );
+    }
+
+    #[test]
+    fn maps_ndc_corners_to_the_viewport() {
+        let (renderer, matrix) = setup(RenderMode::R3D);
+        // with a 90 degree fov and 2:1 aspect, z = -1 spans x -2..2 and y -1..1
+        let project = |x, y, z| renderer.project_point(&matrix, &Vector3D::new(x, y, z)).unwrap().unwrap();
+        assert_near(project(-2.0, 1.0, -1.0), (0, 0));
+        assert_near(project(2.0, 1.0, -1.0), (100, 0));
+        assert_near(project(-2.0, -1.0, -1.0), (0, 50));
+        assert_near(project(2.0, -1.0, -1.0), (100, 50));
+        assert_eq!(project(0.0, 0.0, -5.0), (50, 25));
+    }
+
+    #[test]
+    fn drops_points_behind_the_camera() {
+        let (renderer, matrix) = setup(RenderMode::R3D);
+        let project = |z| renderer.project_point(&matrix, &Vector3D::new(0.0, 0.0, z)).unwrap();
+        assert_eq!(project(1.0), None);
+        // in front of the camera but closer than the near plane
+        assert_eq!(project(-0.05), None);
+        assert_eq!(project(-0.2), Some((50, 25)));
+
+        // nothing is clipped in 2D
+        let (renderer, matrix) = setup(RenderMode::R2D);
+        assert_eq!(renderer.project_point(&matrix, &Vector3D::new(3.0, 4.0, 10.0)).unwrap(), Some((53, 29)));
+    }
+
+    #[test]
+    fn clips_edges_at_the_near_plane() {
+        let (renderer, matrix) = setup(RenderMode::R3D);
+        let mut buffer = ProjectionBuffer::new();
+        let vertices = [
+            Vector3D::new(1.0, 0.0, -1.0),
+            Vector3D::new(1.0, 0.0, 1.0),
+            Vector3D::new(-1.0, 0.0, 1.0),
+            Vector3D::new(-1.0, 0.0, -1.0)
+        ];
+        renderer.project_points(&matrix, vertices.iter().copied(), WINDOW, &mut buffer).unwrap();
+        assert_eq!(buffer.get_points().count(), 2);
+        buffer.clip_edges([(0, 1), (2, 3), (1, 2), (3, 0)].iter().copied());
+
+        // the edge behind the camera is dropped entirely
+        let edges = buffer.get_edges();
+        assert_eq!(edges.len(), 3);
+        // x = 1 meets the near plane at z = -0.1, ten times further out
+        // than at z = -1, and at the nearest depth
+        let (start, end) = edges[0];
+        assert_near((start.x, start.y), (75, 25));
+        assert_near((end.x, end.y), (300, 25));
+        assert!((end.depth + 1.0).abs() < 1e-4);
+        // the clipped end is always the one that was behind the plane
+        let (start, end) = edges[1];
+        assert_near((start.x, start.y), (-200, 25));
+        assert_near((end.x, end.y), (25, 25));
+        assert!((start.depth + 1.0).abs() < 1e-4);
+        let (start, end) = edges[2];
+        assert_near((start.x, start.y), (25, 25));
+        assert_near((end.x, end.y), (75, 25));
+
+        // bounds take in the clipped ends, clamped to the buffer
+        assert_eq!(buffer.get_bounds(), Some(Rect::new(0, 25, 100, 1)));
+    }
+
+    #[test]
+    fn keeps_rotation_and_orientation_in_sync() {
+        let mut transform = Transform::new();
+        transform.set_rotation(0.0, 90.0, 0.0);
+        assert!(!transform.uses_quaternion());
+        let euler = transform.get_orientation();
+
+        transform.set_orientation(Quaternion::from_axis_angle(Vector3D::Y, std::f32::consts::FRAC_PI_2));
+        assert!(transform.uses_quaternion());
+        assert!((transform.get_rotation() - Vector3D::new(0.0, 90.0, 0.0)).length() < 1e-3);
+        assert!(transform.get_orientation().dot(&euler).abs() > 1.0 - 1e-5);
+
+        // setting the rotation afterwards takes effect rather than being ignored
+        transform.rotate_y(90.0);
+        transform.set_rotation(0.0, 0.0, 0.0);
+        assert!(!transform.uses_quaternion());
+        assert_eq!(transform.get_rotation(), Vector3D::ZERO);
+        assert!(transform.get_orientation().dot(&Quaternion::IDENTITY).abs() > 1.0 - 1e-5);
+    }
+}
@@ -0,0 +1,542 @@
+// tile based rasterizer, projected primitives are binned into screen tiles
+// which are then rasterized in parallel, each tile is only ever touched by
+// one thread so the frame and depth buffers can be shared without locking
+//
+// primitives are grouped into layers, every layer is drawn over the ones
+// before it, the whole frame is rasterized in one pass with each tile
+// resetting its own depth between layers
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use super::super::dirty::Rect;
+use super::super::simd;
+
+pub const DEFAULT_TILE_SIZE: i32 = 64;
+
+// a worker thread is only spawned for every this many tiles with something
+// to draw, as spawning costs more than rasterizing a few sparse tiles
+const MIN_TILES_PER_THREAD: usize = 8;
+
+#[derive(Copy, Clone, Debug)]
+pub enum Primitive {
+    Point {
+        position: (i32, i32),
+        depth: f32,
+        color: u32
+    },
+    Line {
+        start: (i32, i32),
+        end: (i32, i32),
+        start_depth: f32,
+        end_depth: f32,
+        color: u32
+    }
+}
+
+// start, end, start depth and end depth
+type LineEnds = ((i32, i32), (i32, i32), f32, f32);
+
+// cuts a line down to the part inside the inclusive bounds (liang-barsky),
+// none if no part of it is inside
+fn clip_line(
+    start: (i32, i32),
+    end: (i32, i32),
+    start_depth: f32,
+    end_depth: f32,
+    (min_x, min_y, max_x, max_y): (i32, i32, i32, i32)
+) -> Option<LineEnds> {
+    // in f64 so saturated coordinates can't overflow
+    let origin = (f64::from(start.0), f64::from(start.1));
+    let delta = (f64::from(end.0) - origin.0, f64::from(end.1) - origin.1);
+    let (mut first, mut last) = (0.0f64, 1.0f64);
+    let edges = [
+        (-delta.0, origin.0 - f64::from(min_x)),
+        (delta.0, f64::from(max_x) - origin.0),
+        (-delta.1, origin.1 - f64::from(min_y)),
+        (delta.1, f64::from(max_y) - origin.1)
+    ];
+    for (p, q) in edges.iter() {
+        if *p == 0.0 {
+            if *q < 0.0 {
+                return None;
+            }
+            continue;
+        }
+        let t = q / p;
+        if *p < 0.0 {
+            first = first.max(t);
+        }
+        else {
+            last = last.min(t);
+        }
+    }
+    if first > last {
+        return None;
+    }
+    let point = |t: f64| (
+        (origin.0 + delta.0 * t).round() as i32,
+        (origin.1 + delta.1 * t).round() as i32
+    );
+    let depth = |t: f64| start_depth + (end_depth - start_depth) * t as f32;
+    Some((point(first), point(last), depth(first), depth(last)))
+}
+
+impl Primitive {
+    // inclusive screen space bounds (min x, min y, max x, max y)
+    fn get_bounds(&self) -> (i32, i32, i32, i32) {
+        match *self {
+            Primitive::Point { position, .. } => (position.0, position.1, position.0, position.1),
+            Primitive::Line { start, end, .. } => (
+                start.0.min(end.0),
+                start.1.min(end.1),
+                start.0.max(end.0),
+                start.1.max(end.1)
+            )
+        }
+    }
+}
+
+// raw view of a buffer shared between the worker threads, only sound
+// because every thread writes to a disjoint set of tiles
+#[derive(Copy, Clone)]
+struct SharedBuffer<T> {
+    pointer: *mut T,
+    length: usize
+}
+
+unsafe impl<T: Send> Send for SharedBuffer<T> {}
+unsafe impl<T: Send> Sync for SharedBuffer<T> {}
+
+impl<T> SharedBuffer<T> {
+    fn new(buffer: &mut [T]) -> Self {
+        Self {
+            pointer: buffer.as_mut_ptr(),
+            length: buffer.len()
+        }
+    }
+
+    unsafe fn get(&self, index: usize) -> *mut T {
+        assert!(index < self.length, "Index {} is outside of the shared buffer", index);
+        self.pointer.add(index)
+    }
+
+    // the elements from start up to (excluding) end
+    #[allow(clippy::mut_from_ref)]
+    unsafe fn slice(&self, start: usize, end: usize) -> &mut [T] {
+        assert!(start <= end && end <= self.length, "Range {}..{} is outside of the shared buffer", start, end);
+        std::slice::from_raw_parts_mut(self.pointer.add(start), end - start)
+    }
+}
+
+// everything a worker needs to write pixels
+#[derive(Copy, Clone)]
+struct Target {
+    frame: SharedBuffer<u32>,
+    depth: SharedBuffer<f32>,
+    width: i32,
+    use_depth: bool
+}
+
+pub struct TileRasterizer {
+    thread_count: usize,
+    tile_size: i32,
+    width: i32,
+    height: i32,
+    tiles: Vec<Rect>,
+    // per tile, the layer and index of every primitive touching it
+    bins: Vec<Vec<(u32, usize)>>,
+    primitives: Vec<Primitive>,
+    layer: u32,
+    depth_buffer: Vec<f32>,
+    // whether depth has been written since the buffer was last reset
+    depth_written: bool,
+    // when set only pixels inside these regions are drawn
+    clip_regions: Option<Vec<Rect>>
+}
+
+impl TileRasterizer {
+    pub fn new(thread_count: usize) -> Self {
+        Self {
+            thread_count: thread_count.max(1),
+            tile_size: DEFAULT_TILE_SIZE,
+            width: 0,
+            height: 0,
+            tiles: Vec::new(),
+            bins: Vec::new(),
+            primitives: Vec::new(),
+            layer: 0,
+            depth_buffer: Vec::new(),
+            depth_written: false,
+            clip_regions: None
+        }
+    }
+
+    pub fn get_thread_count(&self) -> usize {
+        self.thread_count
+    }
+
+    pub fn set_thread_count(&mut self, thread_count: usize) {
+        self.thread_count = thread_count.max(1);
+    }
+
+    pub fn get_tile_size(&self) -> i32 {
+        self.tile_size
+    }
+
+    pub fn set_tile_size(&mut self, tile_size: i32) {
+        self.tile_size = tile_size.max(1);
+        // force the tiles to be rebuilt on the next frame
+        self.width = 0;
+        self.height = 0;
+    }
+
+    pub fn get_depth_buffer(&self) -> &[f32] {
+        &self.depth_buffer
+    }
+
+    // discards any submitted primitives and resets the depth buffer if the
+    // last frame used it, rebuilding the tiles if the target size has changed
+    pub fn begin_frame(&mut self, width: i32, height: i32) {
+        if width != self.width || height != self.height {
+            self.width = width.max(0);
+            self.height = height.max(0);
+            self.tiles.clear();
+            let mut y = 0;
+            while y < self.height {
+                let mut x = 0;
+                while x < self.width {
+                    self.tiles.push(Rect::new(
+                        x,
+                        y,
+                        self.tile_size.min(self.width - x),
+                        self.tile_size.min(self.height - y)
+                    ));
+                    x += self.tile_size;
+                }
+                y += self.tile_size;
+            }
+            self.bins.resize_with(self.tiles.len(), Vec::new);
+            self.depth_buffer.resize((self.width * self.height) as usize, f32::INFINITY);
+        }
+        for bin in self.bins.iter_mut() {
+            bin.clear();
+        }
+        self.primitives.clear();
+        self.layer = 0;
+        if self.depth_written {
+            self.depth_buffer.fill(f32::INFINITY);
+            self.depth_written = false;
+        }
+        self.clip_regions = None;
+    }
+
+    // primitives submitted from now on are drawn over everything before,
+    // ignoring the depth of earlier layers
+    pub fn begin_layer(&mut self) {
+        self.layer += 1;
+    }
+
+    // restricts drawing for the current frame to the given regions
+    pub fn set_clip_regions(&mut self, regions: &[Rect]) {
+        self.clip_regions = Some(regions.to_vec());
+    }
+
+    pub fn submit(&mut self, mut primitive: Primitive) {
+        let viewport = (0, 0, self.width - 1, self.height - 1);
+        if let Primitive::Line { start, end, start_depth, end_depth, color } = primitive {
+            // clipped up front so lines reaching far off screen aren't
+            // stepped through, and all tiles draw the same clipped line
+            match clip_line(start, end, start_depth, end_depth, viewport) {
+                Some((start, end, start_depth, end_depth)) => {
+                    primitive = Primitive::Line { start, end, start_depth, end_depth, color };
+                },
+                None => return
+            }
+        }
+        let (min_x, min_y, max_x, max_y) = primitive.get_bounds();
+        if max_x < 0 || max_y < 0 || min_x >= self.width || min_y >= self.height {
+            return;
+        }
+        let tiles_x = (self.width + self.tile_size - 1) / self.tile_size;
+        let first_column = min_x.max(0) / self.tile_size;
+        let last_column = max_x.min(self.width - 1) / self.tile_size;
+        let first_row = min_y.max(0) / self.tile_size;
+        let last_row = max_y.min(self.height - 1) / self.tile_size;
+        let index = self.primitives.len();
+        self.primitives.push(primitive);
+        for row in first_row..=last_row {
+            for column in first_column..=last_column {
+                self.bins[(row * tiles_x + column) as usize].push((self.layer, index));
+            }
+        }
+    }
+
+    // draws every submitted primitive into the buffer, which must be
+    // width * height pixels as given to begin_frame
+    pub fn rasterize(&mut self, buffer: &mut [u32], use_depth: bool) {
+        assert!(
+            buffer.len() >= (self.width * self.height) as usize,
+            "Frame buffer is smaller than the rasterizer target"
+        );
+        let target = Target {
+            frame: SharedBuffer::new(buffer),
+            depth: SharedBuffer::new(&mut self.depth_buffer),
+            width: self.width,
+            use_depth
+        };
+        self.depth_written |= use_depth;
+        let tiles = &self.tiles;
+        let bins = &self.bins;
+        let primitives = &self.primitives;
+        let clip_regions = &self.clip_regions;
+        let busy_tiles: Vec<usize> = (0..tiles.len()).filter(|index| !bins[*index].is_empty()).collect();
+        let next_tile = AtomicUsize::new(0);
+
+        let work = || {
+            while let Some(&index) = busy_tiles.get(next_tile.fetch_add(1, Ordering::Relaxed)) {
+                let tile = tiles[index];
+                let areas: Vec<Rect> = match clip_regions {
+                    Some(regions) => regions.iter().filter_map(|region| region.intersection(&tile)).collect(),
+                    None => vec![tile]
+                };
+                if areas.is_empty() {
+                    continue;
+                }
+                let mut current_layer = bins[index][0].0;
+                for (layer, primitive) in bins[index].iter() {
+                    unsafe {
+                        if *layer != current_layer {
+                            current_layer = *layer;
+                            if use_depth {
+                                reset_depth(target, &tile);
+                            }
+                        }
+                        rasterize_primitive(&primitives[*primitive], &tile, &areas, target);
+                    }
+                }
+            }
+        };
+
+        let thread_count = self.thread_count.min(busy_tiles.len() / MIN_TILES_PER_THREAD);
+        if thread_count <= 1 {
+            work();
+        }
+        else {
+            thread::scope(|scope| {
+                for _ in 1..thread_count {
+                    scope.spawn(work);
+                }
+                work();
+            });
+        }
+    }
+}
+
+// clears the depth of a tile so the next layer draws over everything in it
+unsafe fn reset_depth(target: Target, tile: &Rect) {
+    for y in tile.y..(tile.y + tile.height) {
+        for x in tile.x..(tile.x + tile.width) {
+            *target.depth.get((y * target.width + x) as usize) = f32::INFINITY;
+        }
+    }
+}
+
+// writes a pixel if it lies inside one of the areas (all within the tile
+// being drawn) and passes the depth test (less or equal)
+unsafe fn plot(target: Target, areas: &[Rect], x: i32, y: i32, depth: f32, color: u32) {
+    if !areas.iter().any(|area| area.contains(x, y)) {
+        return;
+    }
+    let index = (y * target.width + x) as usize;
+    if target.use_depth {
+        let stored = target.depth.get(index);
+        if depth > *stored {
+            return;
+        }
+        *stored = depth;
+    }
+    *target.frame.get(index) = color;
+}
+
+// fills the pixels from min_x to max_x (inclusive) on row y that lie inside
+// one of the areas, only used without depth testing
+unsafe fn fill_span(target: Target, areas: &[Rect], y: i32, min_x: i32, max_x: i32, color: u32) {
+    for area in areas.iter().filter(|area| y >= area.y && y < area.get_bottom()) {
+        let start = min_x.max(area.x);
+        let end = (max_x + 1).min(area.get_right());
+        if start < end {
+            let row = (y * target.width) as usize;
+            simd::fill_u32(target.frame.slice(row + start as usize, row + end as usize), color);
+        }
+    }
+}
+
+unsafe fn rasterize_primitive(primitive: &Primitive, tile: &Rect, areas: &[Rect], target: Target) {
+    match *primitive {
+        Primitive::Point { position, depth, color } => {
+            plot(target, areas, position.0, position.1, depth, color);
+        },
+        // horizontal lines cover exactly the pixels between their ends, so
+        // without depth to test they are filled a row at a time
+        Primitive::Line { start, end, color, .. } if start.1 == end.1 && !target.use_depth => {
+            fill_span(target, areas, start.1, start.0.min(end.0), start.0.max(end.0), color);
+        },
+        Primitive::Line { start, end, start_depth, end_depth, color } => {
+            // dda over the whole line so pixels are identical regardless of
+            // which tile draws them, only the steps inside the tile are walked
+            let dx = end.0 as f32 - start.0 as f32;
+            let dy = end.1 as f32 - start.1 as f32;
+            let steps = dx.abs().max(dy.abs()) as i32;
+            if steps == 0 {
+                plot(target, areas, start.0, start.1, start_depth, color);
+                return;
+            }
+            let (step_x, step_y) = (dx / steps as f32, dy / steps as f32);
+            let (mut first, mut last) = (0.0f32, steps as f32);
+            let bounds = [
+                (step_x, start.0 as f32, tile.x as f32, (tile.x + tile.width) as f32),
+                (step_y, start.1 as f32, tile.y as f32, (tile.y + tile.height) as f32)
+            ];
+            for (step, origin, low, high) in bounds.iter() {
+                if *step == 0.0 {
+                    if *origin < low - 1.0 || *origin > high + 1.0 {
+                        return;
+                    }
+                    continue;
+                }
+                let a = (low - 1.0 - origin) / step;
+                let b = (high + 1.0 - origin) / step;
+                first = first.max(a.min(b));
+                last = last.min(a.max(b));
+            }
+            if first > last {
+                return;
+            }
+            let depth_step = (end_depth - start_depth) / steps as f32;
+            for i in (first.floor().max(0.0) as i32)..=(last.ceil().min(steps as f32) as i32) {
+                let x = (start.0 as f32 + step_x * i as f32).round() as i32;
+                let y = (start.1 as f32 + step_y * i as f32).round() as i32;
+                plot(target, areas, x, y, start_depth + depth_step * i as f32, color);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BOUNDS: (i32, i32, i32, i32) = (0, 0, 99, 49);
+
+    #[test]
+    fn keeps_lines_inside_the_bounds() {
+        assert_eq!(clip_line((1, 2), (30, 40), 0.0, 1.0, BOUNDS), Some(((1, 2), (30, 40), 0.0, 1.0)));
+        assert_eq!(clip_line((5, 5), (5, 5), 0.5, 0.5, BOUNDS), Some(((5, 5), (5, 5), 0.5, 0.5)));
+    }
+
+    #[test]
+    fn cuts_lines_at_the_bounds() {
+        let (start, end, start_depth, end_depth) = clip_line((-100, 10), (199, 10), 0.0, 1.0, BOUNDS).unwrap();
+        assert_eq!((start, end), ((0, 10), (99, 10)));
+        assert!((start_depth - 100.0 / 299.0).abs() < 1e-6);
+        assert!((end_depth - 199.0 / 299.0).abs() < 1e-6);
+        assert_eq!(clip_line((50, -10), (50, 100), 0.0, 0.0, BOUNDS).map(|line| (line.0, line.1)), Some(((50, 0), (50, 49))));
+    }
+
+    #[test]
+    fn drops_lines_outside_the_bounds() {
+        assert_eq!(clip_line((-10, -10), (-1, 60), 0.0, 0.0, BOUNDS), None);
+        assert_eq!(clip_line((100, 0), (200, 49), 0.0, 0.0, BOUNDS), None);
+        // crosses the corner region without entering the bounds
+        assert_eq!(clip_line((90, -20), (120, 10), 0.0, 0.0, BOUNDS), None);
+    }
+
+    #[test]
+    fn handles_saturated_coordinates() {
+        let line = clip_line((i32::MIN, i32::MIN), (i32::MAX, i32::MAX), 0.0, 1.0, BOUNDS).unwrap();
+        let ((start_x, start_y), (end_x, end_y), _, _) = line;
+        for (x, y) in [(start_x, start_y), (end_x, end_y)].iter() {
+            assert!((0..=99).contains(x) && (0..=49).contains(y));
+        }
+        assert_eq!(clip_line((i32::MIN, 10), (i32::MAX, 10), 0.0, 0.0, BOUNDS).map(|line| (line.0, line.1)), Some(((0, 10), (99, 10))));
+        assert_eq!(clip_line((i32::MAX, i32::MIN), (i32::MAX, i32::MAX), 0.0, 0.0, BOUNDS), None);
+    }
+
+    fn draw(rasterizer: &mut TileRasterizer, use_depth: bool) -> Vec<u32> {
+        rasterizer.begin_frame(200, 120);
+        for i in 0..40 {
+            rasterizer.submit(Primitive::Line {
+                start: (-20 + i * 7, -10),
+                end: (230 - i * 5, 130),
+                start_depth: i as f32,
+                end_depth: 40.0 - i as f32,
+                color: i as u32 + 1
+            });
+            if i % 10 == 9 {
+                rasterizer.begin_layer();
+            }
+        }
+        rasterizer.submit(Primitive::Point { position: (3, 4), depth: -1.0, color: 99 });
+        let mut buffer = vec![0; 200 * 120];
+        rasterizer.rasterize(&mut buffer, use_depth);
+        buffer
+    }
+
+    #[test]
+    fn matches_across_thread_counts() {
+        for use_depth in [false, true] {
+            let mut single = TileRasterizer::new(1);
+            single.set_tile_size(16);
+            let expected = draw(&mut single, use_depth);
+            assert_eq!(expected[4 * 200 + 3], 99);
+            let mut threaded = TileRasterizer::new(4);
+            threaded.set_tile_size(16);
+            assert_eq!(draw(&mut threaded, use_depth), expected);
+            assert_eq!(threaded.get_depth_buffer(), single.get_depth_buffer());
+        }
+    }
+
+    #[test]
+    fn only_resets_depth_after_it_was_used() {
+        let mut rasterizer = TileRasterizer::new(1);
+        draw(&mut rasterizer, false);
+        assert!(!rasterizer.depth_written);
+        assert!(rasterizer.get_depth_buffer().iter().all(|depth| *depth == f32::INFINITY));
+
+        draw(&mut rasterizer, true);
+        assert!(rasterizer.depth_written);
+        assert_eq!(rasterizer.get_depth_buffer()[4 * 200 + 3], -1.0);
+        rasterizer.begin_frame(200, 120);
+        assert!(!rasterizer.depth_written);
+        assert!(rasterizer.get_depth_buffer().iter().all(|depth| *depth == f32::INFINITY));
+    }
+
+    #[test]
+    fn fills_spans_like_plotted_lines() {
+        // horizontal lines are filled through simd without depth and
+        // plotted a pixel at a time with it, both must cover the same pixels
+        let spans = [((0, 0), (0, 0)), ((5, 1), (1, 1)), ((3, 2), (20, 2)), ((-7, 3), (70, 3)), ((63, 4), (13, 4))];
+        let draw = |use_depth: bool, clip: Option<&[Rect]>| {
+            let mut rasterizer = TileRasterizer::new(1);
+            rasterizer.set_tile_size(8);
+            rasterizer.begin_frame(64, 5);
+            if let Some(regions) = clip {
+                rasterizer.set_clip_regions(regions);
+            }
+            for (i, (start, end)) in spans.iter().enumerate() {
+                rasterizer.submit(Primitive::Line { start: *start, end: *end, start_depth: 0.0, end_depth: 0.0, color: i as u32 + 1 });
+            }
+            let mut buffer = vec![0; 64 * 5];
+            rasterizer.rasterize(&mut buffer, use_depth);
+            buffer
+        };
+        let filled = draw(false, None);
+        assert_eq!(filled, draw(true, None));
+        assert_eq!(filled[3 * 64..4 * 64], [4; 64]);
+        assert_eq!(filled[2 * 64 + 2..2 * 64 + 22], [0, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 0]);
+
+        let regions = [Rect::new(3, 0, 9, 5), Rect::new(30, 2, 5, 2)];
+        assert_eq!(draw(false, Some(&regions)), draw(true, Some(&regions)));
+    }
+}
@@ -0,0 +1,150 @@
+// scaling of a fixed internal resolution frame up (or down) to the window
+
+use super::dirty::Rect;
+use super::simd;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ScalingMode {
+    // fills the window, distorting the frame if the aspect ratios differ
+    Stretch,
+    // keeps the frame's aspect ratio, filling the remaining space with bars
+    Letterbox
+}
+
+// the area of the destination the source is drawn into
+pub fn fit_rect(source_size: (i32, i32), destination_size: (i32, i32), mode: ScalingMode) -> Rect {
+    let (source_width, source_height) = source_size;
+    let (destination_width, destination_height) = destination_size;
+    match mode {
+        ScalingMode::Stretch => Rect::new(0, 0, destination_width, destination_height),
+        ScalingMode::Letterbox => {
+            if source_width <= 0 || source_height <= 0 {
+                return Rect::new(0, 0, 0, 0);
+            }
+            let scale = (destination_width as f32 / source_width as f32)
+                .min(destination_height as f32 / source_height as f32);
+            let width = (source_width as f32 * scale).round() as i32;
+            let height = (source_height as f32 * scale).round() as i32;
+            Rect::new(
+                (destination_width - width) / 2,
+                (destination_height - height) / 2,
+                width,
+                height
+            )
+        }
+    }
+}
+
+// maps a point in the destination back to the source, none if it is
+// outside of the area the source was drawn into
+pub fn to_source(point: (i32, i32), source_size: (i32, i32), area: Rect) -> Option<(i32, i32)> {
+    if !area.contains(point.0, point.1) {
+        return None;
+    }
+    Some((
+        ((point.0 - area.x) as i64 * source_size.0 as i64 / area.width as i64) as i32,
+        ((point.1 - area.y) as i64 * source_size.1 as i64 / area.height as i64) as i32
+    ))
+}
+
+pub fn fill_rect(buffer: &mut [u32], width: i32, rect: Rect, color: u32) {
+    let height = if width > 0 { buffer.len() as i32 / width } else { 0 };
+    if let Some(rect) = rect.clip(width, height) {
+        for y in rect.y..rect.get_bottom() {
+            let row_start = (y * width) as usize;
+            simd::fill_u32(
+                &mut buffer[row_start + rect.x as usize..row_start + rect.get_right() as usize],
+                color
+            );
+        }
+    }
+}
+
+// nearest neighbour copy of the whole source into the area of the destination
+pub fn blit_scaled(source: &[u32], source_size: (i32, i32), destination: &mut [u32], destination_size: (i32, i32), area: Rect) {
+    let (source_width, source_height) = source_size;
+    let (destination_width, destination_height) = destination_size;
+    if source_width <= 0 || source_height <= 0 || area.is_empty() {
+        return;
+    }
+    let clipped = match area.clip(destination_width, destination_height) {
+        Some(clipped) => clipped,
+        None => return
+    };
+    // source column for every destination column, shared by all rows
+    let columns: Vec<usize> = (clipped.x..clipped.get_right())
+        .map(|x| ((x - area.x) as i64 * source_width as i64 / area.width as i64) as usize)
+        .collect();
+    for y in clipped.y..clipped.get_bottom() {
+        let source_y = ((y - area.y) as i64 * source_height as i64 / area.height as i64) as usize;
+        let source_row = &source[source_y * source_width as usize..(source_y + 1) * source_width as usize];
+        let row_start = (y * destination_width + clipped.x) as usize;
+        let destination_row = &mut destination[row_start..row_start + columns.len()];
+        for (pixel, column) in destination_row.iter_mut().zip(columns.iter()) {
+            *pixel = source_row[*column];
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn letterboxes_wider_frames() {
+        let area = fit_rect((320, 200), (800, 600), ScalingMode::Letterbox);
+        assert_eq!(area, Rect::new(0, 50, 800, 500));
+        assert_eq!(to_source((0, 49), (320, 200), area), None);
+        assert_eq!(to_source((0, 50), (320, 200), area), Some((0, 0)));
+        assert_eq!(to_source((400, 300), (320, 200), area), Some((160, 100)));
+        assert_eq!(to_source((799, 549), (320, 200), area), Some((319, 199)));
+        assert_eq!(to_source((0, 550), (320, 200), area), None);
+    }
+
+    #[test]
+    fn pillarboxes_taller_frames() {
+        // an odd size that doesn't divide evenly into the window
+        let area = fit_rect((3, 7), (100, 100), ScalingMode::Letterbox);
+        assert_eq!(area, Rect::new(28, 0, 43, 100));
+        assert_eq!(to_source((27, 50), (3, 7), area), None);
+        assert_eq!(to_source((28, 0), (3, 7), area), Some((0, 0)));
+        assert_eq!(to_source((70, 99), (3, 7), area), Some((2, 6)));
+        assert_eq!(to_source((71, 50), (3, 7), area), None);
+    }
+
+    #[test]
+    fn stretches_to_the_whole_window() {
+        let area = fit_rect((3, 7), (100, 50), ScalingMode::Stretch);
+        assert_eq!(area, Rect::new(0, 0, 100, 50));
+        assert_eq!(to_source((0, 0), (3, 7), area), Some((0, 0)));
+        assert_eq!(to_source((99, 49), (3, 7), area), Some((2, 6)));
+        assert_eq!(to_source((100, 0), (3, 7), area), None);
+        assert_eq!(fit_rect((0, 7), (100, 50), ScalingMode::Letterbox), Rect::new(0, 0, 0, 0));
+    }
+
+    #[test]
+    fn blits_into_the_letterboxed_area() {
+        let source = [1, 2, 3, 4, 5, 6];
+        let area = fit_rect((3, 2), (8, 3), ScalingMode::Letterbox);
+        assert_eq!(area, Rect::new(1, 0, 5, 3));
+        let mut destination = [0; 24];
+        blit_scaled(&source, (3, 2), &mut destination, (8, 3), area);
+        assert_eq!(destination, [
+            0, 1, 1, 2, 2, 3, 0, 0,
+            0, 1, 1, 2, 2, 3, 0, 0,
+            0, 4, 4, 5, 5, 6, 0, 0
+        ]);
+    }
+
+    #[test]
+    fn blits_areas_partly_off_the_destination() {
+        let source = [1, 2, 3, 4, 5, 6];
+        let mut destination = [0; 8];
+        blit_scaled(&source, (3, 2), &mut destination, (4, 2), Rect::new(-3, 0, 6, 2));
+        assert_eq!(destination, [2, 3, 3, 0, 5, 6, 6, 0]);
+
+        let mut buffer = [0; 8];
+        fill_rect(&mut buffer, 4, Rect::new(2, -1, 5, 2), 9);
+        assert_eq!(buffer, [0, 0, 9, 9, 0, 0, 0, 0]);
+    }
+}
@@ -0,0 +1,579 @@
+// saving and loading of scenes (the objects, entities, camera and background
+// color) as json
+//
+// objects are rebuilt from their type name, so anything a type keeps outside
+// of its transform, colors and vertices is reset to its default, objects of
+// an unknown type are loaded as meshes of their saved vertices
+//
+// for objects and entities alike the name, tags and the components the
+// simulator itself provides (Transform, Geometry, Material, Velocity, Hidden,
+// Layer and ZOrder) are saved, any other components are not, so entities
+// only made of those are left out
+//
+// fields that aren't recognised are skipped, so a scene saved by a newer
+// version still loads with whatever this version understands
+
+use std::fmt;
+use std::io;
+
+use super::Color;
+use super::json::{Json, JsonError};
+use super::objects::{Object, Vertex, Cube, Spot, Circle, Mesh};
+use super::renderer::{Camera, Projection, Transform};
+use super::renderer::linearalgebra::{Vector3D, Quaternion};
+use super::world::{World, Entity, Geometry, Material, Velocity, Hidden, Layer, ZOrder};
+
+// 2 added entities, names, tags and components
+pub const SCENE_VERSION: u32 = 2;
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io(io::Error),
+    Parse(JsonError),
+    // the document is valid json but not a scene
+    Invalid(String)
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SceneError::Io(error) => write!(f, "Could not access scene: {}", error),
+            SceneError::Parse(error) => write!(f, "Could not parse scene: {}", error),
+            SceneError::Invalid(message) => write!(f, "Invalid scene: {}", message)
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+impl From<io::Error> for SceneError {
+    fn from(error: io::Error) -> Self {
+        SceneError::Io(error)
+    }
+}
+
+impl From<JsonError> for SceneError {
+    fn from(error: JsonError) -> Self {
+        SceneError::Parse(error)
+    }
+}
+
+fn invalid(message: &str) -> SceneError {
+    SceneError::Invalid(String::from(message))
+}
+
+// what is saved of an entity or object from the world
+#[derive(Clone, Debug, Default)]
+pub struct SceneEntity {
+    pub name: Option<String>,
+    pub tags: Vec<String>,
+    pub transform: Option<Transform>,
+    pub geometry: Option<Geometry>,
+    pub material: Option<Material>,
+    pub velocity: Option<Velocity>,
+    pub hidden: bool,
+    pub layer: Option<Layer>,
+    pub z_order: Option<ZOrder>
+}
+
+impl SceneEntity {
+    pub fn from_world(world: &World, entity: Entity) -> Self {
+        Self {
+            name: world.get_name(entity).map(String::from),
+            tags: world.get_tags(entity).map(String::from).collect(),
+            transform: world.get::<Transform>(entity).copied(),
+            geometry: world.get::<Geometry>(entity).cloned(),
+            material: world.get::<Material>(entity).copied(),
+            velocity: world.get::<Velocity>(entity).copied(),
+            hidden: world.has::<Hidden>(entity),
+            layer: world.get::<Layer>(entity).copied(),
+            z_order: world.get::<ZOrder>(entity).copied()
+        }
+    }
+
+    // whether there is anything to save
+    pub fn is_empty(&self) -> bool {
+        self.name.is_none()
+            && self.tags.is_empty()
+            && self.transform.is_none()
+            && self.geometry.is_none()
+            && self.material.is_none()
+            && self.velocity.is_none()
+            && !self.hidden
+            && self.layer.is_none()
+            && self.z_order.is_none()
+    }
+
+    pub fn insert_into(self, world: &mut World, entity: Entity) {
+        if let Some(name) = &self.name {
+            world.set_name(entity, name);
+        }
+        for tag in self.tags.iter() {
+            world.add_tag(entity, tag);
+        }
+        if let Some(transform) = self.transform {
+            world.insert(entity, transform);
+        }
+        if let Some(geometry) = self.geometry {
+            world.insert(entity, geometry);
+        }
+        if let Some(material) = self.material {
+            world.insert(entity, material);
+        }
+        if let Some(velocity) = self.velocity {
+            world.insert(entity, velocity);
+        }
+        if self.hidden {
+            world.insert(entity, Hidden);
+        }
+        if let Some(layer) = self.layer {
+            world.insert(entity, layer);
+        }
+        if let Some(z_order) = self.z_order {
+            world.insert(entity, z_order);
+        }
+    }
+}
+
+// everything a scene file holds, parts missing from the file are none
+pub struct Scene {
+    pub version: u32,
+    pub background_color: Option<Color>,
+    pub camera: Option<Camera>,
+    pub objects: Vec<(Box<dyn Object>, SceneEntity)>,
+    pub entities: Vec<SceneEntity>
+}
+
+//#region Writing
+fn vector_to_json(vector: Vector3D) -> Json {
+    Json::Array(vec![vector.x.into(), vector.y.into(), vector.z.into()])
+}
+
+// colors are saved by their channels whatever order they are drawn in, "#rrggbbaa"
+fn color_to_json(color: Color) -> Json {
+    Json::String(format!("#{:02x}{:02x}{:02x}{:02x}", color.r, color.g, color.b, color.a))
+}
+
+fn transform_to_json(transform: &Transform) -> Json {
+    let mut entries = vec![
+        (String::from("position"), vector_to_json(transform.position)),
+        (String::from("rotation"), vector_to_json(transform.get_rotation())),
+        (String::from("scale"), vector_to_json(transform.scale))
+    ];
+    if transform.uses_quaternion() {
+        let orientation = transform.get_orientation();
+        entries.push((String::from("orientation"), Json::Array(vec![
+            orientation.w.into(),
+            orientation.x.into(),
+            orientation.y.into(),
+            orientation.z.into()
+        ])));
+    }
+    Json::Object(entries)
+}
+
+fn camera_to_json(camera: &Camera) -> Json {
+    let projection = match camera.get_projection() {
+        Projection::Perspective { fov, near, far } => Json::Object(vec![
+            (String::from("type"), "perspective".into()),
+            (String::from("fov"), fov.into()),
+            (String::from("near"), near.into()),
+            (String::from("far"), far.into())
+        ]),
+        Projection::Orthographic { height, near, far } => Json::Object(vec![
+            (String::from("type"), "orthographic".into()),
+            (String::from("height"), height.into()),
+            (String::from("near"), near.into()),
+            (String::from("far"), far.into())
+        ])
+    };
+    Json::Object(vec![
+        (String::from("transform"), transform_to_json(&camera.transform)),
+        (String::from("projection"), projection)
+    ])
+}
+
+fn mesh_to_json(verticies: &[Vertex]) -> Json {
+    Json::Array(verticies.iter().map(|vertex| Json::Object(vec![
+        (String::from("position"), vector_to_json(vertex.get_rel_pos())),
+        (String::from("connections"), Json::Array(
+            vertex.get_connections().iter().map(|index| (*index).into()).collect()
+        ))
+    ])).collect())
+}
+
+fn layer_to_json(layer: Layer) -> Json {
+    match layer {
+        Layer::Background => "background".into(),
+        Layer::World => "world".into(),
+        Layer::Overlay => "overlay".into()
+    }
+}
+
+// the name, tags and components, only those that are set are written
+fn entity_to_json(entity: &SceneEntity) -> Vec<(String, Json)> {
+    let mut entries = Vec::new();
+    if let Some(name) = &entity.name {
+        entries.push((String::from("name"), name.as_str().into()));
+    }
+    if !entity.tags.is_empty() {
+        entries.push((String::from("tags"), Json::Array(entity.tags.iter().map(|tag| tag.as_str().into()).collect())));
+    }
+    let mut components = Vec::new();
+    if let Some(transform) = &entity.transform {
+        components.push((String::from("transform"), transform_to_json(transform)));
+    }
+    if let Some(geometry) = &entity.geometry {
+        components.push((String::from("geometry"), mesh_to_json(&geometry.verticies)));
+    }
+    if let Some(material) = &entity.material {
+        components.push((String::from("material"), Json::Object(vec![
+            (String::from("frame_color"), color_to_json(material.frame_color)),
+            (String::from("fill_color"), color_to_json(material.fill_color))
+        ])));
+    }
+    if let Some(velocity) = &entity.velocity {
+        components.push((String::from("velocity"), Json::Object(vec![
+            (String::from("linear"), vector_to_json(velocity.linear)),
+            (String::from("angular"), vector_to_json(velocity.angular))
+        ])));
+    }
+    if entity.hidden {
+        components.push((String::from("hidden"), Json::Bool(true)));
+    }
+    if let Some(layer) = entity.layer {
+        components.push((String::from("layer"), layer_to_json(layer)));
+    }
+    if let Some(ZOrder(z_order)) = entity.z_order {
+        components.push((String::from("z_order"), Json::Number(z_order.to_string())));
+    }
+    if !components.is_empty() {
+        entries.push((String::from("components"), Json::Object(components)));
+    }
+    entries
+}
+
+fn object_to_json(object: &dyn Object, entity: &SceneEntity) -> Json {
+    let mut entries = vec![
+        (String::from("type"), object.get_type_name().into()),
+        (String::from("transform"), transform_to_json(object.transform())),
+        (String::from("frame_color"), color_to_json(object.get_frame_color())),
+        (String::from("fill_color"), color_to_json(object.get_fill_color())),
+        (String::from("mesh"), mesh_to_json(object.get_verticies()))
+    ];
+    entries.extend(entity_to_json(entity));
+    Json::Object(entries)
+}
+
+pub fn write_scene<'a, I, E>(background_color: Color, camera: &Camera, objects: I, entities: E) -> String
+where
+    I: Iterator<Item = (&'a dyn Object, SceneEntity)>,
+    E: Iterator<Item = SceneEntity>
+{
+    Json::Object(vec![
+        (String::from("version"), SCENE_VERSION.into()),
+        (String::from("background_color"), color_to_json(background_color)),
+        (String::from("camera"), camera_to_json(camera)),
+        (String::from("objects"), Json::Array(objects.map(|(object, entity)| object_to_json(object, &entity)).collect())),
+        (String::from("entities"), Json::Array(entities.map(|entity| Json::Object(entity_to_json(&entity))).collect()))
+    ]).to_pretty_string()
+}
+//#endregion
+
+//#region Reading
+fn number_from_json(json: &Json, name: &str) -> Result<f32, SceneError> {
+    json.as_f32().ok_or_else(|| SceneError::Invalid(format!("{} must be a number", name)))
+}
+
+fn vector_from_json(json: &Json, name: &str) -> Result<Vector3D, SceneError> {
+    match json.as_array() {
+        Some([x, y, z]) => Ok(Vector3D::new(
+            number_from_json(x, name)?,
+            number_from_json(y, name)?,
+            number_from_json(z, name)?
+        )),
+        _ => Err(SceneError::Invalid(format!("{} must be an array of 3 numbers", name)))
+    }
+}
+
+fn color_from_json(json: &Json) -> Result<Color, SceneError> {
+    let text = json.as_str().unwrap_or("");
+    let digits = text.strip_prefix('#').unwrap_or(text);
+    let value = u32::from_str_radix(digits, 16).ok().filter(|_| digits.len() == 6 || digits.len() == 8);
+    match value {
+        Some(value) if digits.len() == 6 => Ok(Color::rgb((value >> 16) as u8, (value >> 8) as u8, value as u8)),
+        Some(value) => Ok(Color::rgba((value >> 24) as u8, (value >> 16) as u8, (value >> 8) as u8, value as u8)),
+        None => Err(SceneError::Invalid(format!("{:?} is not a color, expected #rrggbb or #rrggbbaa", text)))
+    }
+}
+
+// missing parts are left at their defaults
+fn transform_from_json(json: &Json) -> Result<Transform, SceneError> {
+    let mut transform = Transform::new();
+    if let Some(position) = json.get("position") {
+        transform.position = vector_from_json(position, "position")?;
+    }
+    if let Some(rotation) = json.get("rotation") {
+        let rotation = vector_from_json(rotation, "rotation")?;
+        transform.set_rotation(rotation.x, rotation.y, rotation.z);
+    }
+    if let Some(scale) = json.get("scale") {
+        transform.scale = vector_from_json(scale, "scale")?;
+    }
+    if let Some(orientation) = json.get("orientation") {
+        match orientation.as_array() {
+            Some([w, x, y, z]) => transform.set_orientation(Quaternion::new(
+                number_from_json(w, "orientation")?,
+                number_from_json(x, "orientation")?,
+                number_from_json(y, "orientation")?,
+                number_from_json(z, "orientation")?
+            )),
+            _ => return Err(invalid("orientation must be an array of 4 numbers"))
+        }
+    }
+    Ok(transform)
+}
+
+fn camera_from_json(json: &Json) -> Result<Camera, SceneError> {
+    let mut camera = Camera::new();
+    if let Some(transform) = json.get("transform") {
+        camera.transform = transform_from_json(transform)?;
+    }
+    if let Some(projection) = json.get("projection") {
+        let field = |name: &str| projection.get(name)
+            .ok_or_else(|| SceneError::Invalid(format!("projection is missing {}", name)))
+            .and_then(|value| number_from_json(value, name));
+        match projection.get("type").and_then(|kind| kind.as_str()) {
+            Some("perspective") => camera.use_perspective(field("fov")?, field("near")?, field("far")?),
+            Some("orthographic") => camera.use_orthographic(field("height")?, field("near")?, field("far")?),
+            _ => return Err(invalid("projection type must be perspective or orthographic"))
+        }
+    }
+    Ok(camera)
+}
+
+fn mesh_from_json(json: &Json) -> Result<Vec<Vertex>, SceneError> {
+    let vertices = json.as_array().ok_or_else(|| invalid("mesh must be an array"))?;
+    let mut mesh = Vec::with_capacity(vertices.len());
+    for vertex in vertices.iter() {
+        let position = vertex.get("position").ok_or_else(|| invalid("vertex is missing a position"))?;
+        let mut result = Vertex::new(vector_from_json(position, "vertex position")?);
+        if let Some(connections) = vertex.get("connections") {
+            let connections = connections.as_array().ok_or_else(|| invalid("connections must be an array"))?;
+            for connection in connections.iter() {
+                let index = connection.as_u64()
+                    .filter(|index| (*index as usize) < vertices.len())
+                    .ok_or_else(|| invalid("connections must be indices of vertices in the mesh"))?;
+                result.add_connection(index as usize);
+            }
+        }
+        mesh.push(result);
+    }
+    Ok(mesh)
+}
+
+fn layer_from_json(json: &Json) -> Result<Layer, SceneError> {
+    match json.as_str() {
+        Some("background") => Ok(Layer::Background),
+        Some("world") => Ok(Layer::World),
+        Some("overlay") => Ok(Layer::Overlay),
+        _ => Err(invalid("layer must be background, world or overlay"))
+    }
+}
+
+fn entity_from_json(json: &Json) -> Result<SceneEntity, SceneError> {
+    let mut entity = SceneEntity::default();
+    if let Some(name) = json.get("name") {
+        entity.name = Some(String::from(name.as_str().ok_or_else(|| invalid("name must be a string"))?));
+    }
+    if let Some(tags) = json.get("tags") {
+        let tags = tags.as_array().ok_or_else(|| invalid("tags must be an array"))?;
+        for tag in tags.iter() {
+            entity.tags.push(String::from(tag.as_str().ok_or_else(|| invalid("tags must be strings"))?));
+        }
+    }
+    let components = match json.get("components") {
+        Some(components) => components,
+        None => return Ok(entity)
+    };
+    if let Some(transform) = components.get("transform") {
+        entity.transform = Some(transform_from_json(transform)?);
+    }
+    if let Some(geometry) = components.get("geometry") {
+        entity.geometry = Some(Geometry::new(mesh_from_json(geometry)?));
+    }
+    if let Some(material) = components.get("material") {
+        let frame_color = match material.get("frame_color") {
+            Some(color) => color_from_json(color)?,
+            None => Color::BLACK
+        };
+        let fill_color = match material.get("fill_color") {
+            Some(color) => color_from_json(color)?,
+            None => frame_color
+        };
+        entity.material = Some(Material::new(frame_color, fill_color));
+    }
+    if let Some(velocity) = components.get("velocity") {
+        let field = |name: &str| match velocity.get(name) {
+            Some(vector) => vector_from_json(vector, name),
+            None => Ok(Vector3D::ZERO)
+        };
+        entity.velocity = Some(Velocity::new(field("linear")?, field("angular")?));
+    }
+    if let Some(hidden) = components.get("hidden") {
+        entity.hidden = hidden.as_bool().ok_or_else(|| invalid("hidden must be true or false"))?;
+    }
+    if let Some(layer) = components.get("layer") {
+        entity.layer = Some(layer_from_json(layer)?);
+    }
+    if let Some(z_order) = components.get("z_order") {
+        let z_order = match z_order {
+            Json::Number(text) => text.parse::<i32>().ok(),
+            _ => None
+        };
+        entity.z_order = Some(ZOrder(z_order.ok_or_else(|| invalid("z_order must be a whole number"))?));
+    }
+    Ok(entity)
+}
+
+fn object_from_json(json: &Json) -> Result<Box<dyn Object>, SceneError> {
+    let type_name = json.get("type").and_then(|name| name.as_str()).ok_or_else(|| invalid("object is missing its type"))?;
+    let frame_color = match json.get("frame_color") {
+        Some(color) => color_from_json(color)?,
+        None => Color::BLACK
+    };
+    let fill_color = match json.get("fill_color") {
+        Some(color) => color_from_json(color)?,
+        None => frame_color
+    };
+    let mut object: Box<dyn Object> = match type_name {
+        "Cube" => Box::new(Cube::new()),
+        "Spot" => Box::new(Spot::new(frame_color)),
+        "Circle" => Box::new(Circle::new(frame_color, fill_color)),
+        _ => {
+            let mesh = match json.get("mesh") {
+                Some(mesh) => mesh_from_json(mesh)?,
+                None => Vec::new()
+            };
+            Box::new(Mesh::new(mesh, frame_color))
+        }
+    };
+    if let Some(transform) = json.get("transform") {
+        *object.transform_mut() = transform_from_json(transform)?;
+    }
+    object.set_frame_color(frame_color);
+    object.set_fill_color(fill_color);
+    object.cache_transform();
+    Ok(object)
+}
+
+// scenes from a newer version are read as far as they are understood, the
+// caller can compare the returned version against SCENE_VERSION to tell
+pub fn read_scene(text: &str) -> Result<Scene, SceneError> {
+    let json = Json::parse(text)?;
+    let version = json.get("version")
+        .and_then(|version| version.as_u64())
+        .ok_or_else(|| invalid("missing version"))? as u32;
+    let background_color = match json.get("background_color") {
+        Some(color) => Some(color_from_json(color)?),
+        None => None
+    };
+    let camera = match json.get("camera") {
+        Some(camera) => Some(camera_from_json(camera)?),
+        None => None
+    };
+    let mut objects = Vec::new();
+    if let Some(entries) = json.get("objects") {
+        let entries = entries.as_array().ok_or_else(|| invalid("objects must be an array"))?;
+        for entry in entries.iter() {
+            objects.push((object_from_json(entry)?, entity_from_json(entry)?));
+        }
+    }
+    let mut entities = Vec::new();
+    if let Some(entries) = json.get("entities") {
+        let entries = entries.as_array().ok_or_else(|| invalid("entities must be an array"))?;
+        for entry in entries.iter() {
+            entities.push(entity_from_json(entry)?);
+        }
+    }
+    Ok(Scene {
+        version,
+        background_color,
+        camera,
+        objects,
+        entities
+    })
+}
+//#endregion
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rewrite(scene: &Scene) -> String {
+        write_scene(
+            scene.background_color.unwrap(),
+            scene.camera.as_ref().unwrap(),
+            scene.objects.iter().map(|(object, entity)| (object.as_ref(), entity.clone())),
+            scene.entities.iter().cloned()
+        )
+    }
+
+    #[test]
+    fn round_trips_objects_and_entities() {
+        let mut world = World::new();
+        let entity = world.spawn();
+        world.set_name(entity, "spinner").add_tag(entity, "solid").add_tag(entity, "moving");
+        let mut transform = Transform::new();
+        transform.position = Vector3D::new(1.0, -2.0, 3.5);
+        world.insert(entity, transform)
+            .insert(entity, Geometry::cube())
+            .insert(entity, Material::new(Color::rgba(1, 2, 3, 4), Color::rgb(200, 100, 50)))
+            .insert(entity, Velocity::new(Vector3D::X, Vector3D::new(0.0, 90.0, 0.0)))
+            .insert(entity, Hidden)
+            .insert(entity, Layer::Overlay)
+            .insert(entity, ZOrder(-2));
+
+        let object: Box<dyn Object> = Box::new(Cube::new().set_position(0.0, 1.0, 2.0));
+        let object_entity = SceneEntity {
+            name: Some(String::from("cube")),
+            ..SceneEntity::default()
+        };
+        let text = write_scene(
+            Color::rgba(10, 20, 30, 255),
+            &Camera::new(),
+            std::iter::once((object.as_ref(), object_entity)),
+            std::iter::once(SceneEntity::from_world(&world, entity))
+        );
+
+        let scene = read_scene(&text).unwrap();
+        assert_eq!(scene.version, SCENE_VERSION);
+        assert_eq!(scene.objects.len(), 1);
+        assert_eq!(scene.objects[0].1.name.as_deref(), Some("cube"));
+        let loaded = &scene.entities[0];
+        assert_eq!(loaded.name.as_deref(), Some("spinner"));
+        assert_eq!(loaded.tags, vec!["moving", "solid"]);
+        assert_eq!(loaded.transform.unwrap().position, Vector3D::new(1.0, -2.0, 3.5));
+        assert!(loaded.hidden);
+        assert_eq!(loaded.layer, Some(Layer::Overlay));
+        assert_eq!(loaded.z_order, Some(ZOrder(-2)));
+        assert_eq!(rewrite(&scene), text);
+    }
+
+    #[test]
+    fn keeps_color_channels() {
+        let color = Color::rgba(0x12, 0x34, 0x56, 0x78);
+        let loaded = color_from_json(&color_to_json(color)).unwrap();
+        assert_eq!((loaded.r, loaded.g, loaded.b, loaded.a), (0x12, 0x34, 0x56, 0x78));
+    }
+
+    #[test]
+    fn reads_newer_versions_and_rejects_non_scenes() {
+        let scene = read_scene(r#"{"version": 99, "future_field": [1, 2], "entities": []}"#).unwrap();
+        assert_eq!(scene.version, 99);
+        assert!(scene.objects.is_empty());
+        assert!(scene.camera.is_none());
+        assert!(matches!(read_scene(r#"{"objects": []}"#), Err(SceneError::Invalid(_))));
+        assert!(matches!(read_scene("{"), Err(SceneError::Parse(_))));
+    }
+}
@@ -0,0 +1,397 @@
+// recording and replaying of everything that makes a run nondeterministic,
+// the frame deltas, input, window events and random seeds, so a session can
+// be played back frame for frame
+//
+// file layout, all little endian:
+//     magic "SIMSESSN", version u32, random state u64
+//     then per frame a FRAME record followed by that frame's other records
+//
+// records start with a tag byte:
+//     FRAME   delta f32, resolution width i32, height i32
+//     INPUT   kind u8 then key u64, button u16, position i32 i32 or wheel f32
+//     WINDOW  kind u8 then width i32 and height i32 for a resize
+//     SEED    seed u64
+
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use super::input::{InputEvent, Key, MouseButton};
+use super::events::Event;
+
+const MAGIC: &[u8; 8] = b"SIMSESSN";
+const VERSION: u32 = 2;
+
+const FRAME: u8 = 0;
+const INPUT: u8 = 1;
+const WINDOW: u8 = 2;
+const SEED: u8 = 3;
+
+// every named key in the order of their codes, anything else is stored as
+// OTHER_KEY plus the platform code, which can use all 32 bits
+const NAMED_KEYS: [Key; 66] = [
+    Key::A, Key::B, Key::C, Key::D, Key::E, Key::F, Key::G, Key::H, Key::I, Key::J, Key::K, Key::L, Key::M,
+    Key::N, Key::O, Key::P, Key::Q, Key::R, Key::S, Key::T, Key::U, Key::V, Key::W, Key::X, Key::Y, Key::Z,
+    Key::Num0, Key::Num1, Key::Num2, Key::Num3, Key::Num4, Key::Num5, Key::Num6, Key::Num7, Key::Num8, Key::Num9,
+    Key::F1, Key::F2, Key::F3, Key::F4, Key::F5, Key::F6, Key::F7, Key::F8, Key::F9, Key::F10, Key::F11, Key::F12,
+    Key::Up, Key::Down, Key::Left, Key::Right, Key::Space, Key::Enter, Key::Escape, Key::Tab, Key::Backspace,
+    Key::Delete, Key::Insert, Key::Home, Key::End, Key::PageUp, Key::PageDown, Key::Shift, Key::Control, Key::Alt
+];
+const OTHER_KEY: u64 = 1 << 32;
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn key_to_code(key: Key) -> u64 {
+    if let Key::Other(code) = key {
+        return OTHER_KEY | code as u64;
+    }
+    NAMED_KEYS.iter()
+        .position(|named| *named == key)
+        .unwrap_or(0) as u64
+}
+
+fn key_from_code(code: u64) -> io::Result<Key> {
+    if code & OTHER_KEY != 0 {
+        return u32::try_from(code & !OTHER_KEY)
+            .map(Key::Other)
+            .map_err(|_| invalid_data("Unknown key in session"));
+    }
+    NAMED_KEYS.get(code as usize)
+        .copied()
+        .ok_or_else(|| invalid_data("Unknown key in session"))
+}
+
+// other buttons are offset past the named ones, so every u8 code fits
+fn button_to_code(button: MouseButton) -> u16 {
+    match button {
+        MouseButton::Left => 0,
+        MouseButton::Right => 1,
+        MouseButton::Middle => 2,
+        MouseButton::Other(code) => code as u16 + 3
+    }
+}
+
+fn button_from_code(code: u16) -> io::Result<MouseButton> {
+    match code {
+        0 => Ok(MouseButton::Left),
+        1 => Ok(MouseButton::Right),
+        2 => Ok(MouseButton::Middle),
+        code => u8::try_from(code - 3)
+            .map(MouseButton::Other)
+            .map_err(|_| invalid_data("Unknown mouse button in session"))
+    }
+}
+
+// everything needed to repeat a single frame
+#[derive(Clone, Debug, PartialEq)]
+pub struct SessionFrame {
+    // unscaled delta time
+    pub delta: f32,
+    // size of the frame objects were drawn into
+    pub resolution: (i32, i32),
+    // every input event applied this frame, including injected ones
+    pub input: Vec<InputEvent>,
+    // resize, close and focus events from the window
+    pub window_events: Vec<Event>,
+}
+
+impl SessionFrame {
+    pub fn new() -> Self {
+        Self {
+            delta: 0.0,
+            resolution: (0, 0),
+            input: Vec::new(),
+            window_events: Vec::new(),
+        }
+    }
+}
+
+//#region SessionRecorder
+pub struct SessionRecorder {
+    writer: BufWriter<File>,
+    frame: SessionFrame,
+    frame_count: u64,
+}
+
+impl SessionRecorder {
+    // random state is that of the simulator's generator as recording starts
+    pub fn create(path: &Path, random_state: u64) -> io::Result<Self> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        writer.write_all(&random_state.to_le_bytes())?;
+        Ok(Self {
+            writer,
+            frame: SessionFrame::new(),
+            frame_count: 0,
+        })
+    }
+
+    pub fn get_frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    // the frame being recorded, written out by end_frame
+    pub fn get_frame_mut(&mut self) -> &mut SessionFrame {
+        &mut self.frame
+    }
+
+    // seeds are written straight away as they can be set between frames
+    pub fn record_seed(&mut self, seed: u64) -> io::Result<()> {
+        self.writer.write_all(&[SEED])?;
+        self.writer.write_all(&seed.to_le_bytes())
+    }
+
+    pub fn end_frame(&mut self) -> io::Result<()> {
+        let frame = std::mem::replace(&mut self.frame, SessionFrame::new());
+        let writer = &mut self.writer;
+        writer.write_all(&[FRAME])?;
+        writer.write_all(&frame.delta.to_bits().to_le_bytes())?;
+        writer.write_all(&frame.resolution.0.to_le_bytes())?;
+        writer.write_all(&frame.resolution.1.to_le_bytes())?;
+        for event in frame.input.iter() {
+            writer.write_all(&[INPUT])?;
+            match *event {
+                InputEvent::KeyDown(key) => {
+                    writer.write_all(&[0])?;
+                    writer.write_all(&key_to_code(key).to_le_bytes())?;
+                },
+                InputEvent::KeyUp(key) => {
+                    writer.write_all(&[1])?;
+                    writer.write_all(&key_to_code(key).to_le_bytes())?;
+                },
+                InputEvent::MouseDown(button) => {
+                    writer.write_all(&[2])?;
+                    writer.write_all(&button_to_code(button).to_le_bytes())?;
+                },
+                InputEvent::MouseUp(button) => {
+                    writer.write_all(&[3])?;
+                    writer.write_all(&button_to_code(button).to_le_bytes())?;
+                },
+                InputEvent::MouseMoved { x, y } => {
+                    writer.write_all(&[4])?;
+                    writer.write_all(&x.to_le_bytes())?;
+                    writer.write_all(&y.to_le_bytes())?;
+                },
+                InputEvent::MouseWheel(delta) => {
+                    writer.write_all(&[5])?;
+                    writer.write_all(&delta.to_bits().to_le_bytes())?;
+                }
+            }
+        }
+        for event in frame.window_events.iter() {
+            match *event {
+                Event::Resized { width, height } => {
+                    writer.write_all(&[WINDOW, 0])?;
+                    writer.write_all(&width.to_le_bytes())?;
+                    writer.write_all(&height.to_le_bytes())?;
+                },
+                Event::CloseRequested => writer.write_all(&[WINDOW, 1])?,
+                Event::FocusGained => writer.write_all(&[WINDOW, 2])?,
+                Event::FocusLost => writer.write_all(&[WINDOW, 3])?,
+                // only window events are recorded, the rest follow from them
+                _ => {}
+            }
+        }
+        self.frame_count += 1;
+        Ok(())
+    }
+
+    // flushes the file, returning how many frames were recorded
+    pub fn finish(mut self) -> io::Result<u64> {
+        self.writer.flush()?;
+        Ok(self.frame_count)
+    }
+}
+//#endregion
+
+//#region SessionReplay
+pub struct SessionReplay {
+    random_state: u64,
+    frames: VecDeque<SessionFrame>,
+    // seeds in the order they were set
+    seeds: VecDeque<u64>,
+    frame_count: usize,
+}
+
+fn read_bytes<const N: usize>(reader: &mut impl Read) -> io::Result<[u8; N]> {
+    let mut bytes = [0u8; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn read_u8(reader: &mut impl Read) -> io::Result<u8> {
+    Ok(read_bytes::<1>(reader)?[0])
+}
+
+fn read_u16(reader: &mut impl Read) -> io::Result<u16> {
+    Ok(u16::from_le_bytes(read_bytes(reader)?))
+}
+
+fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    Ok(u32::from_le_bytes(read_bytes(reader)?))
+}
+
+fn read_i32(reader: &mut impl Read) -> io::Result<i32> {
+    Ok(i32::from_le_bytes(read_bytes(reader)?))
+}
+
+fn read_u64(reader: &mut impl Read) -> io::Result<u64> {
+    Ok(u64::from_le_bytes(read_bytes(reader)?))
+}
+
+impl SessionReplay {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let mut reader = BufReader::new(File::open(path)?);
+        if &read_bytes::<8>(&mut reader)? != MAGIC {
+            return Err(invalid_data("Not a session file"));
+        }
+        let version = read_u32(&mut reader)?;
+        if version != VERSION {
+            return Err(invalid_data(&format!("Unsupported session version {}", version)));
+        }
+        let random_state = read_u64(&mut reader)?;
+        let mut frames = VecDeque::new();
+        let mut seeds = VecDeque::new();
+        loop {
+            let tag = match read_u8(&mut reader) {
+                Ok(tag) => tag,
+                Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => break,
+                Err(error) => return Err(error)
+            };
+            if tag == SEED {
+                seeds.push_back(read_u64(&mut reader)?);
+                continue;
+            }
+            if tag == FRAME {
+                let delta = f32::from_bits(read_u32(&mut reader)?);
+                let resolution = (read_i32(&mut reader)?, read_i32(&mut reader)?);
+                frames.push_back(SessionFrame { delta, resolution, input: Vec::new(), window_events: Vec::new() });
+                continue;
+            }
+            let frame = frames.back_mut().ok_or_else(|| invalid_data("Session record before the first frame"))?;
+            match tag {
+                INPUT => {
+                    let event = match read_u8(&mut reader)? {
+                        0 => InputEvent::KeyDown(key_from_code(read_u64(&mut reader)?)?),
+                        1 => InputEvent::KeyUp(key_from_code(read_u64(&mut reader)?)?),
+                        2 => InputEvent::MouseDown(button_from_code(read_u16(&mut reader)?)?),
+                        3 => InputEvent::MouseUp(button_from_code(read_u16(&mut reader)?)?),
+                        4 => InputEvent::MouseMoved { x: read_i32(&mut reader)?, y: read_i32(&mut reader)? },
+                        5 => InputEvent::MouseWheel(f32::from_bits(read_u32(&mut reader)?)),
+                        _ => return Err(invalid_data("Unknown input event in session"))
+                    };
+                    frame.input.push(event);
+                },
+                WINDOW => {
+                    let event = match read_u8(&mut reader)? {
+                        0 => Event::Resized { width: read_i32(&mut reader)?, height: read_i32(&mut reader)? },
+                        1 => Event::CloseRequested,
+                        2 => Event::FocusGained,
+                        3 => Event::FocusLost,
+                        _ => return Err(invalid_data("Unknown window event in session"))
+                    };
+                    frame.window_events.push(event);
+                },
+                _ => return Err(invalid_data("Unknown record in session"))
+            }
+        }
+        let frame_count = frames.len();
+        Ok(Self {
+            random_state,
+            frames,
+            seeds,
+            frame_count,
+        })
+    }
+
+    pub fn get_random_state(&self) -> u64 {
+        self.random_state
+    }
+
+    // total frames in the session
+    pub fn get_frame_count(&self) -> usize {
+        self.frame_count
+    }
+
+    pub fn get_remaining_frames(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn next_frame(&mut self) -> Option<SessionFrame> {
+        self.frames.pop_front()
+    }
+
+    // the next recorded seed, none once they have all been used
+    pub fn next_seed(&mut self) -> Option<u64> {
+        self.seeds.pop_front()
+    }
+}
+//#endregion
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_key_and_button_codes() {
+        for key in NAMED_KEYS.iter().copied().chain([Key::Other(0), Key::Other(0x8000_0001), Key::Other(u32::MAX)]) {
+            assert_eq!(key_from_code(key_to_code(key)).unwrap(), key);
+        }
+        for button in [MouseButton::Left, MouseButton::Right, MouseButton::Middle, MouseButton::Other(0), MouseButton::Other(253), MouseButton::Other(255)] {
+            assert_eq!(button_from_code(button_to_code(button)).unwrap(), button);
+        }
+        assert_ne!(button_to_code(MouseButton::Other(253)), button_to_code(MouseButton::Other(255)));
+        assert!(key_from_code(NAMED_KEYS.len() as u64).is_err());
+        assert!(key_from_code(OTHER_KEY | (1 << 33)).is_err());
+        assert!(button_from_code(259).is_err());
+    }
+
+    #[test]
+    fn replays_a_recorded_session() {
+        let path = std::env::temp_dir().join(format!("simulation_engine_session_{}.bin", std::process::id()));
+        let mut recorder = SessionRecorder::create(&path, 42).unwrap();
+        recorder.record_seed(7).unwrap();
+        let first = SessionFrame {
+            delta: 0.25,
+            resolution: (320, 200),
+            input: vec![
+                InputEvent::KeyDown(Key::A),
+                InputEvent::KeyUp(Key::Other(0xFFFF_FFFF)),
+                InputEvent::MouseDown(MouseButton::Other(255)),
+                InputEvent::MouseUp(MouseButton::Middle),
+                InputEvent::MouseMoved { x: -3, y: 40 },
+                InputEvent::MouseWheel(-1.5)
+            ],
+            window_events: vec![Event::Resized { width: 640, height: 400 }, Event::FocusLost, Event::Closed]
+        };
+        *recorder.get_frame_mut() = first.clone();
+        recorder.end_frame().unwrap();
+        recorder.get_frame_mut().delta = 0.5;
+        recorder.end_frame().unwrap();
+        assert_eq!(recorder.finish().unwrap(), 2);
+
+        let replay = SessionReplay::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        let mut replay = replay.unwrap();
+        assert_eq!(replay.get_random_state(), 42);
+        assert_eq!(replay.get_frame_count(), 2);
+        assert_eq!(replay.next_seed(), Some(7));
+        assert_eq!(replay.next_seed(), None);
+        // closing isn't a window event that gets recorded
+        let expected = SessionFrame {
+            window_events: vec![Event::Resized { width: 640, height: 400 }, Event::FocusLost],
+            ..first
+        };
+        assert_eq!(replay.next_frame(), Some(expected));
+        assert_eq!(replay.get_remaining_frames(), 1);
+        let second = replay.next_frame().unwrap();
+        assert_eq!(second.delta, 0.5);
+        assert!(second.input.is_empty());
+        assert_eq!(replay.next_frame(), None);
+    }
+}
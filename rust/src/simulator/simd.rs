@@ -0,0 +1,288 @@
+// portable simd helpers for the hot loops of the renderer, each function
+// picks the widest instruction set available at runtime and falls back
+// to a scalar loop on other architectures
+
+#[cfg(target_arch="x86")]
+use std::arch::x86::*;
+#[cfg(target_arch="x86_64")]
+use std::arch::x86_64::*;
+#[cfg(target_arch="aarch64")]
+use std::arch::aarch64::*;
+
+use std::time::Instant;
+
+use super::renderer::linearalgebra::{Matrix, Vector3D, Vector4D};
+
+//#region Fill
+pub fn fill_u32(buffer: &mut [u32], value: u32) {
+    #[cfg(any(target_arch="x86", target_arch="x86_64"))]
+    {
+        if is_x86_feature_detected!("avx2") {
+            return unsafe { fill_u32_avx2(buffer, value) };
+        }
+        if is_x86_feature_detected!("sse2") {
+            return unsafe { fill_u32_sse2(buffer, value) };
+        }
+    }
+    #[cfg(target_arch="aarch64")]
+    {
+        return unsafe { fill_u32_neon(buffer, value) };
+    }
+    #[allow(unreachable_code)]
+    fill_u32_scalar(buffer, value);
+}
+
+pub fn fill_u32_scalar(buffer: &mut [u32], value: u32) {
+    for pixel in buffer.iter_mut() {
+        *pixel = value;
+    }
+}
+
+#[cfg(any(target_arch="x86", target_arch="x86_64"))]
+#[target_feature(enable="avx2")]
+unsafe fn fill_u32_avx2(buffer: &mut [u32], value: u32) {
+    let packed = _mm256_set1_epi32(value as i32);
+    let mut chunks = buffer.chunks_exact_mut(8);
+    for chunk in &mut chunks {
+        _mm256_storeu_si256(chunk.as_mut_ptr() as *mut __m256i, packed);
+    }
+    fill_u32_scalar(chunks.into_remainder(), value);
+}
+
+#[cfg(any(target_arch="x86", target_arch="x86_64"))]
+#[target_feature(enable="sse2")]
+unsafe fn fill_u32_sse2(buffer: &mut [u32], value: u32) {
+    let packed = _mm_set1_epi32(value as i32);
+    let mut chunks = buffer.chunks_exact_mut(4);
+    for chunk in &mut chunks {
+        _mm_storeu_si128(chunk.as_mut_ptr() as *mut __m128i, packed);
+    }
+    fill_u32_scalar(chunks.into_remainder(), value);
+}
+
+#[cfg(target_arch="aarch64")]
+unsafe fn fill_u32_neon(buffer: &mut [u32], value: u32) {
+    let packed = vdupq_n_u32(value);
+    let mut chunks = buffer.chunks_exact_mut(4);
+    for chunk in &mut chunks {
+        vst1q_u32(chunk.as_mut_ptr(), packed);
+    }
+    fill_u32_scalar(chunks.into_remainder(), value);
+}
+//#endregion
+
+//#region Transform
+// multiplies every point (with w = 1) by a row major 4x4 matrix,
+// output must be at least as long as points
+pub fn transform_points(matrix: &[f32; 16], points: &[Vector3D], output: &mut [Vector4D]) {
+    assert!(output.len() >= points.len(), "Output buffer is smaller than the number of points");
+    #[cfg(any(target_arch="x86", target_arch="x86_64"))]
+    {
+        if is_x86_feature_detected!("sse2") {
+            return unsafe { transform_points_sse2(matrix, points, output) };
+        }
+    }
+    #[cfg(target_arch="aarch64")]
+    {
+        return unsafe { transform_points_neon(matrix, points, output) };
+    }
+    #[allow(unreachable_code)]
+    transform_points_scalar(matrix, points, output);
+}
+
+pub fn transform_points_scalar(matrix: &[f32; 16], points: &[Vector3D], output: &mut [Vector4D]) {
+    for (point, result) in points.iter().zip(output.iter_mut()) {
+        let row = |r: usize| {
+            matrix[r * 4] * point.x
+                + matrix[r * 4 + 1] * point.y
+                + matrix[r * 4 + 2] * point.z
+                + matrix[r * 4 + 3]
+        };
+        *result = Vector4D::new(row(0), row(1), row(2), row(3));
+    }
+}
+
+// the columns of the matrix are loaded once so each point costs
+// three multiplies and three adds across all four lanes
+#[cfg(any(target_arch="x86", target_arch="x86_64"))]
+#[target_feature(enable="sse2")]
+unsafe fn transform_points_sse2(matrix: &[f32; 16], points: &[Vector3D], output: &mut [Vector4D]) {
+    let column = |c: usize| _mm_set_ps(matrix[12 + c], matrix[8 + c], matrix[4 + c], matrix[c]);
+    let (c0, c1, c2, c3) = (column(0), column(1), column(2), column(3));
+    let mut lanes = [0.0f32; 4];
+    for (point, result) in points.iter().zip(output.iter_mut()) {
+        let transformed = _mm_add_ps(
+            _mm_add_ps(
+                _mm_mul_ps(c0, _mm_set1_ps(point.x)),
+                _mm_mul_ps(c1, _mm_set1_ps(point.y))
+            ),
+            _mm_add_ps(
+                _mm_mul_ps(c2, _mm_set1_ps(point.z)),
+                c3
+            )
+        );
+        _mm_storeu_ps(lanes.as_mut_ptr(), transformed);
+        *result = Vector4D::new(lanes[0], lanes[1], lanes[2], lanes[3]);
+    }
+}
+
+#[cfg(target_arch="aarch64")]
+unsafe fn transform_points_neon(matrix: &[f32; 16], points: &[Vector3D], output: &mut [Vector4D]) {
+    let column = |c: usize| {
+        let values = [matrix[c], matrix[4 + c], matrix[8 + c], matrix[12 + c]];
+        vld1q_f32(values.as_ptr())
+    };
+    let (c0, c1, c2, c3) = (column(0), column(1), column(2), column(3));
+    let mut lanes = [0.0f32; 4];
+    for (point, result) in points.iter().zip(output.iter_mut()) {
+        let transformed = vaddq_f32(
+            vaddq_f32(
+                vmulq_n_f32(c0, point.x),
+                vmulq_n_f32(c1, point.y)
+            ),
+            vaddq_f32(
+                vmulq_n_f32(c2, point.z),
+                c3
+            )
+        );
+        vst1q_f32(lanes.as_mut_ptr(), transformed);
+        *result = Vector4D::new(lanes[0], lanes[1], lanes[2], lanes[3]);
+    }
+}
+//#endregion
+
+//#region Benchmark
+// compares the simd paths against the scalar code they replaced,
+// run with the simd_bench feature
+pub fn benchmark() {
+    const PIXELS: usize = 1920 * 1080;
+    const FILL_ITERATIONS: u32 = 200;
+    const POINTS: usize = 100_000;
+    const TRANSFORM_ITERATIONS: u32 = 20;
+
+    let mut buffer = vec![0u32; PIXELS];
+
+    let timer = Instant::now();
+    for i in 0..FILL_ITERATIONS {
+        // the raw pointer loop previously used by Window::fill
+        unsafe {
+            let pointer = buffer.as_mut_ptr();
+            for offset in 0..PIXELS {
+                *pointer.add(offset) = i;
+            }
+        }
+    }
+    let scalar_fill = timer.elapsed();
+
+    let timer = Instant::now();
+    for i in 0..FILL_ITERATIONS {
+        fill_u32(&mut buffer, i);
+    }
+    let simd_fill = timer.elapsed();
+
+    println!("Fill ({} iterations of {} pixels):", FILL_ITERATIONS, PIXELS);
+    println!("\tScalar: {}ms", scalar_fill.as_millis());
+    println!("\tSIMD: {}ms", simd_fill.as_millis());
+
+    let matrix = (
+        Matrix::translation(Vector3D::new(1.0, 2.0, -20.0))
+            * Matrix::rotation_x(0.5)
+            * Matrix::rotation_y(0.25)
+            * Matrix::scaling(Vector3D::new(2.0, 2.0, 2.0))
+    ).expect("Benchmark matrices are all 4x4");
+    let array = matrix.to_array().expect("Benchmark matrix is 4x4");
+    let points: Vec<Vector3D> = (0..POINTS)
+        .map(|i| Vector3D::new(i as f32, (i % 7) as f32, (i % 13) as f32))
+        .collect();
+    let mut output = vec![Vector4D::ZERO; POINTS];
+
+    let timer = Instant::now();
+    for _ in 0..TRANSFORM_ITERATIONS {
+        // the per vertex Matrix path used by Renderer::project_point
+        for (point, result) in points.iter().zip(output.iter_mut()) {
+            let (transformed, w) = matrix.transform_point(*point).expect("Benchmark matrix is 4x4");
+            *result = transformed.extend(w);
+        }
+    }
+    let matrix_transform = timer.elapsed();
+
+    let timer = Instant::now();
+    for _ in 0..TRANSFORM_ITERATIONS {
+        transform_points_scalar(&array, &points, &mut output);
+    }
+    let scalar_transform = timer.elapsed();
+
+    let timer = Instant::now();
+    for _ in 0..TRANSFORM_ITERATIONS {
+        transform_points(&array, &points, &mut output);
+    }
+    let simd_transform = timer.elapsed();
+
+    println!("Vertex Transform ({} iterations of {} points):", TRANSFORM_ITERATIONS, POINTS);
+    println!("\tMatrix: {}ms", matrix_transform.as_millis());
+    println!("\tScalar: {}ms", scalar_transform.as_millis());
+    println!("\tSIMD: {}ms", simd_transform.as_millis());
+}
+//#endregion
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // every fill path available on this machine, the dispatching one included
+    fn fills() -> Vec<fn(&mut [u32], u32)> {
+        let mut fills: Vec<fn(&mut [u32], u32)> = vec![fill_u32];
+        #[cfg(any(target_arch="x86", target_arch="x86_64"))]
+        {
+            if is_x86_feature_detected!("avx2") {
+                fills.push(|buffer, value| unsafe { fill_u32_avx2(buffer, value) });
+            }
+            if is_x86_feature_detected!("sse2") {
+                fills.push(|buffer, value| unsafe { fill_u32_sse2(buffer, value) });
+            }
+        }
+        #[cfg(target_arch="aarch64")]
+        fills.push(|buffer, value| unsafe { fill_u32_neon(buffer, value) });
+        fills
+    }
+
+    #[test]
+    fn fills_match_the_scalar_fill() {
+        let mut expected = [1u32; 48];
+        let mut actual = [1u32; 48];
+        for fill in fills() {
+            // offsets so the slices start off lane boundaries, and lengths
+            // that leave every possible remainder
+            for start in 0..4 {
+                for length in 0..=(40 - start) {
+                    fill_u32_scalar(&mut expected[start..start + length], 0xAABB_CC00 | length as u32);
+                    fill(&mut actual[start..start + length], 0xAABB_CC00 | length as u32);
+                    assert_eq!(actual, expected, "start {} length {}", start, length);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn transforms_match_the_scalar_transform() {
+        let matrix = (
+            Matrix::translation(Vector3D::new(1.0, 2.0, -20.0))
+                * Matrix::rotation_x(0.5)
+                * Matrix::perspective(1.2, 1.5, 0.1, 100.0)
+        ).unwrap().to_array().unwrap();
+        for count in 0..=11 {
+            let points: Vec<Vector3D> = (0..count)
+                .map(|i| Vector3D::new(i as f32 * 1.5 - 4.0, (i % 3) as f32, -(i as f32)))
+                .collect();
+            let mut expected = vec![Vector4D::ZERO; count];
+            // longer than needed, the extra entries must be left alone
+            let mut actual = vec![Vector4D::new(9.0, 9.0, 9.0, 9.0); count + 3];
+            transform_points_scalar(&matrix, &points, &mut expected);
+            transform_points(&matrix, &points, &mut actual);
+            for (actual, expected) in actual.iter().zip(expected.iter()) {
+                assert!((*actual - *expected).length() <= 1e-5 * (1.0 + expected.length()), "{} != {}", actual, expected);
+            }
+            assert!(actual[count..].iter().all(|point| *point == Vector4D::new(9.0, 9.0, 9.0, 9.0)));
+        }
+    }
+}
@@ -3,26 +3,563 @@
 // reexport the Instant and Duration structs
 pub use std::time::{Instant, Duration};
 
+// weight given to the newest frame time in the smoothed average
+const SMOOTHING_FACTOR: f32 = 0.1;
+
+// how much simulation time a single step advances by default
+pub const DEFAULT_STEP_SIZE: f32 = 1.0 / 60.0;
+
+// simulation time is wall time scaled by the time scale, and stops while
+// paused apart from any frames that are stepped through
 pub struct Time {
     delta_time: f32,
-    last_update: Instant
+    unscaled_delta_time: f32,
+    smoothed_delta_time: f32,
+    time_scale: f32,
+    paused: bool,
+    pending_steps: u32,
+    step_size: f32,
+    elapsed_time: f64,
+    frame_count: u64,
+    simulated_frame_count: u64,
+    start: Instant,
+    last_update: Instant,
+    // when set every frame lasts this long regardless of the wall clock
+    fixed_delta: Option<f32>,
+    paced_frames: u64,
+    missed_deadlines: u64,
+    worst_overrun: Duration
 }
 
 impl Time {
     pub fn new() -> Self {
+        let now = Instant::now();
         Self {
             delta_time: 0.0,
-            last_update: Instant::now()
+            unscaled_delta_time: 0.0,
+            smoothed_delta_time: 0.0,
+            time_scale: 1.0,
+            paused: false,
+            pending_steps: 0,
+            step_size: DEFAULT_STEP_SIZE,
+            elapsed_time: 0.0,
+            frame_count: 0,
+            simulated_frame_count: 0,
+            start: now,
+            last_update: now,
+            fixed_delta: None,
+            paced_frames: 0,
+            missed_deadlines: 0,
+            worst_overrun: Duration::ZERO
         }
     }
 
+    // advances a frame, returning the simulation delta time
     pub fn update(&mut self) -> f32 {
-        self.delta_time = self.last_update.elapsed().as_secs_f32();
+        self.unscaled_delta_time = match self.fixed_delta {
+            Some(delta) => delta,
+            None => self.last_update.elapsed().as_secs_f32()
+        };
         self.last_update = Instant::now();
+        self.smoothed_delta_time = if self.smoothed_delta_time == 0.0 {
+            self.unscaled_delta_time
+        }
+        else {
+            self.smoothed_delta_time + (self.unscaled_delta_time - self.smoothed_delta_time) * SMOOTHING_FACTOR
+        };
+        self.delta_time = if self.pending_steps > 0 {
+            self.pending_steps -= 1;
+            self.step_size
+        }
+        else if self.paused {
+            0.0
+        }
+        else {
+            self.unscaled_delta_time * self.time_scale
+        };
+        self.elapsed_time += self.delta_time as f64;
+        self.frame_count += 1;
+        if self.delta_time > 0.0 {
+            self.simulated_frame_count += 1;
+        }
         self.delta_time
     }
 
+    // simulation time passed last frame, zero while paused
     pub fn get_delta_time(&self) -> f32 {
         self.delta_time
     }
-}
\ No newline at end of file
+
+    // wall time passed last frame, ignoring pausing and the time scale
+    pub fn get_unscaled_delta_time(&self) -> f32 {
+        self.unscaled_delta_time
+    }
+
+    pub fn get_fixed_delta(&self) -> Option<f32> {
+        self.fixed_delta
+    }
+
+    // decouples time from the wall clock, every frame then advances by
+    // exactly the given delta (before scaling), none goes back to real time
+    pub fn set_fixed_delta(&mut self, delta: Option<f32>) {
+        self.fixed_delta = delta.map(|delta| delta.max(0.0));
+        self.last_update = Instant::now();
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    // advances the next frame by the step size, even while paused
+    pub fn step(&mut self) {
+        self.step_frames(1);
+    }
+
+    pub fn step_frames(&mut self, frames: u32) {
+        self.pending_steps = self.pending_steps.saturating_add(frames);
+    }
+
+    pub fn get_step_size(&self) -> f32 {
+        self.step_size
+    }
+
+    pub fn set_step_size(&mut self, step_size: f32) {
+        self.step_size = step_size.max(0.0);
+    }
+
+    // 1 is real time, below 1 is slow motion and above is fast forward
+    pub fn get_time_scale(&self) -> f32 {
+        self.time_scale
+    }
+
+    pub fn set_time_scale(&mut self, time_scale: f32) {
+        self.time_scale = time_scale.max(0.0);
+    }
+
+    // total simulation time in seconds
+    pub fn get_elapsed_time(&self) -> f64 {
+        self.elapsed_time
+    }
+
+    // wall time since the simulation was created
+    pub fn get_real_elapsed_time(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    // frames that have been drawn
+    pub fn get_frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    // frames in which simulation time passed
+    pub fn get_simulated_frame_count(&self) -> u64 {
+        self.simulated_frame_count
+    }
+
+    // exponential moving average of the unscaled delta time, steadier than
+    // the raw value when frame times jitter
+    pub fn get_smoothed_delta_time(&self) -> f32 {
+        self.smoothed_delta_time
+    }
+
+    pub fn get_smoothed_frame_rate(&self) -> f32 {
+        if self.smoothed_delta_time > 0.0 {
+            1.0 / self.smoothed_delta_time
+        }
+        else {
+            0.0
+        }
+    }
+
+    // records the result of waiting for a frame deadline, an overrun of
+    // zero means the frame finished in time
+    pub fn record_pacing(&mut self, overrun: Duration) {
+        self.paced_frames += 1;
+        if overrun > Duration::ZERO {
+            self.missed_deadlines += 1;
+            self.worst_overrun = self.worst_overrun.max(overrun);
+        }
+    }
+
+    // frames that have been held to the target frame rate
+    pub fn get_paced_frames(&self) -> u64 {
+        self.paced_frames
+    }
+
+    // frames that took longer than the target frame time
+    pub fn get_missed_deadlines(&self) -> u64 {
+        self.missed_deadlines
+    }
+
+    pub fn get_missed_deadline_ratio(&self) -> f32 {
+        if self.paced_frames > 0 {
+            self.missed_deadlines as f32 / self.paced_frames as f32
+        }
+        else {
+            0.0
+        }
+    }
+
+    // the furthest any frame has run past its deadline
+    pub fn get_worst_overrun(&self) -> Duration {
+        self.worst_overrun
+    }
+
+    pub fn reset_pacing_stats(&mut self) {
+        self.paced_frames = 0;
+        self.missed_deadlines = 0;
+        self.worst_overrun = Duration::ZERO;
+    }
+}
+
+// remaining waits shorter than this are spun rather than slept, as a sleep
+// can overshoot by a millisecond or more depending on the platform timer
+pub const DEFAULT_SPIN_THRESHOLD: Duration = Duration::from_millis(2);
+
+// holds frames to a target rate by waiting until each frame's deadline
+pub struct FramePacer {
+    frame_delay: Duration,
+    spin_threshold: Duration,
+    next_deadline: Option<Instant>
+}
+
+impl FramePacer {
+    pub fn new(target_frame_rate: u16) -> Self {
+        let mut pacer = Self {
+            frame_delay: Duration::ZERO,
+            spin_threshold: DEFAULT_SPIN_THRESHOLD,
+            next_deadline: None
+        };
+        pacer.set_target_frame_rate(target_frame_rate);
+        pacer
+    }
+
+    pub fn set_target_frame_rate(&mut self, target: u16) {
+        self.frame_delay = Duration::from_nanos(1_000_000_000 / target.max(1) as u64);
+    }
+
+    pub fn get_frame_delay(&self) -> Duration {
+        self.frame_delay
+    }
+
+    pub fn get_spin_threshold(&self) -> Duration {
+        self.spin_threshold
+    }
+
+    pub fn set_spin_threshold(&mut self, threshold: Duration) {
+        self.spin_threshold = threshold;
+    }
+
+    // forgets the current deadline, used after pacing has been turned off
+    pub fn reset(&mut self) {
+        self.next_deadline = None;
+    }
+
+    // sleeps for most of the time left until the deadline then spins for the
+    // rest, returning how far past the deadline the frame already was
+    pub fn wait(&mut self) -> Duration {
+        let now = Instant::now();
+        let deadline = match self.next_deadline {
+            Some(deadline) => deadline,
+            None => {
+                self.next_deadline = Some(now + self.frame_delay);
+                return Duration::ZERO;
+            }
+        };
+        let overrun = now.saturating_duration_since(deadline);
+        if overrun == Duration::ZERO {
+            let remaining = deadline.saturating_duration_since(now);
+            if remaining > self.spin_threshold {
+                std::thread::sleep(remaining - self.spin_threshold);
+            }
+            while Instant::now() < deadline {
+                std::hint::spin_loop();
+            }
+        }
+        // deadlines follow on from each other so small errors don't drift,
+        // unless a whole frame has been missed in which case start afresh
+        self.next_deadline = Some(if overrun < self.frame_delay {
+            deadline + self.frame_delay
+        }
+        else {
+            Instant::now() + self.frame_delay
+        });
+        overrun
+    }
+}
+
+// past this many steps in one frame the remaining time is dropped, so a
+// slow frame can't cause ever more steps to be needed
+pub const DEFAULT_MAX_STEPS: u32 = 5;
+
+// accumulates frame time and hands it out in fixed size steps so the
+// simulation advances the same way regardless of frame rate
+pub struct FixedTimestep {
+    step: f64,
+    max_steps: u32,
+    accumulator: f64
+}
+
+impl FixedTimestep {
+    pub fn new(tick_rate: f32) -> Self {
+        let mut timestep = Self {
+            step: 0.0,
+            max_steps: DEFAULT_MAX_STEPS,
+            accumulator: 0.0
+        };
+        timestep.set_tick_rate(tick_rate);
+        timestep
+    }
+
+    // steps per second
+    pub fn get_tick_rate(&self) -> f32 {
+        (1.0 / self.step) as f32
+    }
+
+    pub fn set_tick_rate(&mut self, tick_rate: f32) {
+        self.step = 1.0 / (tick_rate as f64).max(f64::EPSILON);
+    }
+
+    // length of each step in seconds
+    pub fn get_step(&self) -> f32 {
+        self.step as f32
+    }
+
+    pub fn get_max_steps(&self) -> u32 {
+        self.max_steps
+    }
+
+    pub fn set_max_steps(&mut self, max_steps: u32) {
+        self.max_steps = max_steps.max(1);
+    }
+
+    // adds the time that has passed and returns how many steps to run
+    pub fn advance(&mut self, delta: f32) -> u32 {
+        self.accumulator += delta.max(0.0) as f64;
+        let steps = (self.accumulator / self.step).floor();
+        if steps > self.max_steps as f64 {
+            self.accumulator %= self.step;
+            self.max_steps
+        }
+        else {
+            self.accumulator -= steps * self.step;
+            steps as u32
+        }
+    }
+
+    // how far the leftover time is towards the next step, from 0 to 1
+    pub fn get_alpha(&self) -> f32 {
+        ((self.accumulator / self.step) as f32).clamp(0.0, 1.0)
+    }
+
+    pub fn reset(&mut self) {
+        self.accumulator = 0.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixed_time(delta: f32) -> Time {
+        let mut time = Time::new();
+        time.set_fixed_delta(Some(delta));
+        time
+    }
+
+    #[test]
+    fn advances_by_the_fixed_delta() {
+        let mut time = fixed_time(0.25);
+        assert_eq!(time.update(), 0.25);
+        assert_eq!(time.update(), 0.25);
+        assert_eq!(time.get_elapsed_time(), 0.5);
+        assert_eq!(time.get_frame_count(), 2);
+        assert_eq!(time.get_simulated_frame_count(), 2);
+        assert_eq!(time.get_smoothed_delta_time(), 0.25);
+        assert_eq!(time.get_smoothed_frame_rate(), 4.0);
+        time.set_fixed_delta(Some(-1.0));
+        assert_eq!(time.get_fixed_delta(), Some(0.0));
+    }
+
+    #[test]
+    fn freezes_while_paused() {
+        let mut time = fixed_time(0.25);
+        time.update();
+        time.pause();
+        assert!(time.is_paused());
+        assert_eq!(time.update(), 0.0);
+        assert_eq!(time.update(), 0.0);
+        // wall time still passes and frames are still counted
+        assert_eq!(time.get_unscaled_delta_time(), 0.25);
+        assert_eq!(time.get_elapsed_time(), 0.25);
+        assert_eq!(time.get_frame_count(), 3);
+        assert_eq!(time.get_simulated_frame_count(), 1);
+
+        time.resume();
+        assert_eq!(time.update(), 0.25);
+        assert_eq!(time.get_elapsed_time(), 0.5);
+        time.toggle_pause();
+        assert!(time.is_paused());
+        time.toggle_pause();
+        assert!(!time.is_paused());
+    }
+
+    #[test]
+    fn scales_with_the_time_scale() {
+        let mut time = fixed_time(0.25);
+        time.set_time_scale(2.0);
+        assert_eq!(time.update(), 0.5);
+        time.set_time_scale(0.5);
+        assert_eq!(time.update(), 0.125);
+        assert_eq!(time.get_unscaled_delta_time(), 0.25);
+        assert_eq!(time.get_elapsed_time(), 0.625);
+        // a zero scale stops time without pausing
+        time.set_time_scale(-1.0);
+        assert_eq!(time.get_time_scale(), 0.0);
+        assert_eq!(time.update(), 0.0);
+        assert_eq!(time.get_simulated_frame_count(), 2);
+        assert!(!time.is_paused());
+    }
+
+    #[test]
+    fn steps_single_frames_while_paused() {
+        let mut time = fixed_time(0.25);
+        time.set_step_size(0.125);
+        time.pause();
+        time.step();
+        // the step size is used regardless of the frame time and scale
+        time.set_time_scale(4.0);
+        assert_eq!(time.update(), 0.125);
+        assert_eq!(time.update(), 0.0);
+        assert_eq!(time.get_elapsed_time(), 0.125);
+
+        time.step_frames(2);
+        assert_eq!(time.update(), 0.125);
+        assert_eq!(time.update(), 0.125);
+        assert_eq!(time.update(), 0.0);
+        assert_eq!(time.get_elapsed_time(), 0.375);
+        assert_eq!(time.get_frame_count(), 5);
+        assert_eq!(time.get_simulated_frame_count(), 3);
+        time.set_step_size(-1.0);
+        assert_eq!(time.get_step_size(), 0.0);
+    }
+
+    #[test]
+    fn smooths_the_frame_time() {
+        let mut time = fixed_time(0.1);
+        time.update();
+        assert_eq!(time.get_smoothed_delta_time(), 0.1);
+        time.set_fixed_delta(Some(0.2));
+        time.update();
+        assert!((time.get_smoothed_delta_time() - 0.11).abs() < 1e-6);
+        for _ in 0..200 {
+            time.update();
+        }
+        assert!((time.get_smoothed_delta_time() - 0.2).abs() < 1e-4);
+        assert!((time.get_smoothed_frame_rate() - 5.0).abs() < 1e-2);
+        assert_eq!(Time::new().get_smoothed_frame_rate(), 0.0);
+    }
+
+    #[test]
+    fn counts_missed_deadlines() {
+        let mut time = Time::new();
+        assert_eq!(time.get_missed_deadline_ratio(), 0.0);
+        time.record_pacing(Duration::ZERO);
+        time.record_pacing(Duration::from_millis(3));
+        time.record_pacing(Duration::from_millis(1));
+        time.record_pacing(Duration::ZERO);
+        assert_eq!(time.get_paced_frames(), 4);
+        assert_eq!(time.get_missed_deadlines(), 2);
+        assert_eq!(time.get_missed_deadline_ratio(), 0.5);
+        assert_eq!(time.get_worst_overrun(), Duration::from_millis(3));
+        time.reset_pacing_stats();
+        assert_eq!(time.get_paced_frames(), 0);
+        assert_eq!(time.get_missed_deadlines(), 0);
+        assert_eq!(time.get_worst_overrun(), Duration::ZERO);
+    }
+
+    #[test]
+    fn waits_until_the_deadline() {
+        let mut pacer = FramePacer::new(100);
+        assert_eq!(pacer.get_frame_delay(), Duration::from_millis(10));
+        // the first frame only sets the deadline
+        let start = Instant::now();
+        assert_eq!(pacer.wait(), Duration::ZERO);
+        assert_eq!(pacer.wait(), Duration::ZERO);
+        assert!(start.elapsed() >= Duration::from_millis(10));
+        assert_eq!(pacer.wait(), Duration::ZERO);
+        assert!(start.elapsed() >= Duration::from_millis(20));
+
+        pacer.set_target_frame_rate(0);
+        assert_eq!(pacer.get_frame_delay(), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn recovers_from_a_frame_past_its_deadline() {
+        let mut pacer = FramePacer::new(100);
+        pacer.set_spin_threshold(Duration::from_millis(20));
+        pacer.wait();
+        std::thread::sleep(Duration::from_millis(35));
+        // the deadline is behind the current time, which must not underflow
+        let overrun = pacer.wait();
+        assert!(overrun >= Duration::from_millis(25));
+        // with whole frames missed the next deadline is a frame from now,
+        // rather than further frames all being late trying to catch up
+        let start = Instant::now();
+        assert_eq!(pacer.wait(), Duration::ZERO);
+        assert!(start.elapsed() >= Duration::from_millis(5));
+
+        pacer.reset();
+        assert_eq!(pacer.wait(), Duration::ZERO);
+    }
+
+    #[test]
+    fn accumulates_whole_steps() {
+        let mut timestep = FixedTimestep::new(10.0);
+        assert_eq!(timestep.advance(0.05), 0);
+        assert!((timestep.get_alpha() - 0.5).abs() < 1e-5);
+        assert_eq!(timestep.advance(0.1), 1);
+        assert!((timestep.get_alpha() - 0.5).abs() < 1e-5);
+        assert_eq!(timestep.advance(0.25), 3);
+        assert!(timestep.get_alpha() < 1e-5);
+        // negative deltas don't take time away
+        assert_eq!(timestep.advance(-1.0), 0);
+        assert!(timestep.get_alpha() < 1e-5);
+    }
+
+    #[test]
+    fn drops_time_past_the_max_steps() {
+        let mut timestep = FixedTimestep::new(10.0);
+        timestep.set_max_steps(2);
+        assert_eq!(timestep.advance(1.05), 2);
+        // only the partial step is kept, so alpha stays within 0 and 1
+        assert!((timestep.get_alpha() - 0.5).abs() < 1e-4);
+        assert_eq!(timestep.advance(0.0), 0);
+
+        timestep.reset();
+        assert_eq!(timestep.get_alpha(), 0.0);
+        timestep.set_max_steps(0);
+        assert_eq!(timestep.get_max_steps(), 1);
+    }
+
+    #[test]
+    fn clamps_the_tick_rate() {
+        let mut timestep = FixedTimestep::new(50.0);
+        assert!((timestep.get_step() - 0.02).abs() < 1e-6);
+        timestep.set_tick_rate(0.0);
+        assert!(timestep.get_step().is_finite());
+        assert_eq!(timestep.advance(1.0), 0);
+    }
+}
@@ -1,5 +1,6 @@
 use std::cmp::{Eq, PartialEq};
 
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 enum ColorMode {
     RGB,
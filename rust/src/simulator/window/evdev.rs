@@ -0,0 +1,269 @@
+// keyboard and mouse input read from /dev/input/event* for backends without
+// a window system to deliver it, each device is read on its own thread as
+// the reads block, the threads wait for input with a timeout so they notice
+// when the input is dropped and stop
+
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::mem;
+use std::os::raw::{c_int, c_short};
+use std::os::unix::io::AsRawFd;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread::{self, JoinHandle};
+
+use super::super::input::{InputEvent, Key, MouseButton};
+
+// struct input_event is a timeval followed by type, code and value
+const TIME_SIZE: usize = 2 * mem::size_of::<isize>();
+const EVENT_SIZE: usize = TIME_SIZE + 8;
+
+const EV_KEY: u16 = 0x01;
+const EV_REL: u16 = 0x02;
+const REL_X: u16 = 0x00;
+const REL_Y: u16 = 0x01;
+const REL_WHEEL: u16 = 0x08;
+const BTN_LEFT: u16 = 0x110;
+const BTN_RIGHT: u16 = 0x111;
+const BTN_MIDDLE: u16 = 0x112;
+
+// how long a reader waits for input before checking whether it should stop
+const READ_TIMEOUT_MS: c_int = 100;
+const POLLIN: c_short = 0x001;
+
+#[repr(C)]
+struct PollFd {
+    fd: c_int,
+    events: c_short,
+    revents: c_short,
+}
+
+extern "C" {
+    fn poll(fds: *mut PollFd, count: std::os::raw::c_ulong, timeout: c_int) -> c_int;
+}
+
+#[derive(Copy, Clone)]
+struct RawEvent {
+    kind: u16,
+    code: u16,
+    value: i32,
+}
+
+pub struct EvdevInput {
+    receiver: Receiver<RawEvent>,
+    running: Arc<AtomicBool>,
+    readers: Vec<JoinHandle<()>>,
+    // the cursor is tracked from relative movement and kept on the surface
+    mouse_position: (i32, i32),
+    size: (i32, i32),
+}
+
+impl EvdevInput {
+    // none if no input devices could be opened, usually due to permissions
+    pub fn open(size: (i32, i32)) -> Option<Self> {
+        let entries = fs::read_dir("/dev/input").ok()?;
+        let (sender, receiver) = mpsc::channel();
+        let running = Arc::new(AtomicBool::new(true));
+        let mut readers = Vec::new();
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let is_event_device = entry.file_name()
+                .to_str()
+                .is_some_and(|name| name.starts_with("event"));
+            if !is_event_device {
+                continue;
+            }
+            if let Ok(file) = File::open(entry.path()) {
+                readers.push(spawn_reader(file, sender.clone(), running.clone()));
+            }
+        }
+        if readers.is_empty() {
+            eprintln!("No readable input devices in /dev/input, input is disabled");
+            return None;
+        }
+        Some(Self {
+            receiver,
+            running,
+            readers,
+            mouse_position: (size.0 / 2, size.1 / 2),
+            size,
+        })
+    }
+
+    // translates everything read since the last call
+    pub fn poll(&mut self, input: &mut Vec<InputEvent>) {
+        let mut moved = false;
+        while let Ok(event) = self.receiver.try_recv() {
+            match (event.kind, event.code) {
+                (EV_REL, REL_X) => {
+                    self.mouse_position.0 = (self.mouse_position.0 + event.value).max(0).min(self.size.0 - 1);
+                    moved = true;
+                },
+                (EV_REL, REL_Y) => {
+                    self.mouse_position.1 = (self.mouse_position.1 + event.value).max(0).min(self.size.1 - 1);
+                    moved = true;
+                },
+                (EV_REL, REL_WHEEL) => input.push(InputEvent::MouseWheel(event.value as f32)),
+                (EV_KEY, code) => {
+                    // value is 1 for a press, 0 for a release and 2 for a repeat
+                    let down = event.value != 0;
+                    let mouse_button = match code {
+                        BTN_LEFT => Some(MouseButton::Left),
+                        BTN_RIGHT => Some(MouseButton::Right),
+                        BTN_MIDDLE => Some(MouseButton::Middle),
+                        0x113..=0x117 => Some(MouseButton::Other((code - BTN_LEFT) as u8)),
+                        _ => None
+                    };
+                    input.push(match (mouse_button, down) {
+                        (Some(button), true) => InputEvent::MouseDown(button),
+                        (Some(button), false) => InputEvent::MouseUp(button),
+                        (None, true) => InputEvent::KeyDown(key_from_code(code)),
+                        (None, false) => InputEvent::KeyUp(key_from_code(code))
+                    });
+                },
+                _ => {}
+            }
+        }
+        if moved {
+            input.push(InputEvent::MouseMoved { x: self.mouse_position.0, y: self.mouse_position.1 });
+        }
+    }
+}
+
+impl Drop for EvdevInput {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Release);
+        for reader in self.readers.drain(..) {
+            let _ = reader.join();
+        }
+    }
+}
+
+// waits for the device to have an event to read, false on timeout, errors
+// once the device has gone
+fn wait_readable(file: &File) -> io::Result<bool> {
+    let mut poll_fd = PollFd {
+        fd: file.as_raw_fd(),
+        events: POLLIN,
+        revents: 0,
+    };
+    let result = unsafe { poll(&mut poll_fd, 1, READ_TIMEOUT_MS) };
+    if result < 0 {
+        let error = io::Error::last_os_error();
+        return match error.kind() {
+            io::ErrorKind::Interrupted => Ok(false),
+            _ => Err(error)
+        };
+    }
+    if result == 0 {
+        return Ok(false);
+    }
+    if poll_fd.revents & POLLIN == 0 {
+        // hung up or errored without anything left to read
+        return Err(io::Error::new(io::ErrorKind::BrokenPipe, "Input device is no longer readable"));
+    }
+    Ok(true)
+}
+
+// the thread ends once the input is dropped, or the device can't be read
+fn spawn_reader(mut file: File, sender: Sender<RawEvent>, running: Arc<AtomicBool>) -> JoinHandle<()> {
+    thread::spawn(move || {
+        let mut bytes = [0u8; EVENT_SIZE];
+        while running.load(Ordering::Acquire) {
+            match wait_readable(&file) {
+                Ok(true) => {},
+                Ok(false) => continue,
+                Err(_) => break
+            }
+            if file.read_exact(&mut bytes).is_err() {
+                break;
+            }
+            let event = RawEvent {
+                kind: u16::from_ne_bytes([bytes[TIME_SIZE], bytes[TIME_SIZE + 1]]),
+                code: u16::from_ne_bytes([bytes[TIME_SIZE + 2], bytes[TIME_SIZE + 3]]),
+                value: i32::from_ne_bytes([
+                    bytes[TIME_SIZE + 4],
+                    bytes[TIME_SIZE + 5],
+                    bytes[TIME_SIZE + 6],
+                    bytes[TIME_SIZE + 7]
+                ]),
+            };
+            if sender.send(event).is_err() {
+                break;
+            }
+        }
+    })
+}
+
+// linux key codes from input-event-codes.h
+fn key_from_code(code: u16) -> Key {
+    match code {
+        1 => Key::Escape,
+        2 => Key::Num1,
+        3 => Key::Num2,
+        4 => Key::Num3,
+        5 => Key::Num4,
+        6 => Key::Num5,
+        7 => Key::Num6,
+        8 => Key::Num7,
+        9 => Key::Num8,
+        10 => Key::Num9,
+        11 => Key::Num0,
+        14 => Key::Backspace,
+        15 => Key::Tab,
+        16 => Key::Q,
+        17 => Key::W,
+        18 => Key::E,
+        19 => Key::R,
+        20 => Key::T,
+        21 => Key::Y,
+        22 => Key::U,
+        23 => Key::I,
+        24 => Key::O,
+        25 => Key::P,
+        28 => Key::Enter,
+        29 | 97 => Key::Control,
+        30 => Key::A,
+        31 => Key::S,
+        32 => Key::D,
+        33 => Key::F,
+        34 => Key::G,
+        35 => Key::H,
+        36 => Key::J,
+        37 => Key::K,
+        38 => Key::L,
+        42 | 54 => Key::Shift,
+        44 => Key::Z,
+        45 => Key::X,
+        46 => Key::C,
+        47 => Key::V,
+        48 => Key::B,
+        49 => Key::N,
+        50 => Key::M,
+        56 | 100 => Key::Alt,
+        57 => Key::Space,
+        59 => Key::F1,
+        60 => Key::F2,
+        61 => Key::F3,
+        62 => Key::F4,
+        63 => Key::F5,
+        64 => Key::F6,
+        65 => Key::F7,
+        66 => Key::F8,
+        67 => Key::F9,
+        68 => Key::F10,
+        87 => Key::F11,
+        88 => Key::F12,
+        102 => Key::Home,
+        103 => Key::Up,
+        104 => Key::PageUp,
+        105 => Key::Left,
+        106 => Key::Right,
+        107 => Key::End,
+        108 => Key::Down,
+        109 => Key::PageDown,
+        110 => Key::Insert,
+        111 => Key::Delete,
+        code => Key::Other(code as u32)
+    }
+}
@@ -0,0 +1,169 @@
+// draws straight to a linux framebuffer device such as /dev/fb0, for use on
+// a console without any display server, the frame is placed at the builder's
+// position and clipped to the screen
+
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::os::unix::fs::FileExt;
+use std::path::Path;
+
+use super::{WindowBuilder, Surface};
+use super::super::dirty::Rect;
+#[cfg(target_os="linux")]
+use super::super::input::InputEvent;
+#[cfg(target_os="linux")]
+use super::super::events::Event;
+#[cfg(target_os="linux")]
+use super::evdev::EvdevInput;
+
+pub const DEFAULT_DEVICE: &str = "/dev/fb0";
+
+pub struct FramebufferSurface {
+    file: Option<File>,
+    // size of the whole screen
+    screen_size: (i32, i32),
+    // bytes per row, which can be more than width * bytes per pixel
+    stride: usize,
+    bytes_per_pixel: usize,
+    position: (i32, i32),
+    size: (i32, i32),
+    row_bytes: Vec<u8>,
+    #[cfg(target_os="linux")]
+    input: Option<EvdevInput>,
+}
+
+// reads a value such as "1920,1080" or "32" from the device's sysfs entry
+fn read_attribute(device: &str, attribute: &str) -> io::Result<Vec<usize>> {
+    let name = Path::new(device)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Invalid framebuffer device"))?;
+    let text = fs::read_to_string(Path::new("/sys/class/graphics").join(name).join(attribute))?;
+    text.trim()
+        .split(',')
+        .map(|value| value.trim().parse::<usize>()
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error)))
+        .collect()
+}
+
+impl FramebufferSurface {
+    pub fn open(device: &str, builder: &WindowBuilder) -> io::Result<Self> {
+        let file = OpenOptions::new().write(true).open(device)?;
+        let screen_size = read_attribute(device, "virtual_size")?;
+        if screen_size.len() != 2 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Unexpected framebuffer size"));
+        }
+        let screen_size = (screen_size[0] as i32, screen_size[1] as i32);
+        let bits_per_pixel = read_attribute(device, "bits_per_pixel")?[0];
+        if bits_per_pixel != 32 && bits_per_pixel != 16 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Unsupported framebuffer depth of {} bits", bits_per_pixel)
+            ));
+        }
+        let bytes_per_pixel = bits_per_pixel / 8;
+        let stride = read_attribute(device, "stride")
+            .map(|stride| stride[0])
+            .unwrap_or(screen_size.0 as usize * bytes_per_pixel);
+        let position = (
+            builder.x.unwrap_or(0).max(0).min(screen_size.0),
+            builder.y.unwrap_or(0).max(0).min(screen_size.1)
+        );
+        // fills the screen unless told otherwise, as if maximized
+        let mut size = (
+            builder.width.unwrap_or(screen_size.0),
+            builder.height.unwrap_or(screen_size.1)
+        );
+        if builder.start_maximized {
+            size = screen_size;
+        }
+        let size = (
+            size.0.max(0).min(screen_size.0 - position.0),
+            size.1.max(0).min(screen_size.1 - position.1)
+        );
+        Ok(Self {
+            file: Some(file),
+            screen_size,
+            stride,
+            bytes_per_pixel,
+            position,
+            size,
+            row_bytes: Vec::new(),
+            #[cfg(target_os="linux")]
+            input: EvdevInput::open(size),
+        })
+    }
+
+    fn write_frame(&mut self, frame: &[u32], size: (i32, i32), regions: Option<&[Rect]>) -> io::Result<()> {
+        let file = match &self.file {
+            Some(file) => file,
+            None => return Ok(())
+        };
+        let (width, height) = size;
+        let full = [Rect::new(0, 0, width, height)];
+        let (visible_width, visible_height) = (width.min(self.size.0), height.min(self.size.1));
+        let regions = regions.unwrap_or(&full).iter()
+            .filter_map(|region| region.clip(visible_width, visible_height));
+        for region in regions {
+            for y in region.y..region.get_bottom() {
+                let start = (y * width + region.x) as usize;
+                let pixels = &frame[start..start + region.width as usize];
+                self.row_bytes.clear();
+                if self.bytes_per_pixel == 4 {
+                    for pixel in pixels.iter() {
+                        self.row_bytes.extend_from_slice(&pixel.to_le_bytes());
+                    }
+                }
+                else {
+                    // rgb565
+                    for pixel in pixels.iter() {
+                        let red = (pixel >> 19) & 0x1F;
+                        let green = (pixel >> 10) & 0x3F;
+                        let blue = (pixel >> 3) & 0x1F;
+                        let packed = ((red << 11) | (green << 5) | blue) as u16;
+                        self.row_bytes.extend_from_slice(&packed.to_le_bytes());
+                    }
+                }
+                let offset = (self.position.1 + y) as usize * self.stride
+                    + (self.position.0 + region.x) as usize * self.bytes_per_pixel;
+                file.write_all_at(&self.row_bytes, offset as u64)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Surface for FramebufferSurface {
+    fn is_running(&self) -> bool {
+        self.file.is_some()
+    }
+
+    fn get_client_size(&self) -> (i32, i32) {
+        self.size
+    }
+
+    fn get_window_pos(&self) -> (i32, i32) {
+        self.position
+    }
+
+    fn present(&mut self, frame: &[u32], size: (i32, i32), regions: Option<&[Rect]>) {
+        if let Err(error) = self.write_frame(frame, size, regions) {
+            eprintln!("Failed to write to framebuffer: {}", error);
+        }
+    }
+
+    #[cfg(target_os="linux")]
+    fn poll_events(&mut self, input: &mut Vec<InputEvent>, _events: &mut Vec<Event>) {
+        if let Some(devices) = &mut self.input {
+            devices.poll(input);
+        }
+    }
+
+    fn close(&mut self) {
+        self.file = None;
+        #[cfg(target_os="linux")]
+        {
+            self.input = None;
+        }
+    }
+}
@@ -0,0 +1,35 @@
+// a backend that shows nothing, frames are still drawn so the simulator
+// behaves the same as it would with a real window
+
+use super::Surface;
+use super::super::dirty::Rect;
+
+pub struct HeadlessSurface {
+    size: (i32, i32),
+    running: bool,
+}
+
+impl HeadlessSurface {
+    pub fn new(size: (i32, i32)) -> Self {
+        Self {
+            size: (size.0.max(0), size.1.max(0)),
+            running: true,
+        }
+    }
+}
+
+impl Surface for HeadlessSurface {
+    fn is_running(&self) -> bool {
+        self.running
+    }
+
+    fn get_client_size(&self) -> (i32, i32) {
+        self.size
+    }
+
+    fn present(&mut self, _frame: &[u32], _size: (i32, i32), _regions: Option<&[Rect]>) {}
+
+    fn close(&mut self) {
+        self.running = false;
+    }
+}
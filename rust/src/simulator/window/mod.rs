@@ -1,137 +1,92 @@
 #![allow(dead_code)]
 
-// prevent console from opening
-#![windows_subsystem = "windows"]
+// the window owns the pixel buffers and hands finished frames to a backend
+// surface, which is the only platform specific part
 
-// https://docs.rs/winapi/*/x86_64-pc-windows-msvc/winapi/um/libloaderapi/index.html?search=winuser
-extern crate winapi;
-
-use std::ffi::OsStr;
-use std::os::windows::ffi::OsStrExt;
-use std::iter::once;
-use std::mem;
-use std::ptr::{null_mut};
-use std::time::{Instant, Duration};
+use std::time::{Duration, Instant};
 use std::cmp::{min, max};
-
-use self::winapi::um::libloaderapi::GetModuleHandleW;
-use self::winapi::um::winuser::{
-    DefWindowProcW,
-    RegisterClassW,
-    CreateWindowExW,
-    IsWindow,
-    GetDC,
-    GetClientRect,
-    GetWindowRect,
-    TranslateMessage,
-    DispatchMessageW,
-    PeekMessageW,
-    BeginDeferWindowPos,
-    DeferWindowPos,
-    EndDeferWindowPos,
-    TrackMouseEvent,
-    GetCursorPos,
-    GetAsyncKeyState,
-    ShowWindow,
-};
-use self::winapi::um::winuser::{
-    MSG,
-    WNDCLASSW,
-    CS_OWNDC,
-    CS_HREDRAW,
-    CS_VREDRAW,
-    CW_USEDEFAULT,
-    WS_OVERLAPPEDWINDOW,
-    WS_VISIBLE,
-    WS_MAXIMIZEBOX,
-    WS_SIZEBOX,
-    SW_SHOWMAXIMIZED,
-    PM_REMOVE,
-    HOVER_DEFAULT,
-    VK_LBUTTON,
-};
-// windows messages
-use self::winapi::um::winuser::{
-    WM_MOUSEMOVE,
-    WM_MOUSELEAVE,
-    WM_RBUTTONDOWN,
-    WM_RBUTTONUP,
-    WM_LBUTTONDOWN,
-    WM_LBUTTONUP,
-    WM_NCLBUTTONDOWN,
-    WM_NCLBUTTONUP,
-    WM_NCMOUSEMOVE,
-    WM_NCMOUSELEAVE,
-    WM_SYSCOMMAND,
-    SC_SIZE,
-    SWP_DRAWFRAME,
-    SWP_NOOWNERZORDER,
-    TME_LEAVE,
-    TME_NONCLIENT,
-};
-// windows nc hit values
-use self::winapi::um::winuser::{
-    HTLEFT,
-    HTRIGHT,
-    HTTOP,
-    HTTOPLEFT,
-    HTTOPRIGHT,
-    HTBOTTOM,
-    HTBOTTOMLEFT,
-    HTBOTTOMRIGHT,
-    TRACKMOUSEEVENT,
-};
-use self::winapi::um::wingdi::{
-    StretchDIBits,
-    SRCCOPY,
-    BITMAPINFO,
-    BITMAPINFOHEADER,
-    DIB_RGB_COLORS,
-    BI_RGB,
-};
-use self::winapi::um::winnt::{
-    VOID,
-    PAGE_READWRITE,
-    MEM_RESERVE,
-    MEM_COMMIT,
-    MEM_RELEASE,
-};
-use self::winapi::um::memoryapi::{
-    VirtualAlloc,
-    VirtualFree,
-};
-
-use winapi::shared::minwindef::{
-    DWORD,
-    LPARAM,
-    LRESULT,
-    UINT,
-};
-use self::winapi::shared::basetsd::{
-    SIZE_T,
-};
-use self::winapi::shared::windef::{
-    HWND,
-    HDC,
-    RECT,
-    POINT,
-};
-
-use std::os::raw::c_int;
-use std::io::Error;
+use std::mem;
 
 // export color module
 pub mod color;
 use color::Color;
 
-static mut WINDOWCOUNT: u32 = 0;
+mod swapchain;
+use swapchain::SwapChain;
+
+#[cfg(windows)]
+mod win32;
+#[cfg(unix)]
+mod framebuffer;
+#[cfg(unix)]
+mod shm;
+#[cfg(target_os="linux")]
+mod evdev;
+mod headless;
+mod terminal;
+
+use super::simd;
+use super::dirty::Rect;
+use super::input::InputEvent;
+use super::events::Event;
+
+// size used when the backend has no size of its own to fall back on
+const DEFAULT_SIZE: (i32, i32) = (800, 600);
+
+// where frames are presented
+#[derive(Clone, Debug, PartialEq)]
+pub enum Backend {
+    // win32 on windows, the framebuffer device on unix, falling back to
+    // headless if it can't be opened
+    Native,
+    // nothing is shown, for tests and containers
+    Headless,
+    // a linux framebuffer device such as /dev/fb0
+    Framebuffer(String),
+    // frames are written to a shared memory file for a viewer to show
+    SharedMemory(String),
+    // frames are drawn to the terminal with ansi colors, for use over ssh
+    Terminal,
+}
+
+// a platform specific destination for the presented frames
+trait Surface {
+    fn is_running(&self) -> bool;
+
+    // size of the area frames are shown in
+    fn get_client_size(&self) -> (i32, i32);
+
+    fn get_window_size(&self) -> (i32, i32) {
+        self.get_client_size()
+    }
+
+    fn get_window_pos(&self) -> (i32, i32) {
+        (0, 0)
+    }
+
+    // shows the frame, only the given regions need copying if there are any
+    fn present(&mut self, frame: &[u32], size: (i32, i32), regions: Option<&[Rect]>);
+
+    // waits until the display is ready for another frame
+    fn wait_for_vsync(&mut self) {}
+
+    // gathers the input and window events since the last call
+    fn poll_events(&mut self, _input: &mut Vec<InputEvent>, _events: &mut Vec<Event>) {}
+
+    fn has_focus(&self) -> bool {
+        true
+    }
+
+    fn close(&mut self);
+}
 
 pub struct WindowBuilder{
-    title: Vec<u16>,
-    x: c_int,
-    y: c_int,
-    width: c_int,
-    height: c_int,
+    title: String,
+    // none leaves the choice to the backend
+    x: Option<i32>,
+    y: Option<i32>,
+    width: Option<i32>,
+    height: Option<i32>,
     background_color: Color,
     show_frame_rate: bool,
     min_size: (i32, i32),
@@ -139,17 +94,19 @@ pub struct WindowBuilder{
     start_maximized: bool,
     allow_resize: bool,
     allow_maximize: bool,
+    vsync: bool,
+    backend: Backend,
 }
 
 //#region WindowBuilder
 impl WindowBuilder {
     pub fn new() -> Self {
         Self {
-            x: CW_USEDEFAULT,
-            y: CW_USEDEFAULT,
-            width: CW_USEDEFAULT,
-            height: CW_USEDEFAULT,
-            title: win_32_string("New Window"),
+            x: None,
+            y: None,
+            width: None,
+            height: None,
+            title: String::from("New Window"),
             background_color: Color::WHITE,
             show_frame_rate: false,
             min_size: (-1, -1),
@@ -157,6 +114,8 @@ impl WindowBuilder {
             start_maximized: false,
             allow_resize: true,
             allow_maximize: true,
+            vsync: false,
+            backend: Backend::Native,
         }
     }
 
@@ -166,7 +125,7 @@ impl WindowBuilder {
     }
 
     pub fn ref_set_x(&mut self, x: i32) -> &mut Self {
-        self.x = x as c_int;
+        self.x = Some(x);
         self
     }
 
@@ -176,7 +135,7 @@ impl WindowBuilder {
     }
 
     pub fn ref_set_y(&mut self, y: i32) -> &mut Self {
-        self.y = y as c_int;
+        self.y = Some(y);
         self
     }
 
@@ -197,7 +156,7 @@ impl WindowBuilder {
     }
 
     pub fn ref_set_width(&mut self, width: i32) -> &mut Self {
-        self.width = width as c_int;
+        self.width = Some(width);
         self
     }
 
@@ -207,7 +166,7 @@ impl WindowBuilder {
     }
 
     pub fn ref_set_height(&mut self, height: i32) -> &mut Self {
-        self.height = height as c_int;
+        self.height = Some(height);
         self
     }
 
@@ -228,7 +187,7 @@ impl WindowBuilder {
     }
 
     pub fn ref_set_title(&mut self, title: &str) -> &mut Self {
-        self.title = win_32_string(title);
+        self.title = String::from(title);
         self
     }
 
@@ -388,467 +347,255 @@ impl WindowBuilder {
         self
     }
 
-    pub fn build(mut self) -> Window {
-        self.ref_build()
+    // waits for the desktop compositor before presenting each frame
+    pub fn enable_vsync(self) -> Self {
+        self.set_vsync(true)
     }
 
-    pub fn ref_build(&mut self) -> Window {
-        unsafe {
-            // hInstance gets a handle to the instance of the window class
-            let hinstance = GetModuleHandleW(null_mut());
+    pub fn ref_enable_vsync(&mut self) -> &mut Self {
+        self.ref_set_vsync(true)
+    }
 
-            let class_name = win_32_string(
-                &format!("window_{}", WINDOWCOUNT)
-            );
+    pub fn disable_vsync(self) -> Self {
+        self.set_vsync(false)
+    }
 
-            WINDOWCOUNT += 1;
-    
-            // create the window class
-            let wnd_class = WNDCLASSW {
-                style: CS_OWNDC | CS_HREDRAW | CS_VREDRAW,
-                lpfnWndProc: Some(DefWindowProcW),
-                hInstance: hinstance, // instance handle for the window
-                lpszClassName: class_name.as_ptr(),
-                cbClsExtra: 0,
-                cbWndExtra: 0,
-                hIcon: null_mut(),
-                hCursor: null_mut(),
-                hbrBackground: null_mut(),
-                lpszMenuName: null_mut(),
-            };
-    
-            let register_result = RegisterClassW(&wnd_class);
+    pub fn ref_disable_vsync(&mut self) -> &mut Self {
+        self.ref_set_vsync(false)
+    }
 
-            if register_result == 0 {
-                panic!("{}", Error::last_os_error());
-            }
+    pub fn set_vsync(mut self, vsync: bool) -> Self {
+        self.ref_set_vsync(vsync);
+        self
+    }
 
-            let mut window_style = WS_OVERLAPPEDWINDOW | WS_VISIBLE;
+    pub fn ref_set_vsync(&mut self, vsync: bool) -> &mut Self {
+        self.vsync = vsync;
+        self
+    }
 
-            if !self.allow_resize {
-                window_style &= !WS_SIZEBOX;
-            }
+    // picks where frames are presented, native by default
+    pub fn set_backend(mut self, backend: Backend) -> Self {
+        self.ref_set_backend(backend);
+        self
+    }
 
-            if !self.allow_maximize {
-                window_style &= !WS_MAXIMIZEBOX;
-            }
+    pub fn ref_set_backend(&mut self, backend: Backend) -> &mut Self {
+        self.backend = backend;
+        self
+    }
 
-            if self.start_maximized {
-                window_style |= WS_MAXIMIZEBOX; 
-            }
-    
-            // create a display window from the registered window class
-            // https://msdn.microsoft.com/en-us/library/windows/desktop/ms632680(v=vs.85).aspx
-            let handle = CreateWindowExW(
-                0,
-                class_name.as_ptr(),
-                self.title.as_ptr(),
-                window_style,
-                self.x, // x
-                self.y, // y
-                self.width, // width
-                self.height, // height
-                null_mut(), //hWindParent
-                null_mut(), // hMenu
-                hinstance,
-                null_mut() // lpParam
-            );
-    
-            if handle.is_null() {
-                panic!("{}", Error::last_os_error());
-            }
+    pub fn use_headless(self) -> Self {
+        self.set_backend(Backend::Headless)
+    }
 
-            if self.start_maximized {
-                let result = ShowWindow(handle, SW_SHOWMAXIMIZED);
-                if result == 0 {
-                    println!("{}", Error::last_os_error());
-                }
-            }
+    pub fn ref_use_headless(&mut self) -> &mut Self {
+        self.ref_set_backend(Backend::Headless)
+    }
 
-            let (client_width, client_height) = Window::get_client_size_from_handle(handle);
-            let video_memory_size = (client_width * client_height * 4) as SIZE_T;
-            let video_memory_pointer = VirtualAlloc(
-                null_mut(),
-                video_memory_size, // * 4 due to there being 4 bytes per pixel
-                MEM_RESERVE | MEM_COMMIT,
-                PAGE_READWRITE
-            );
-
-            if video_memory_pointer.is_null() {
-                panic!("{}", Error::last_os_error());
-            }
+    pub fn use_framebuffer(self, device: &str) -> Self {
+        self.set_backend(Backend::Framebuffer(String::from(device)))
+    }
 
-            // ensure the minimum size the window can be is the taskbar height
-            if self.min_size.1 < Window::get_taskbar_height_from_handle(handle) {
-                self.min_size = (self.min_size.0, Window::get_taskbar_height_from_handle(handle) + 1);
-            }
+    pub fn ref_use_framebuffer(&mut self, device: &str) -> &mut Self {
+        self.ref_set_backend(Backend::Framebuffer(String::from(device)))
+    }
 
-            Window {
-                handle,
-                device_context: GetDC(handle),
-                video_memory_pointer,
-                bitmap_info: generate_bitmap_info(client_width, client_height),
-                minimum_size: self.min_size,
-                maximum_size: self.max_size,
-                background_color: self.background_color,
-                update_state: UpdateState::new(handle),
-                show_frame_rate: self.show_frame_rate,
-                frame_count: 0,
-                frame_start_time: None,
-            }
-        }
+    pub fn use_shared_memory(self, name: &str) -> Self {
+        self.set_backend(Backend::SharedMemory(String::from(name)))
     }
-}
 
-impl std::fmt::Debug for WindowBuilder {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("WindowBuilder")
-            .field("Title", &String::from_utf16(&self.title))
-            .field("X Position", &self.x)
-            .field("Y Position", &self.y)
-            .field("Width", &self.width)
-            .field("Height", &self.height)
-            .field("Background Color", &self.background_color)
-            .field("Min Size", &self.min_size)
-            .field("Max Size", &self.max_size)
-            .field("Show FPS", &self.show_frame_rate)
-            .field("Start Maximized", &self.start_maximized)
-            .field("Allow Maximize", &self.allow_maximize)
-            .field("Allow Resize", &self.allow_resize)
-            .finish()
+    pub fn ref_use_shared_memory(&mut self, name: &str) -> &mut Self {
+        self.ref_set_backend(Backend::SharedMemory(String::from(name)))
     }
-}
-//#endregion
 
-struct UpdateState {
-    nc_tracker: TRACKMOUSEEVENT,
-    w_tracker: TRACKMOUSEEVENT,
-    sizing_direction: LRESULT,
-    cached_cursor_pos: (i32, i32),
-    cancel_draw: bool,
-}
+    pub fn use_terminal(self) -> Self {
+        self.set_backend(Backend::Terminal)
+    }
 
-impl UpdateState {
-    fn new(handle: HWND) -> Self {
-        Self {
-            nc_tracker: TRACKMOUSEEVENT {
-                cbSize: mem::size_of::<TRACKMOUSEEVENT>() as DWORD,
-                dwFlags: TME_LEAVE | TME_NONCLIENT,
-                hwndTrack: handle,
-                dwHoverTime: HOVER_DEFAULT
-            },
-            w_tracker: TRACKMOUSEEVENT {
-                cbSize: mem::size_of::<TRACKMOUSEEVENT>() as DWORD,
-                dwFlags: TME_LEAVE,
-                hwndTrack: handle,
-                dwHoverTime: HOVER_DEFAULT
-            },
-            sizing_direction: 0,
-            cached_cursor_pos: (0, 0),
-            cancel_draw: false
+    pub fn ref_use_terminal(&mut self) -> &mut Self {
+        self.ref_set_backend(Backend::Terminal)
+    }
+
+    fn get_size(&self) -> Option<(i32, i32)> {
+        match (self.width, self.height) {
+            (Some(width), Some(height)) => Some((width, height)),
+            _ => None
         }
     }
 
-    fn get_sizing_direction(&self) -> LRESULT {
-        self.sizing_direction
+    fn get_size_or_default(&self) -> (i32, i32) {
+        (
+            self.width.unwrap_or(DEFAULT_SIZE.0),
+            self.height.unwrap_or(DEFAULT_SIZE.1)
+        )
+    }
+
+    pub fn build(mut self) -> Window {
+        self.ref_build()
     }
 
-    fn set_sizing_direction(&mut self, direction: LRESULT) {
-        self.sizing_direction = direction;
+    pub fn ref_build(&mut self) -> Window {
+        let surface = self.build_surface();
+        let (client_width, client_height) = surface.get_client_size();
+        let has_focus = surface.has_focus();
+        Window {
+            surface,
+            buffers: SwapChain::new(client_width, client_height, self.background_color.into()),
+            vsync: self.vsync,
+            background_color: self.background_color,
+            show_frame_rate: self.show_frame_rate,
+            frame_count: 0,
+            frame_start_time: None,
+            input_events: Vec::new(),
+            events: Vec::new(),
+            has_focus,
+        }
     }
 
-    fn clear_sizing_direction(&mut self) {
-        self.set_sizing_direction(0);
+    fn build_surface(&self) -> Box<dyn Surface> {
+        match &self.backend {
+            Backend::Native => self.build_native_surface(),
+            Backend::Headless => Box::new(headless::HeadlessSurface::new(self.get_size_or_default())),
+            Backend::Framebuffer(device) => self.build_framebuffer_surface(device),
+            Backend::SharedMemory(name) => self.build_shared_memory_surface(name),
+            Backend::Terminal => Box::new(terminal::TerminalSurface::new(self.get_size())),
+        }
     }
 
-    fn get_cached_cursor_pos(&self) -> (i32, i32) {
-        self.cached_cursor_pos
+    #[cfg(windows)]
+    fn build_native_surface(&self) -> Box<dyn Surface> {
+        Box::new(win32::Win32Surface::new(self))
     }
 
-    fn cache_cursor_pos(&mut self, pos: (i32, i32)) {
-        self.cached_cursor_pos = pos;
+    #[cfg(unix)]
+    fn build_native_surface(&self) -> Box<dyn Surface> {
+        self.build_framebuffer_surface(framebuffer::DEFAULT_DEVICE)
     }
 
-    fn drawing_enabled(&self) -> bool {
-        !self.cancel_draw
+    #[cfg(not(any(windows, unix)))]
+    fn build_native_surface(&self) -> Box<dyn Surface> {
+        eprintln!("No native window backend for this platform, running headless");
+        Box::new(headless::HeadlessSurface::new(self.get_size_or_default()))
     }
 
-    fn enable_draw(&mut self) {
-        self.cancel_draw = false;
+    #[cfg(unix)]
+    fn build_framebuffer_surface(&self, device: &str) -> Box<dyn Surface> {
+        match framebuffer::FramebufferSurface::open(device, self) {
+            Ok(surface) => Box::new(surface),
+            Err(error) => {
+                eprintln!("Could not open framebuffer {}, running headless: {}", device, error);
+                Box::new(headless::HeadlessSurface::new(self.get_size_or_default()))
+            }
+        }
     }
 
-    fn cancel_draw(&mut self) {
-        self.cancel_draw = true;
+    #[cfg(not(unix))]
+    fn build_framebuffer_surface(&self, device: &str) -> Box<dyn Surface> {
+        eprintln!("Framebuffer {} is only supported on unix, running headless", device);
+        Box::new(headless::HeadlessSurface::new(self.get_size_or_default()))
     }
 
-    fn track_mouse(&mut self) {
-        unsafe {
-            let nc_result = TrackMouseEvent(&mut self.nc_tracker);
-            let w_result = TrackMouseEvent(&mut self.w_tracker);
-            if nc_result == 0 || w_result == 0 {
-                panic!("{}", Error::last_os_error());
+    #[cfg(unix)]
+    fn build_shared_memory_surface(&self, name: &str) -> Box<dyn Surface> {
+        match shm::SharedMemorySurface::create(name, self.get_size_or_default()) {
+            Ok(surface) => Box::new(surface),
+            Err(error) => {
+                eprintln!("Could not create shared memory frame {}, running headless: {}", name, error);
+                Box::new(headless::HeadlessSurface::new(self.get_size_or_default()))
             }
         }
     }
+
+    #[cfg(not(unix))]
+    fn build_shared_memory_surface(&self, name: &str) -> Box<dyn Surface> {
+        eprintln!("Shared memory frame {} is only supported on unix, running headless", name);
+        Box::new(headless::HeadlessSurface::new(self.get_size_or_default()))
+    }
 }
 
-/*
-To Do:
-    - Add maximum size option
+impl std::fmt::Debug for WindowBuilder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WindowBuilder")
+            .field("Title", &self.title)
+            .field("X Position", &self.x)
+            .field("Y Position", &self.y)
+            .field("Width", &self.width)
+            .field("Height", &self.height)
+            .field("Background Color", &self.background_color)
+            .field("Min Size", &self.min_size)
+            .field("Max Size", &self.max_size)
+            .field("Show FPS", &self.show_frame_rate)
+            .field("Start Maximized", &self.start_maximized)
+            .field("Allow Maximize", &self.allow_maximize)
+            .field("Allow Resize", &self.allow_resize)
+            .field("VSync", &self.vsync)
+            .field("Backend", &self.backend)
+            .finish()
+    }
+}
+//#endregion
 
-*/
 pub struct Window {
-    handle: HWND,
-    device_context: HDC,
-    video_memory_pointer: *mut VOID,
-    bitmap_info: BITMAPINFO,
-    minimum_size: (i32, i32),
-    maximum_size: (i32, i32),
+    surface: Box<dyn Surface>,
+    buffers: SwapChain,
+    vsync: bool,
     background_color: Color,
-    update_state: UpdateState,
     show_frame_rate: bool,
     frame_count: i32,
     frame_start_time: Option<Instant>,
+    // input received since the events were last taken
+    input_events: Vec<InputEvent>,
+    // resize, focus and close events since they were last taken
+    events: Vec<Event>,
+    has_focus: bool,
 }
 
 impl Window {
-    fn is_resizing(&mut self) -> bool {
-        if unsafe{ GetAsyncKeyState(VK_LBUTTON) } as u16 & 0x8000 == 0x8000 && self.update_state.get_sizing_direction() != 0 {
-            true
-        }
-        else {
-            self.update_state.clear_sizing_direction();
-            false
-        }
-    }
-
-    fn defer_window(&mut self, x: i32, y: i32, width: i32, height: i32, flags: UINT) {
-        #[cfg(feature="window_profile")]
-        let defer_timer = Instant::now();
-        unsafe {
-            let begin_defer = BeginDeferWindowPos(1);
-            let defer = DeferWindowPos(
-                begin_defer,
-                self.handle,
-                null_mut(),
-                x,
-                y,
-                width,
-                height,
-                flags
-            );
-            let result = EndDeferWindowPos(defer);
-            if result == 0 {
-                panic!("{}", Error::last_os_error());
-            }
-        }
-        #[cfg(feature="window_profile")]
-        println!("\tDefer Window Time: {}ms", defer_timer.elapsed().as_millis());
-    }
-
+    // resizes the buffers to match the client area, the next frame is drawn
+    // at the new size while the last one stays on screen until then
     fn update_bitmap(&mut self) {
         #[cfg(feature="window_profile")]
         let bitmap_timer = Instant::now();
         let (client_width, client_height) = self.get_client_size();
-        unsafe {
-            // ensure the memory from the last section of video memory is freed
-            let free_result = VirtualFree(
-                self.video_memory_pointer,
-                0,
-                MEM_RELEASE
-            );
-            if free_result == 0 {
-                panic!("{}", Error::last_os_error());
-            }
-            let video_memory_pointer = VirtualAlloc(
-                null_mut(),
-                (client_width * client_height * 4) as SIZE_T, // * 4 due to there being 4 bytes per pixel
-                MEM_RESERVE | MEM_COMMIT,
-                PAGE_READWRITE
-            );
-            if self.video_memory_pointer.is_null() {
-                panic!("{}", Error::last_os_error());
-            }
-            self.bitmap_info = generate_bitmap_info(client_width, client_height);
-            self.video_memory_pointer = video_memory_pointer;
-            self.fill(self.background_color);
-        }
+        self.buffers.resize(client_width, client_height, self.background_color.into());
         #[cfg(feature="window_profile")]
-        println!("\tUpdate Bitmap Timer: {}ms", bitmap_timer.elapsed().as_millis());
-    }
-
-    fn clamp_width(&self, width: i32) -> i32 {
-        if width > self.maximum_size.0 && self.maximum_size.0 != -1 {
-            self.maximum_size.0
-        }
-        else if width < self.minimum_size.0 && self.minimum_size.0 != -1 {
-            self.minimum_size.0
-        }
-        else {
-            width
-        }
-    }
-
-    fn clamp_height(&self, height: i32) -> i32 {
-        if height > self.maximum_size.1 && self.maximum_size.1 != -1 {
-            self.maximum_size.1
-        }
-        else if height < self.minimum_size.1 && self.minimum_size.1 != -1 {
-            self.minimum_size.1
-        }
-        else {
-            height
-        }
+        eprintln!("\tUpdate Bitmap Timer: {}ms", bitmap_timer.elapsed().as_millis());
     }
 
-    fn handle_resize(&mut self) {
-        #[cfg(feature="window_profile")]
-        let resize_timer = Instant::now();
-        let (cursor_x, cursor_y) = get_cursor_pos();
-        // ensure the cursor has moved
-        if self.update_state.get_cached_cursor_pos() != (cursor_x, cursor_y) {
-            let window_rect = self.get_window_rect();
-            let (mut dx, mut dy) = (0, 0);
-            let (dwidth, dheight) = match self.update_state.get_sizing_direction() {
-                HTTOP => (0, window_rect.top - cursor_y), // needs translate
-                HTBOTTOM => (0, cursor_y - window_rect.bottom),
-                HTLEFT => (window_rect.left - cursor_x, 0), // needs translate
-                HTRIGHT => (cursor_x - window_rect.right, 0),
-                HTTOPLEFT => (window_rect.left - cursor_x, window_rect.top - cursor_y), // needs double translate
-                HTTOPRIGHT => (cursor_x - window_rect.right, window_rect.top - cursor_y), // needs translate
-                HTBOTTOMLEFT => (window_rect.left - cursor_x, cursor_y - window_rect.bottom), // needs translate
-                HTBOTTOMRIGHT => (cursor_x - window_rect.right, cursor_y - window_rect.bottom),
-                _ => (0, 0)
-            };
-            // second round of matching to assign dx and dy
-            match self.update_state.get_sizing_direction() {
-                HTTOP => dy = dheight,
-                HTLEFT => dx = dwidth,
-                HTTOPLEFT => {
-                    dx = dwidth;
-                    dy = dheight;
-                },
-                HTTOPRIGHT => dy = dheight,
-                HTBOTTOMLEFT => dx = dwidth,
-                _ => {}
-            }
-            let (width, height) = self.get_window_size();
-            // dx and dy are used to allow resizing using the top and left borders (remove to see the behaviour this prevents)
-            self.defer_window(
-                window_rect.left - dx,
-                window_rect.top - dy,
-                self.clamp_width(width + dwidth),
-                self.clamp_height(height + dheight),
-                SWP_DRAWFRAME | SWP_NOOWNERZORDER
-            );
-            self.update_state.cache_cursor_pos((cursor_x, cursor_y));
-            self.update_bitmap();
-            // self.draw_screen();
-            self.update_state.cancel_draw();
-        }
-        #[cfg(feature="window_profile")]
-        println!("\tResize Timer: {}ms", resize_timer.elapsed().as_millis());
+    // presents the back buffer and handles any messages
+    pub fn update(&mut self) {
+        self.present(None);
     }
 
-    pub fn handle_messages(&mut self) {
-        #[cfg(feature="window_profile")]
-        let message_timer = Instant::now();
-        unsafe {
-            // only track the cursor if the window is being resized
-            if self.is_resizing() {
-                self.update_state.track_mouse();
-            }
-            let message = mem::MaybeUninit::<MSG>::uninit();
-            if PeekMessageW(message.as_ptr() as *mut MSG, self.handle, 0, 0, PM_REMOVE) != 0 {
-                let message_code = (*(message.as_ptr())).message;
-                let _l_param = (*(message.as_ptr())).lParam;
-                let w_param = (*(message.as_ptr())).wParam;
-                match message_code {
-                    // client area events
-                    WM_MOUSEMOVE => {
-                        if self.is_resizing() {
-                            self.handle_resize();
-                        }
-                    },
-                    WM_MOUSELEAVE => {
-                        if self.is_resizing() {
-                            self.handle_resize();
-                        }
-                    },
-
-                    WM_RBUTTONDOWN | WM_RBUTTONUP => {}, // handle events
-                    WM_LBUTTONDOWN | WM_LBUTTONUP => {}, // handle events
-
-                    // nc events (taskbar, resizing, syscommand etc)
-                    WM_NCLBUTTONDOWN => {
-                        match w_param as isize {
-                            HTTOPLEFT | HTTOPRIGHT |
-                            HTBOTTOMLEFT | HTBOTTOMRIGHT |
-                            HTTOP | HTRIGHT |
-                            HTBOTTOM | HTLEFT => {
-                                self.update_state.set_sizing_direction(w_param as LPARAM);
-                                self.update_state.cache_cursor_pos(get_cursor_pos());
-                            },
-                            _ => {
-                                TranslateMessage(message.as_ptr() as *const MSG);
-                                DispatchMessageW(message.as_ptr() as *const MSG);
-                            }
-                        }
-                    },
-                    WM_NCLBUTTONUP => {
-                        match w_param as isize {
-                            _ => {
-                                TranslateMessage(message.as_ptr() as *const MSG);
-                                DispatchMessageW(message.as_ptr() as *const MSG);
-                            }
-                        }
-                    },
-                    WM_NCMOUSEMOVE => {
-                        if self.is_resizing() {
-                            self.handle_resize();
-                        }
-                    },
-                    WM_NCMOUSELEAVE => {
-                        if self.is_resizing() {
-                            self.handle_resize();
-                        }
-                    },
-                    WM_SYSCOMMAND => {
-                        match w_param {
-                            SC_SIZE => { println!("SC Sizing"); },
-                            _ => {
-                                TranslateMessage(message.as_ptr() as *const MSG);
-                                DispatchMessageW(message.as_ptr() as *const MSG);
-                            }
-                        }
-                    },
-                    _ => {
-                        println!("Uncaught: {}", (*(message.as_ptr())).message);
-                        TranslateMessage(message.as_ptr() as *const MSG);
-                        DispatchMessageW(message.as_ptr() as *const MSG);
-                    }
-                }
-            }
-        }
-        #[cfg(feature="window_profile")]
-        println!("\tMessage Time: {}ms", message_timer.elapsed().as_millis());
+    // as update, but only copies the given regions of the buffer to the screen
+    pub fn update_regions(&mut self, regions: &[Rect]) {
+        self.present(Some(regions));
     }
 
-    // draws the window and handles any messages
-    pub fn update(&mut self) {
+    fn present(&mut self, regions: Option<&[Rect]>) {
         #[cfg(feature="window_profile")]
         let window_update_timer = Instant::now();
         #[cfg(feature="window_profile")]
-        println!("Window Update:\t");
-        // ensure the screen is drawn at least every other frame without interference
-        // of windows messages (used to avoid flickering)
-        if self.update_state.drawing_enabled() {
-            self.handle_messages();
+        eprintln!("Window Update:\t");
+        self.buffers.swap(regions);
+        if self.vsync {
+            self.surface.wait_for_vsync();
+        }
+        let size = self.buffers.get_size();
+        self.surface.present(self.buffers.get_front_buffer(), size, regions);
+        self.surface.poll_events(&mut self.input_events, &mut self.events);
+        // maximizing and resizing change the client area
+        if self.get_client_size() != self.get_buffer_size() {
+            self.update_bitmap();
+            let (width, height) = self.get_buffer_size();
+            self.events.push(Event::Resized { width, height });
         }
-        else {
-            self.update_state.enable_draw();
+        let has_focus = self.surface.has_focus();
+        if has_focus != self.has_focus {
+            self.has_focus = has_focus;
+            self.events.push(if has_focus { Event::FocusGained } else { Event::FocusLost });
         }
-        self.draw_screen();
         if self.show_frame_rate || self.frame_start_time.is_some() {
             if self.frame_start_time.is_none() {
                 self.frame_start_time = Some(Instant::now());
@@ -856,7 +603,7 @@ impl Window {
             const SECONDDURATION: Duration = Duration::from_secs(1);
             self.frame_count += 1;
             if self.frame_start_time.unwrap().elapsed() >= SECONDDURATION {
-                println!("Frames elapsed: {}", self.frame_count);
+                eprintln!("Frames elapsed: {}", self.frame_count);
                 self.frame_count = 0;
                 if self.show_frame_rate {
                     self.frame_start_time = Some(Instant::now());
@@ -867,50 +614,46 @@ impl Window {
             }
         }
         #[cfg(feature="window_profile")]
-        println!("\tWindow Update Time: {}ms\nEnd Window Update", window_update_timer.elapsed().as_millis());
+        eprintln!("\tWindow Update Time: {}ms\nEnd Window Update", window_update_timer.elapsed().as_millis());
     }
 
-    fn draw_screen(&mut self) {
-        #[cfg(feature="window_profile")]
-        let screen_draw_timer = Instant::now();
-        unsafe {
-            let (width, height) = self.get_client_size();
-            // used for handling maximize
-            if width != self.bitmap_info.bmiHeader.biWidth || height != -self.bitmap_info.bmiHeader.biHeight {
-                self.update_bitmap();
-                // self.fill(self.background_color);
-                self.update_state.cancel_draw();
-            }
-            if self.update_state.drawing_enabled() {
-                StretchDIBits(
-                    self.device_context,
-                    0, // x
-                    0, // y
-                    width, // width
-                    height, // height
-                    0, // memory x
-                    0, // memory y
-                    width, // memory width
-                    height, // memory height
-                    self.video_memory_pointer,
-                    &self.bitmap_info,
-                    DIB_RGB_COLORS,
-                    SRCCOPY
-                );
-            }
-        }
-        #[cfg(feature="window_profile")]
-        println!("\tScreen Draw Time: {}ms", screen_draw_timer.elapsed().as_millis());
+    // size of the pixel buffers, which can briefly differ from the client
+    // size while the window is being resized
+    pub fn get_buffer_size(&self) -> (i32, i32) {
+        self.buffers.get_size()
+    }
+
+    // the back buffer, which all drawing goes to and starts each frame
+    // as a copy of the last presented frame
+    pub fn get_buffer_mut(&mut self) -> &mut [u32] {
+        self.buffers.get_back_buffer_mut()
+    }
+
+    pub fn get_buffer(&self) -> &[u32] {
+        self.buffers.get_back_buffer()
+    }
+
+    // the frame currently on screen
+    pub fn get_previous_frame(&self) -> &[u32] {
+        self.buffers.get_front_buffer()
+    }
+
+    pub fn get_vsync(&self) -> bool {
+        self.vsync
+    }
+
+    pub fn set_vsync(&mut self, vsync: bool) {
+        self.vsync = vsync;
     }
 
     fn blip(&mut self, index: usize, color: Color) {
-        unsafe {
-            *((self.video_memory_pointer as *mut u32).add(index)) = color.into();
+        if let Some(pixel) = self.get_buffer_mut().get_mut(index) {
+            *pixel = color.into();
         }
     }
 
     pub fn draw_point(&mut self, x: i32, y: i32, color: Color) {
-        let (width, height) = self.get_client_size();
+        let (width, height) = self.get_buffer_size();
         let x_filtered = if x < 0 {
             0
         }
@@ -963,9 +706,7 @@ impl Window {
         else if dy == 0.0 { // no change in y
             let min_x = min(point_a.0, point_b.0);
             let max_x = max(point_a.0, point_b.0);
-            for x in min_x..max_x {
-                self.draw_point(x, point_a.1, color);
-            }
+            self.draw_span(point_a.1, min_x, max_x, color);
         }
         else { // no change in x
             let min_y = min(point_a.1, point_b.1);
@@ -976,20 +717,61 @@ impl Window {
         }
     }
 
-    pub fn fill(&mut self, color: Color) {
-        #[cfg(feature="window_profile")]
-        let fill_timer = Instant::now();
-        unsafe {
-            let (width, height) = self.get_client_size();
+    // fills the pixels from x_start up to (excluding) x_end on row y,
+    // coordinates are clamped to the screen in the same way as draw_point
+    fn draw_span(&mut self, y: i32, x_start: i32, x_end: i32, color: Color) {
+        if x_start >= x_end {
+            return;
+        }
+        let (width, height) = self.get_buffer_size();
+        if width <= 0 || height <= 0 {
+            return;
+        }
+        let y = y.max(0).min(height - 1);
+        let x_start = x_start.max(0).min(width - 1);
+        let x_end = x_end.max(x_start).min(width);
+        let row_start = (y * width) as usize;
+        let buffer = self.get_buffer_mut();
+        let span_end = min(row_start + x_end as usize, buffer.len());
+        let span_start = min(row_start + x_start as usize, span_end);
+        simd::fill_u32(&mut buffer[span_start..span_end], color.into());
+    }
+
+    pub fn fill_rect(&mut self, rect: Rect, color: Color) {
+        let (width, height) = self.get_buffer_size();
+        if let Some(rect) = rect.clip(width, height) {
             let color_u32: u32 = color.into();
-            let mut offset: usize = 0;
-            for _ in 0..width*height {
-                *((self.video_memory_pointer as *mut u32).add(offset)) = color_u32;
-                offset += 1;
+            let buffer = self.get_buffer_mut();
+            for y in rect.y..rect.get_bottom() {
+                let row_start = (y * width) as usize;
+                simd::fill_u32(
+                    &mut buffer[row_start + rect.x as usize..row_start + rect.get_right() as usize],
+                    color_u32
+                );
             }
         }
+    }
+
+    pub fn fill(&mut self, color: Color) {
         #[cfg(feature="window_profile")]
-        println!("\tFill Time: {}ms", fill_timer.elapsed().as_millis());
+        let fill_timer = Instant::now();
+        self.buffers.fill(color.into());
+        #[cfg(feature="window_profile")]
+        eprintln!("\tFill Time: {}ms", fill_timer.elapsed().as_millis());
+    }
+
+    // input received since this was last called, oldest first
+    pub fn take_input_events(&mut self) -> Vec<InputEvent> {
+        mem::take(&mut self.input_events)
+    }
+
+    // window events since this was last called, oldest first
+    pub fn take_events(&mut self) -> Vec<Event> {
+        mem::take(&mut self.events)
+    }
+
+    pub fn has_focus(&self) -> bool {
+        self.has_focus
     }
 
     pub fn show_frame_rate(&mut self) {
@@ -1005,7 +787,12 @@ impl Window {
     }
 
     pub fn is_running(&self) -> bool {
-        unsafe { IsWindow(self.handle) != 0 }
+        self.surface.is_running()
+    }
+
+    // closes the window, after which it stops running
+    pub fn close(&mut self) {
+        self.surface.close();
     }
 
     pub fn get_background_color(&self) -> Color {
@@ -1016,88 +803,18 @@ impl Window {
         self.background_color = color;
     }
 
-    // window area excluding the taskbar
+    // area frames are shown in, excluding any title bar
     pub fn get_client_size(&self) -> (i32, i32) {
-        Window::get_client_size_from_handle(self.handle)
+        self.surface.get_client_size()
     }
 
-    // size of the window including the taskbar
+    // size of the window including any title bar
     pub fn get_window_size(&self) -> (i32, i32) {
-        Window::get_window_size_from_handle(self.handle)
+        self.surface.get_window_size()
     }
 
-    // position relative to the top left of the primary screen
+    // position relative to the top left of the screen
     pub fn get_window_pos(&self) -> (i32, i32) {
-        let client_rect = self.get_window_rect();
-        (client_rect.left, client_rect.top)
-    }
-
-    pub fn get_window_rect(&self) -> RECT {
-        Window::get_window_rect_from_handle(self.handle)
-    }
-    
-    // relative to top left of window
-    pub fn get_relative_cursor_pos(&self) -> (i32, i32) {
-        let (gx, gy) = get_cursor_pos();
-        let (wx, wy) = self.get_window_pos();
-        (gx - wx, gy - wy)
-    }
-
-    pub fn get_taskbar_height(&self) -> i32 {
-        Window::get_taskbar_height_from_handle(self.handle)
-    }
-
-    pub fn get_taskbar_height_from_handle(wind: HWND) -> i32 {
-        let (_, window_height) = Window::get_window_size_from_handle(wind);
-        let (_, client_height) = Window::get_client_size_from_handle(wind);
-        window_height - client_height
-    }
-
-    pub fn get_window_size_from_handle(wind: HWND) -> (i32, i32) {
-        let window_rect: RECT = Window::get_window_rect_from_handle(wind);
-        (
-            window_rect.right - window_rect.left,
-            window_rect.bottom - window_rect.top
-        )
+        self.surface.get_window_pos()
     }
-
-    pub fn get_client_size_from_handle(wind: HWND) -> (i32, i32) {
-        let mut client_rect: RECT = Default::default();
-        unsafe { GetClientRect(wind, &mut client_rect) };
-        (
-            client_rect.right - client_rect.left,
-            client_rect.bottom - client_rect.top
-        )
-    }
-
-    pub fn get_window_rect_from_handle(wind: HWND) -> RECT {
-        let mut window_rect: RECT = Default::default();
-        unsafe { GetWindowRect(wind, &mut window_rect) };
-        window_rect
-    }
-}
-
-// text in windows is in wide format
-fn win_32_string(text: &str) -> Vec<u16> {
-    OsStr::new(text).encode_wide().chain(once(0)).collect()
 }
-
-// relative to top left of screen
-fn get_cursor_pos() -> (i32, i32) {
-    let mut point = POINT{ x: 0, y: 0 };
-    unsafe{ GetCursorPos(&mut point) };
-    (point.x, point.y)
-}
-
-fn generate_bitmap_info(width: i32, height: i32) -> BITMAPINFO {
-    let mut bitmap_info: BITMAPINFO = Default::default();
-    bitmap_info.bmiHeader = Default::default();
-    bitmap_info.bmiHeader.biSize = mem::size_of::<BITMAPINFOHEADER>() as DWORD;
-    bitmap_info.bmiHeader.biWidth = width;
-    bitmap_info.bmiHeader.biHeight = -height;
-    bitmap_info.bmiHeader.biPlanes = 1;
-    bitmap_info.bmiHeader.biBitCount = 32;
-    bitmap_info.bmiHeader.biCompression = BI_RGB;
-
-    bitmap_info
-}
\ No newline at end of file
@@ -0,0 +1,136 @@
+// frames are written to a file in /dev/shm so a separate viewer process can
+// show them without the simulator needing a display server
+//
+// layout, all little endian:
+//     0   magic "SIMFRAME"
+//     8   width u32
+//     12  height u32
+//     16  frame number u64
+//     24  width * height pixels, u32 0x00RRGGBB
+//
+// the frame number is written last, a viewer should read it before and after
+// copying the pixels and discard the copy if it changed
+//
+// no viewer is included, anything able to map the file and follow the
+// layout above can act as one
+
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::os::unix::fs::FileExt;
+use std::path::PathBuf;
+
+use super::Surface;
+use super::super::dirty::Rect;
+
+const MAGIC: &[u8; 8] = b"SIMFRAME";
+const HEADER_SIZE: u64 = 24;
+const FRAME_NUMBER_OFFSET: u64 = 16;
+
+pub struct SharedMemorySurface {
+    path: PathBuf,
+    file: Option<File>,
+    size: (i32, i32),
+    frame_number: u64,
+    // reused to avoid allocating for every row written
+    row_bytes: Vec<u8>,
+}
+
+impl SharedMemorySurface {
+    // creates (or truncates) /dev/shm/<name>, a name containing a slash is
+    // used as a path as is
+    pub fn create(name: &str, size: (i32, i32)) -> io::Result<Self> {
+        let path = if name.contains('/') {
+            PathBuf::from(name)
+        }
+        else {
+            PathBuf::from("/dev/shm").join(name)
+        };
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)?;
+        let mut surface = Self {
+            path,
+            file: Some(file),
+            size: (size.0.max(0), size.1.max(0)),
+            frame_number: 0,
+            row_bytes: Vec::new(),
+        };
+        surface.write_header()?;
+        Ok(surface)
+    }
+
+    fn write_header(&mut self) -> io::Result<()> {
+        let file = match &self.file {
+            Some(file) => file,
+            None => return Ok(())
+        };
+        let (width, height) = self.size;
+        file.set_len(HEADER_SIZE + (width * height) as u64 * 4)?;
+        file.write_all_at(MAGIC, 0)?;
+        file.write_all_at(&(width as u32).to_le_bytes(), 8)?;
+        file.write_all_at(&(height as u32).to_le_bytes(), 12)?;
+        file.write_all_at(&self.frame_number.to_le_bytes(), FRAME_NUMBER_OFFSET)
+    }
+
+    fn write_frame(&mut self, frame: &[u32], size: (i32, i32), regions: Option<&[Rect]>) -> io::Result<()> {
+        // the whole frame is needed after a resize as the layout has changed
+        let regions = if size != self.size {
+            self.size = size;
+            self.write_header()?;
+            None
+        }
+        else {
+            regions
+        };
+        let (width, height) = size;
+        let full = [Rect::new(0, 0, width, height)];
+        let file = match &self.file {
+            Some(file) => file,
+            None => return Ok(())
+        };
+        for region in regions.unwrap_or(&full).iter().filter_map(|region| region.clip(width, height)) {
+            for y in region.y..region.get_bottom() {
+                let start = (y * width + region.x) as usize;
+                self.row_bytes.clear();
+                for pixel in frame[start..start + region.width as usize].iter() {
+                    self.row_bytes.extend_from_slice(&pixel.to_le_bytes());
+                }
+                file.write_all_at(&self.row_bytes, HEADER_SIZE + start as u64 * 4)?;
+            }
+        }
+        self.frame_number += 1;
+        file.write_all_at(&self.frame_number.to_le_bytes(), FRAME_NUMBER_OFFSET)
+    }
+}
+
+impl Surface for SharedMemorySurface {
+    // deleting the file closes the window
+    fn is_running(&self) -> bool {
+        self.file.is_some() && self.path.exists()
+    }
+
+    fn get_client_size(&self) -> (i32, i32) {
+        self.size
+    }
+
+    fn present(&mut self, frame: &[u32], size: (i32, i32), regions: Option<&[Rect]>) {
+        if let Err(error) = self.write_frame(frame, size, regions) {
+            eprintln!("Failed to write shared memory frame: {}", error);
+        }
+    }
+
+    fn close(&mut self) {
+        if self.file.take().is_some() {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+impl Drop for SharedMemorySurface {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
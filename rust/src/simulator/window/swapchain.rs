@@ -0,0 +1,116 @@
+// a pair of pixel buffers, everything is drawn into the back buffer while
+// the front buffer holds the last presented frame
+
+use super::super::dirty::Rect;
+use super::super::simd;
+
+pub struct SwapChain {
+    width: i32,
+    height: i32,
+    buffers: [Vec<u32>; 2],
+    // index of the buffer currently being presented
+    front: usize,
+    // the back buffer is behind the front buffer, it is brought up to date
+    // when next drawn into unless the whole of it is filled first
+    stale: bool
+}
+
+impl SwapChain {
+    pub fn new(width: i32, height: i32, color: u32) -> Self {
+        let mut swap_chain = Self {
+            width: 0,
+            height: 0,
+            buffers: [Vec::new(), Vec::new()],
+            front: 0,
+            stale: false
+        };
+        swap_chain.resize(width, height, color);
+        swap_chain
+    }
+
+    pub fn get_size(&self) -> (i32, i32) {
+        (self.width, self.height)
+    }
+
+    // reallocates both buffers, filling them with the given color
+    pub fn resize(&mut self, width: i32, height: i32, color: u32) {
+        self.width = width.max(0);
+        self.height = height.max(0);
+        let length = (self.width * self.height) as usize;
+        for buffer in self.buffers.iter_mut() {
+            buffer.clear();
+            buffer.resize(length, color);
+        }
+        self.stale = false;
+    }
+
+    // the last presented frame
+    pub fn get_front_buffer(&self) -> &[u32] {
+        &self.buffers[self.front]
+    }
+
+    // the frame currently being drawn
+    pub fn get_back_buffer(&self) -> &[u32] {
+        if self.stale {
+            // holds the same pixels as the back buffer will once updated
+            return self.get_front_buffer();
+        }
+        &self.buffers[1 - self.front]
+    }
+
+    pub fn get_back_buffer_mut(&mut self) -> &mut [u32] {
+        self.update_back_buffer();
+        &mut self.buffers[1 - self.front]
+    }
+
+    // fills the whole back buffer, which skips bringing it up to date first
+    pub fn fill(&mut self, color: u32) {
+        self.stale = false;
+        simd::fill_u32(&mut self.buffers[1 - self.front], color);
+    }
+
+    fn update_back_buffer(&mut self) {
+        if self.stale {
+            self.stale = false;
+            self.copy_to_back(None);
+        }
+    }
+
+    // copies the given regions (or everything) of the front buffer into the
+    // back buffer
+    fn copy_to_back(&mut self, regions: Option<&[Rect]>) {
+        let (width, height) = (self.width, self.height);
+        let (first, second) = self.buffers.split_at_mut(1);
+        let (source, destination) = if self.front == 0 {
+            (&first[0], &mut second[0])
+        }
+        else {
+            (&second[0], &mut first[0])
+        };
+        match regions {
+            None => destination.copy_from_slice(source),
+            Some(regions) => {
+                for region in regions.iter().filter_map(|region| region.clip(width, height)) {
+                    for y in region.y..region.get_bottom() {
+                        let start = (y * width + region.x) as usize;
+                        let end = start + region.width as usize;
+                        destination[start..end].copy_from_slice(&source[start..end]);
+                    }
+                }
+            }
+        }
+    }
+
+    // makes the back buffer the front buffer so drawing always continues
+    // from the last presented frame, only the changed regions are copied
+    // into the new back buffer straight away, a whole frame is copied when
+    // first drawn into so frames that are redrawn from scratch never copy
+    pub fn swap(&mut self, regions: Option<&[Rect]>) {
+        self.update_back_buffer();
+        self.front = 1 - self.front;
+        match regions {
+            None => self.stale = true,
+            Some(regions) => self.copy_to_back(Some(regions))
+        }
+    }
+}
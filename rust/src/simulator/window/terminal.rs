@@ -0,0 +1,323 @@
+// draws frames to the terminal with ansi truecolor escapes, for watching a
+// simulation over ssh, every cell shows two pixels using the upper half
+// block with the foreground as the top pixel and the background as the bottom
+//
+// the frame is scaled down (or up) to the terminal keeping its aspect ratio,
+// only the cells that changed since the last frame are written
+//
+// the terminal is also restored if the process panics or is interrupted,
+// otherwise the shell would be left on the alternate screen with no cursor
+
+use std::fmt::Write as FmtWrite;
+use std::io::{self, Write};
+use std::sync::Once;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use super::Surface;
+use super::super::dirty::Rect;
+use super::super::scaling::{self, ScalingMode};
+
+const UPPER_HALF_BLOCK: char = '\u{2580}';
+// used when the terminal can't be asked for its size
+const DEFAULT_TERMINAL_SIZE: (i32, i32) = (80, 24);
+// querying the terminal size starts a process so is only done occasionally
+const SIZE_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+const ENTER_ALTERNATE_SCREEN: &str = "\x1b[?1049h\x1b[?25l\x1b[2J";
+const RESTORE_TERMINAL: &str = "\x1b[0m\x1b[?25h\x1b[?1049l";
+
+// whether a surface currently has the terminal on the alternate screen
+static TERMINAL_ACTIVE: AtomicBool = AtomicBool::new(false);
+static INSTALL_RESTORE_HANDLERS: Once = Once::new();
+
+#[derive(Copy, Clone, PartialEq)]
+struct Cell {
+    top: u32,
+    bottom: u32,
+}
+
+pub struct TerminalSurface {
+    running: bool,
+    // the frame size, fixed if given to the builder otherwise following the terminal
+    fixed_size: Option<(i32, i32)>,
+    // in cells
+    terminal_size: (i32, i32),
+    last_size_check: Instant,
+    // what is currently on screen, none forces every cell to be written
+    cells: Vec<Option<Cell>>,
+    // reused to build each frame's output so it can be written at once
+    output: String,
+}
+
+impl TerminalSurface {
+    pub fn new(size: Option<(i32, i32)>) -> Self {
+        let terminal_size = get_terminal_size();
+        let mut surface = Self {
+            running: true,
+            fixed_size: size.map(|(width, height)| (width.max(0), height.max(0))),
+            terminal_size,
+            last_size_check: Instant::now(),
+            cells: Vec::new(),
+            output: String::new(),
+        };
+        surface.reset_cells();
+        install_restore_handlers();
+        // alternate screen so the shell is left as it was, cursor hidden
+        surface.write_raw(ENTER_ALTERNATE_SCREEN);
+        TERMINAL_ACTIVE.store(true, Ordering::SeqCst);
+        surface
+    }
+
+    fn reset_cells(&mut self) {
+        let (columns, rows) = self.terminal_size;
+        self.cells.clear();
+        self.cells.resize((columns * rows) as usize, None);
+    }
+
+    fn write_raw(&self, text: &str) {
+        let stdout = io::stdout();
+        let mut handle = stdout.lock();
+        if let Err(error) = handle.write_all(text.as_bytes()).and_then(|_| handle.flush()) {
+            eprintln!("Failed to write to terminal: {}", error);
+        }
+    }
+
+    fn check_terminal_size(&mut self) {
+        if self.last_size_check.elapsed() < SIZE_CHECK_INTERVAL {
+            return;
+        }
+        self.last_size_check = Instant::now();
+        let terminal_size = get_terminal_size();
+        if terminal_size != self.terminal_size {
+            self.terminal_size = terminal_size;
+            self.reset_cells();
+            self.write_raw("\x1b[0m\x1b[2J");
+        }
+    }
+
+    // average of the pixels in the frame covered by a grid pixel
+    fn sample(frame: &[u32], size: (i32, i32), area: Rect, point: (i32, i32)) -> u32 {
+        if !area.contains(point.0, point.1) {
+            return 0;
+        }
+        let (width, height) = size;
+        let x_start = ((point.0 - area.x) as i64 * width as i64 / area.width as i64) as i32;
+        let y_start = ((point.1 - area.y) as i64 * height as i64 / area.height as i64) as i32;
+        let x_end = (((point.0 - area.x + 1) as i64 * width as i64 / area.width as i64) as i32)
+            .max(x_start + 1)
+            .min(width);
+        let y_end = (((point.1 - area.y + 1) as i64 * height as i64 / area.height as i64) as i32)
+            .max(y_start + 1)
+            .min(height);
+        let (mut red, mut green, mut blue, mut count) = (0u32, 0u32, 0u32, 0u32);
+        for y in y_start..y_end {
+            for pixel in frame[(y * width + x_start) as usize..(y * width + x_end) as usize].iter() {
+                red += (pixel >> 16) & 0xFF;
+                green += (pixel >> 8) & 0xFF;
+                blue += pixel & 0xFF;
+                count += 1;
+            }
+        }
+        if count == 0 {
+            return 0;
+        }
+        ((red / count) << 16) | ((green / count) << 8) | (blue / count)
+    }
+}
+
+impl Surface for TerminalSurface {
+    fn is_running(&self) -> bool {
+        self.running
+    }
+
+    // one pixel per half cell unless a size was given
+    fn get_client_size(&self) -> (i32, i32) {
+        self.fixed_size.unwrap_or((self.terminal_size.0, self.terminal_size.1 * 2))
+    }
+
+    fn present(&mut self, frame: &[u32], size: (i32, i32), _regions: Option<&[Rect]>) {
+        if !self.running {
+            return;
+        }
+        self.check_terminal_size();
+        let (columns, rows) = self.terminal_size;
+        let area = scaling::fit_rect(size, (columns, rows * 2), ScalingMode::Letterbox);
+        self.output.clear();
+        // the position the terminal will write the next cell to, and the
+        // colors it is currently using, so neither is repeated needlessly
+        let mut cursor: Option<(i32, i32)> = None;
+        let mut colors: Option<Cell> = None;
+        for row in 0..rows {
+            for column in 0..columns {
+                let cell = Cell {
+                    top: TerminalSurface::sample(frame, size, area, (column, row * 2)),
+                    bottom: TerminalSurface::sample(frame, size, area, (column, row * 2 + 1)),
+                };
+                let index = (row * columns + column) as usize;
+                if self.cells[index] == Some(cell) {
+                    continue;
+                }
+                self.cells[index] = Some(cell);
+                if cursor != Some((column, row)) {
+                    let _ = write!(self.output, "\x1b[{};{}H", row + 1, column + 1);
+                }
+                if colors.is_none_or(|colors| colors.top != cell.top) {
+                    let _ = write!(self.output, "\x1b[38;2;{};{};{}m", cell.top >> 16 & 0xFF, cell.top >> 8 & 0xFF, cell.top & 0xFF);
+                }
+                if colors.is_none_or(|colors| colors.bottom != cell.bottom) {
+                    let _ = write!(self.output, "\x1b[48;2;{};{};{}m", cell.bottom >> 16 & 0xFF, cell.bottom >> 8 & 0xFF, cell.bottom & 0xFF);
+                }
+                colors = Some(cell);
+                self.output.push(UPPER_HALF_BLOCK);
+                // the cursor doesn't wrap by itself past the last column, so the
+                // next row always starts with a move
+                cursor = Some((column + 1, row));
+            }
+        }
+        if !self.output.is_empty() {
+            self.output.push_str("\x1b[0m");
+            let output = std::mem::take(&mut self.output);
+            self.write_raw(&output);
+            self.output = output;
+        }
+    }
+
+    fn close(&mut self) {
+        if self.running {
+            self.running = false;
+            restore_terminal();
+        }
+    }
+}
+
+impl Drop for TerminalSurface {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+// leaves the alternate screen and shows the cursor, only once however many
+// times it is called, safe to call from a signal handler
+fn restore_terminal() {
+    if TERMINAL_ACTIVE.swap(false, Ordering::SeqCst) {
+        write_unbuffered(RESTORE_TERMINAL.as_bytes());
+    }
+}
+
+// a panic hook and, on unix, sigint and sigterm handlers that restore the
+// terminal before carrying on as they would have
+fn install_restore_handlers() {
+    INSTALL_RESTORE_HANDLERS.call_once(|| {
+        let previous_hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            restore_terminal();
+            previous_hook(info);
+        }));
+        #[cfg(unix)]
+        signals::install();
+    });
+}
+
+#[cfg(unix)]
+fn write_unbuffered(bytes: &[u8]) {
+    signals::write_stdout(bytes);
+}
+
+#[cfg(not(unix))]
+fn write_unbuffered(bytes: &[u8]) {
+    let stdout = io::stdout();
+    let mut handle = stdout.lock();
+    let _ = handle.write_all(bytes).and_then(|_| handle.flush());
+}
+
+// std has no signal handling, so the c library it links against is used
+#[cfg(unix)]
+mod signals {
+    use std::os::raw::{c_int, c_void};
+
+    const SIGINT: c_int = 2;
+    const SIGTERM: c_int = 15;
+    const SIG_DFL: usize = 0;
+    const STDOUT: c_int = 1;
+
+    extern "C" {
+        fn signal(signum: c_int, handler: usize) -> usize;
+        fn raise(signum: c_int) -> c_int;
+        fn write(fd: c_int, buffer: *const c_void, count: usize) -> isize;
+    }
+
+    // restores the terminal then dies to the signal as it would have
+    extern "C" fn handle(signum: c_int) {
+        super::restore_terminal();
+        unsafe {
+            signal(signum, SIG_DFL);
+            raise(signum);
+        }
+    }
+
+    pub fn install() {
+        let handler = handle as extern "C" fn(c_int) as usize;
+        for signum in [SIGINT, SIGTERM].iter() {
+            unsafe {
+                // signals the program already handles (or ignores) are left alone
+                let previous = signal(*signum, handler);
+                if previous != SIG_DFL {
+                    signal(*signum, previous);
+                }
+            }
+        }
+    }
+
+    // straight to the file descriptor, as the locks std uses aren't safe
+    // to take in a signal handler
+    pub fn write_stdout(bytes: &[u8]) {
+        let mut written = 0;
+        while written < bytes.len() {
+            let result = unsafe {
+                write(STDOUT, bytes[written..].as_ptr() as *const c_void, bytes.len() - written)
+            };
+            if result <= 0 {
+                break;
+            }
+            written += result as usize;
+        }
+    }
+}
+
+// (columns, rows), asked of stty as std has no way to query it directly
+#[cfg(unix)]
+fn get_terminal_size() -> (i32, i32) {
+    use std::fs::File;
+    use std::process::{Command, Stdio};
+    let output = File::open("/dev/tty").ok().and_then(|tty| {
+        Command::new("stty")
+            .arg("size")
+            .stdin(Stdio::from(tty))
+            .stderr(Stdio::null())
+            .output()
+            .ok()
+    });
+    let size = output.and_then(|output| {
+        let text = String::from_utf8(output.stdout).ok()?;
+        let mut values = text.split_whitespace().map(|value| value.parse::<i32>());
+        match (values.next(), values.next()) {
+            (Some(Ok(rows)), Some(Ok(columns))) if rows > 0 && columns > 0 => Some((columns, rows)),
+            _ => None
+        }
+    });
+    size.unwrap_or_else(get_terminal_size_from_environment)
+}
+
+#[cfg(not(unix))]
+fn get_terminal_size() -> (i32, i32) {
+    get_terminal_size_from_environment()
+}
+
+fn get_terminal_size_from_environment() -> (i32, i32) {
+    let read = |name: &str| std::env::var(name).ok().and_then(|value| value.parse::<i32>().ok()).filter(|value| *value > 0);
+    (
+        read("COLUMNS").unwrap_or(DEFAULT_TERMINAL_SIZE.0),
+        read("LINES").unwrap_or(DEFAULT_TERMINAL_SIZE.1)
+    )
+}
@@ -0,0 +1,718 @@
+// the win32 window backend, frames are copied to the window with StretchDIBits
+
+// https://docs.rs/winapi/*/x86_64-pc-windows-msvc/winapi/um/libloaderapi/index.html?search=winuser
+use std::ffi::OsStr;
+use std::os::windows::ffi::OsStrExt;
+use std::iter::once;
+use std::mem;
+use std::ptr::{null_mut};
+#[cfg(feature="window_profile")]
+use std::time::Instant;
+
+use winapi::um::libloaderapi::GetModuleHandleW;
+use winapi::um::winuser::{
+    DefWindowProcW,
+    RegisterClassW,
+    CreateWindowExW,
+    IsWindow,
+    GetDC,
+    GetClientRect,
+    GetWindowRect,
+    TranslateMessage,
+    DispatchMessageW,
+    PeekMessageW,
+    BeginDeferWindowPos,
+    DeferWindowPos,
+    EndDeferWindowPos,
+    TrackMouseEvent,
+    GetCursorPos,
+    GetAsyncKeyState,
+    GetForegroundWindow,
+    DestroyWindow,
+    ShowWindow,
+};
+use winapi::um::winuser::{
+    MSG,
+    WNDCLASSW,
+    CS_OWNDC,
+    CS_HREDRAW,
+    CS_VREDRAW,
+    CW_USEDEFAULT,
+    WS_OVERLAPPEDWINDOW,
+    WS_VISIBLE,
+    WS_MAXIMIZEBOX,
+    WS_SIZEBOX,
+    SW_SHOWMAXIMIZED,
+    PM_REMOVE,
+    HOVER_DEFAULT,
+    VK_LBUTTON,
+};
+// windows messages
+use winapi::um::winuser::{
+    WM_MOUSEMOVE,
+    WM_MOUSELEAVE,
+    WM_MOUSEWHEEL,
+    WM_RBUTTONDOWN,
+    WM_RBUTTONUP,
+    WM_LBUTTONDOWN,
+    WM_LBUTTONUP,
+    WM_MBUTTONDOWN,
+    WM_MBUTTONUP,
+    WM_KEYDOWN,
+    WM_KEYUP,
+    WM_SYSKEYDOWN,
+    WM_SYSKEYUP,
+    WM_NCLBUTTONDOWN,
+    WM_NCLBUTTONUP,
+    WM_NCMOUSEMOVE,
+    WM_NCMOUSELEAVE,
+    WM_SYSCOMMAND,
+    SC_SIZE,
+    SC_CLOSE,
+    SWP_DRAWFRAME,
+    SWP_NOOWNERZORDER,
+    TME_LEAVE,
+    TME_NONCLIENT,
+};
+// windows nc hit values
+use winapi::um::winuser::{
+    HTLEFT,
+    HTRIGHT,
+    HTTOP,
+    HTTOPLEFT,
+    HTTOPRIGHT,
+    HTBOTTOM,
+    HTBOTTOMLEFT,
+    HTBOTTOMRIGHT,
+    TRACKMOUSEEVENT,
+};
+// virtual key codes
+use winapi::um::winuser::{
+    GET_WHEEL_DELTA_WPARAM,
+    WHEEL_DELTA,
+    VK_UP,
+    VK_DOWN,
+    VK_LEFT,
+    VK_RIGHT,
+    VK_SPACE,
+    VK_RETURN,
+    VK_ESCAPE,
+    VK_TAB,
+    VK_BACK,
+    VK_DELETE,
+    VK_INSERT,
+    VK_HOME,
+    VK_END,
+    VK_PRIOR,
+    VK_NEXT,
+    VK_SHIFT,
+    VK_CONTROL,
+    VK_MENU,
+    VK_F1,
+};
+use winapi::shared::windowsx::{
+    GET_X_LPARAM,
+    GET_Y_LPARAM,
+};
+use winapi::um::wingdi::{
+    StretchDIBits,
+    SRCCOPY,
+    BITMAPINFO,
+    BITMAPINFOHEADER,
+    DIB_RGB_COLORS,
+    BI_RGB,
+};
+use winapi::um::dwmapi::{
+    DwmFlush,
+};
+
+use winapi::shared::minwindef::{
+    DWORD,
+    LPARAM,
+    WPARAM,
+    LRESULT,
+    UINT,
+};
+use winapi::shared::windef::{
+    HWND,
+    HDC,
+    RECT,
+    POINT,
+};
+
+use std::io::Error;
+
+use super::{WindowBuilder, Surface};
+use super::super::dirty::Rect;
+use super::super::input::{InputEvent, Key, MouseButton};
+use super::super::events::Event;
+
+static mut WINDOWCOUNT: u32 = 0;
+
+struct UpdateState {
+    nc_tracker: TRACKMOUSEEVENT,
+    w_tracker: TRACKMOUSEEVENT,
+    sizing_direction: LRESULT,
+    cached_cursor_pos: (i32, i32),
+}
+
+impl UpdateState {
+    fn new(handle: HWND) -> Self {
+        Self {
+            nc_tracker: TRACKMOUSEEVENT {
+                cbSize: mem::size_of::<TRACKMOUSEEVENT>() as DWORD,
+                dwFlags: TME_LEAVE | TME_NONCLIENT,
+                hwndTrack: handle,
+                dwHoverTime: HOVER_DEFAULT
+            },
+            w_tracker: TRACKMOUSEEVENT {
+                cbSize: mem::size_of::<TRACKMOUSEEVENT>() as DWORD,
+                dwFlags: TME_LEAVE,
+                hwndTrack: handle,
+                dwHoverTime: HOVER_DEFAULT
+            },
+            sizing_direction: 0,
+            cached_cursor_pos: (0, 0)
+        }
+    }
+
+    fn get_sizing_direction(&self) -> LRESULT {
+        self.sizing_direction
+    }
+
+    fn set_sizing_direction(&mut self, direction: LRESULT) {
+        self.sizing_direction = direction;
+    }
+
+    fn clear_sizing_direction(&mut self) {
+        self.set_sizing_direction(0);
+    }
+
+    fn get_cached_cursor_pos(&self) -> (i32, i32) {
+        self.cached_cursor_pos
+    }
+
+    fn cache_cursor_pos(&mut self, pos: (i32, i32)) {
+        self.cached_cursor_pos = pos;
+    }
+
+    fn track_mouse(&mut self) {
+        unsafe {
+            let nc_result = TrackMouseEvent(&mut self.nc_tracker);
+            let w_result = TrackMouseEvent(&mut self.w_tracker);
+            if nc_result == 0 || w_result == 0 {
+                panic!("{}", Error::last_os_error());
+            }
+        }
+    }
+}
+
+pub struct Win32Surface {
+    handle: HWND,
+    device_context: HDC,
+    bitmap_info: BITMAPINFO,
+    minimum_size: (i32, i32),
+    maximum_size: (i32, i32),
+    update_state: UpdateState,
+}
+
+impl Win32Surface {
+    pub fn new(builder: &WindowBuilder) -> Self {
+        unsafe {
+            // hInstance gets a handle to the instance of the window class
+            let hinstance = GetModuleHandleW(null_mut());
+
+            let class_name = win_32_string(
+                &format!("window_{}", WINDOWCOUNT)
+            );
+
+            WINDOWCOUNT += 1;
+    
+            // create the window class
+            let wnd_class = WNDCLASSW {
+                style: CS_OWNDC | CS_HREDRAW | CS_VREDRAW,
+                lpfnWndProc: Some(DefWindowProcW),
+                hInstance: hinstance, // instance handle for the window
+                lpszClassName: class_name.as_ptr(),
+                cbClsExtra: 0,
+                cbWndExtra: 0,
+                hIcon: null_mut(),
+                hCursor: null_mut(),
+                hbrBackground: null_mut(),
+                lpszMenuName: null_mut(),
+            };
+    
+            let register_result = RegisterClassW(&wnd_class);
+
+            if register_result == 0 {
+                panic!("{}", Error::last_os_error());
+            }
+
+            let mut window_style = WS_OVERLAPPEDWINDOW | WS_VISIBLE;
+
+            if !builder.allow_resize {
+                window_style &= !WS_SIZEBOX;
+            }
+
+            if !builder.allow_maximize {
+                window_style &= !WS_MAXIMIZEBOX;
+            }
+
+            if builder.start_maximized {
+                window_style |= WS_MAXIMIZEBOX; 
+            }
+
+            let title = win_32_string(&builder.title);
+    
+            // create a display window from the registered window class
+            // https://msdn.microsoft.com/en-us/library/windows/desktop/ms632680(v=vs.85).aspx
+            let handle = CreateWindowExW(
+                0,
+                class_name.as_ptr(),
+                title.as_ptr(),
+                window_style,
+                builder.x.unwrap_or(CW_USEDEFAULT), // x
+                builder.y.unwrap_or(CW_USEDEFAULT), // y
+                builder.width.unwrap_or(CW_USEDEFAULT), // width
+                builder.height.unwrap_or(CW_USEDEFAULT), // height
+                null_mut(), //hWindParent
+                null_mut(), // hMenu
+                hinstance,
+                null_mut() // lpParam
+            );
+    
+            if handle.is_null() {
+                panic!("{}", Error::last_os_error());
+            }
+
+            if builder.start_maximized {
+                let result = ShowWindow(handle, SW_SHOWMAXIMIZED);
+                if result == 0 {
+                    eprintln!("{}", Error::last_os_error());
+                }
+            }
+
+            let (client_width, client_height) = Win32Surface::get_client_size_from_handle(handle);
+
+            // ensure the minimum size the window can be is the taskbar height
+            let mut min_size = builder.min_size;
+            if min_size.1 < Win32Surface::get_taskbar_height_from_handle(handle) {
+                min_size = (min_size.0, Win32Surface::get_taskbar_height_from_handle(handle) + 1);
+            }
+
+            Win32Surface {
+                handle,
+                device_context: GetDC(handle),
+                bitmap_info: generate_bitmap_info(client_width, client_height),
+                minimum_size: min_size,
+                maximum_size: builder.max_size,
+                update_state: UpdateState::new(handle),
+            }
+        }
+    }
+
+    fn is_resizing(&mut self) -> bool {
+        if unsafe{ GetAsyncKeyState(VK_LBUTTON) } as u16 & 0x8000 == 0x8000 && self.update_state.get_sizing_direction() != 0 {
+            true
+        }
+        else {
+            self.update_state.clear_sizing_direction();
+            false
+        }
+    }
+
+    fn defer_window(&mut self, x: i32, y: i32, width: i32, height: i32, flags: UINT) {
+        #[cfg(feature="window_profile")]
+        let defer_timer = Instant::now();
+        unsafe {
+            let begin_defer = BeginDeferWindowPos(1);
+            let defer = DeferWindowPos(
+                begin_defer,
+                self.handle,
+                null_mut(),
+                x,
+                y,
+                width,
+                height,
+                flags
+            );
+            let result = EndDeferWindowPos(defer);
+            if result == 0 {
+                panic!("{}", Error::last_os_error());
+            }
+        }
+        #[cfg(feature="window_profile")]
+        eprintln!("\tDefer Window Time: {}ms", defer_timer.elapsed().as_millis());
+    }
+
+    fn clamp_width(&self, width: i32) -> i32 {
+        if width > self.maximum_size.0 && self.maximum_size.0 != -1 {
+            self.maximum_size.0
+        }
+        else if width < self.minimum_size.0 && self.minimum_size.0 != -1 {
+            self.minimum_size.0
+        }
+        else {
+            width
+        }
+    }
+
+    fn clamp_height(&self, height: i32) -> i32 {
+        if height > self.maximum_size.1 && self.maximum_size.1 != -1 {
+            self.maximum_size.1
+        }
+        else if height < self.minimum_size.1 && self.minimum_size.1 != -1 {
+            self.minimum_size.1
+        }
+        else {
+            height
+        }
+    }
+
+    fn handle_resize(&mut self) {
+        #[cfg(feature="window_profile")]
+        let resize_timer = Instant::now();
+        let (cursor_x, cursor_y) = get_cursor_pos();
+        // ensure the cursor has moved
+        if self.update_state.get_cached_cursor_pos() != (cursor_x, cursor_y) {
+            let window_rect = self.get_window_rect();
+            let (mut dx, mut dy) = (0, 0);
+            let (dwidth, dheight) = match self.update_state.get_sizing_direction() {
+                HTTOP => (0, window_rect.top - cursor_y), // needs translate
+                HTBOTTOM => (0, cursor_y - window_rect.bottom),
+                HTLEFT => (window_rect.left - cursor_x, 0), // needs translate
+                HTRIGHT => (cursor_x - window_rect.right, 0),
+                HTTOPLEFT => (window_rect.left - cursor_x, window_rect.top - cursor_y), // needs double translate
+                HTTOPRIGHT => (cursor_x - window_rect.right, window_rect.top - cursor_y), // needs translate
+                HTBOTTOMLEFT => (window_rect.left - cursor_x, cursor_y - window_rect.bottom), // needs translate
+                HTBOTTOMRIGHT => (cursor_x - window_rect.right, cursor_y - window_rect.bottom),
+                _ => (0, 0)
+            };
+            // second round of matching to assign dx and dy
+            match self.update_state.get_sizing_direction() {
+                HTTOP => dy = dheight,
+                HTLEFT => dx = dwidth,
+                HTTOPLEFT => {
+                    dx = dwidth;
+                    dy = dheight;
+                },
+                HTTOPRIGHT => dy = dheight,
+                HTBOTTOMLEFT => dx = dwidth,
+                _ => {}
+            }
+            let (width, height) = self.get_window_size();
+            // dx and dy are used to allow resizing using the top and left borders (remove to see the behaviour this prevents)
+            self.defer_window(
+                window_rect.left - dx,
+                window_rect.top - dy,
+                self.clamp_width(width + dwidth),
+                self.clamp_height(height + dheight),
+                SWP_DRAWFRAME | SWP_NOOWNERZORDER
+            );
+            self.update_state.cache_cursor_pos((cursor_x, cursor_y));
+        }
+        #[cfg(feature="window_profile")]
+        eprintln!("\tResize Timer: {}ms", resize_timer.elapsed().as_millis());
+    }
+
+    fn handle_messages(&mut self, input: &mut Vec<InputEvent>, events: &mut Vec<Event>) {
+        #[cfg(feature="window_profile")]
+        let message_timer = Instant::now();
+        unsafe {
+            // only track the cursor if the window is being resized
+            if self.is_resizing() {
+                self.update_state.track_mouse();
+            }
+            let message = mem::MaybeUninit::<MSG>::uninit();
+            // empty the queue so no input is left waiting for the next frame
+            while PeekMessageW(message.as_ptr() as *mut MSG, self.handle, 0, 0, PM_REMOVE) != 0 {
+                let message_code = (*(message.as_ptr())).message;
+                let l_param = (*(message.as_ptr())).lParam;
+                let w_param = (*(message.as_ptr())).wParam;
+                match message_code {
+                    // client area events
+                    WM_MOUSEMOVE => {
+                        if self.is_resizing() {
+                            self.handle_resize();
+                        }
+                        else {
+                            input.push(InputEvent::MouseMoved {
+                                x: GET_X_LPARAM(l_param),
+                                y: GET_Y_LPARAM(l_param)
+                            });
+                        }
+                    },
+                    WM_MOUSELEAVE => {
+                        if self.is_resizing() {
+                            self.handle_resize();
+                        }
+                    },
+
+                    WM_LBUTTONDOWN => input.push(InputEvent::MouseDown(MouseButton::Left)),
+                    WM_LBUTTONUP => input.push(InputEvent::MouseUp(MouseButton::Left)),
+                    WM_RBUTTONDOWN => input.push(InputEvent::MouseDown(MouseButton::Right)),
+                    WM_RBUTTONUP => input.push(InputEvent::MouseUp(MouseButton::Right)),
+                    WM_MBUTTONDOWN => input.push(InputEvent::MouseDown(MouseButton::Middle)),
+                    WM_MBUTTONUP => input.push(InputEvent::MouseUp(MouseButton::Middle)),
+                    WM_MOUSEWHEEL => {
+                        let delta = GET_WHEEL_DELTA_WPARAM(w_param) as f32 / WHEEL_DELTA as f32;
+                        input.push(InputEvent::MouseWheel(delta));
+                    },
+
+                    // keyboard events, system keys are still dispatched so alt shortcuts work
+                    WM_KEYDOWN => input.push(InputEvent::KeyDown(key_from_virtual_key(w_param))),
+                    WM_KEYUP => input.push(InputEvent::KeyUp(key_from_virtual_key(w_param))),
+                    WM_SYSKEYDOWN | WM_SYSKEYUP => {
+                        let key = key_from_virtual_key(w_param);
+                        input.push(if message_code == WM_SYSKEYDOWN {
+                            InputEvent::KeyDown(key)
+                        }
+                        else {
+                            InputEvent::KeyUp(key)
+                        });
+                        TranslateMessage(message.as_ptr() as *const MSG);
+                        DispatchMessageW(message.as_ptr() as *const MSG);
+                    },
+
+                    // nc events (taskbar, resizing, syscommand etc)
+                    WM_NCLBUTTONDOWN => {
+                        match w_param as isize {
+                            HTTOPLEFT | HTTOPRIGHT |
+                            HTBOTTOMLEFT | HTBOTTOMRIGHT |
+                            HTTOP | HTRIGHT |
+                            HTBOTTOM | HTLEFT => {
+                                self.update_state.set_sizing_direction(w_param as LPARAM);
+                                self.update_state.cache_cursor_pos(get_cursor_pos());
+                            },
+                            _ => {
+                                TranslateMessage(message.as_ptr() as *const MSG);
+                                DispatchMessageW(message.as_ptr() as *const MSG);
+                            }
+                        }
+                    },
+                    WM_NCLBUTTONUP => {
+                        match w_param as isize {
+                            _ => {
+                                TranslateMessage(message.as_ptr() as *const MSG);
+                                DispatchMessageW(message.as_ptr() as *const MSG);
+                            }
+                        }
+                    },
+                    WM_NCMOUSEMOVE => {
+                        if self.is_resizing() {
+                            self.handle_resize();
+                        }
+                    },
+                    WM_NCMOUSELEAVE => {
+                        if self.is_resizing() {
+                            self.handle_resize();
+                        }
+                    },
+                    WM_SYSCOMMAND => {
+                        match w_param {
+                            SC_SIZE => { eprintln!("SC Sizing"); },
+                            SC_CLOSE => {
+                                events.push(Event::CloseRequested);
+                                TranslateMessage(message.as_ptr() as *const MSG);
+                                DispatchMessageW(message.as_ptr() as *const MSG);
+                            },
+                            _ => {
+                                TranslateMessage(message.as_ptr() as *const MSG);
+                                DispatchMessageW(message.as_ptr() as *const MSG);
+                            }
+                        }
+                    },
+                    _ => {
+                        eprintln!("Uncaught: {}", (*(message.as_ptr())).message);
+                        TranslateMessage(message.as_ptr() as *const MSG);
+                        DispatchMessageW(message.as_ptr() as *const MSG);
+                    }
+                }
+            }
+        }
+        #[cfg(feature="window_profile")]
+        eprintln!("\tMessage Time: {}ms", message_timer.elapsed().as_millis());
+    }
+
+    pub fn get_window_rect(&self) -> RECT {
+        Win32Surface::get_window_rect_from_handle(self.handle)
+    }
+    
+    // relative to top left of window
+    pub fn get_relative_cursor_pos(&self) -> (i32, i32) {
+        let (gx, gy) = get_cursor_pos();
+        let (wx, wy) = self.get_window_pos();
+        (gx - wx, gy - wy)
+    }
+
+    pub fn get_taskbar_height(&self) -> i32 {
+        Win32Surface::get_taskbar_height_from_handle(self.handle)
+    }
+
+    pub fn get_taskbar_height_from_handle(wind: HWND) -> i32 {
+        let (_, window_height) = Win32Surface::get_window_size_from_handle(wind);
+        let (_, client_height) = Win32Surface::get_client_size_from_handle(wind);
+        window_height - client_height
+    }
+
+    pub fn get_window_size_from_handle(wind: HWND) -> (i32, i32) {
+        let window_rect: RECT = Win32Surface::get_window_rect_from_handle(wind);
+        (
+            window_rect.right - window_rect.left,
+            window_rect.bottom - window_rect.top
+        )
+    }
+
+    pub fn get_client_size_from_handle(wind: HWND) -> (i32, i32) {
+        let mut client_rect: RECT = Default::default();
+        unsafe { GetClientRect(wind, &mut client_rect) };
+        (
+            client_rect.right - client_rect.left,
+            client_rect.bottom - client_rect.top
+        )
+    }
+
+    pub fn get_window_rect_from_handle(wind: HWND) -> RECT {
+        let mut window_rect: RECT = Default::default();
+        unsafe { GetWindowRect(wind, &mut window_rect) };
+        window_rect
+    }
+}
+
+impl Surface for Win32Surface {
+    fn is_running(&self) -> bool {
+        unsafe { IsWindow(self.handle) != 0 }
+    }
+
+    // window area excluding the taskbar
+    fn get_client_size(&self) -> (i32, i32) {
+        Win32Surface::get_client_size_from_handle(self.handle)
+    }
+
+    // size of the window including the taskbar
+    fn get_window_size(&self) -> (i32, i32) {
+        Win32Surface::get_window_size_from_handle(self.handle)
+    }
+
+    // position relative to the top left of the primary screen
+    fn get_window_pos(&self) -> (i32, i32) {
+        let client_rect = self.get_window_rect();
+        (client_rect.left, client_rect.top)
+    }
+
+    fn present(&mut self, frame: &[u32], size: (i32, i32), regions: Option<&[Rect]>) {
+        #[cfg(feature="window_profile")]
+        let screen_draw_timer = Instant::now();
+        let (width, height) = size;
+        if (width, height) != (self.bitmap_info.bmiHeader.biWidth, -self.bitmap_info.bmiHeader.biHeight) {
+            self.bitmap_info = generate_bitmap_info(width, height);
+        }
+        unsafe {
+            let full_screen = [Rect::new(0, 0, width, height)];
+            let regions = regions.unwrap_or(&full_screen);
+            for region in regions.iter().filter_map(|region| region.clip(width, height)) {
+                StretchDIBits(
+                    self.device_context,
+                    region.x, // x
+                    region.y, // y
+                    region.width, // width
+                    region.height, // height
+                    region.x, // memory x
+                    // the source origin is the bottom left even for top down bitmaps
+                    height - region.get_bottom(), // memory y
+                    region.width, // memory width
+                    region.height, // memory height
+                    frame.as_ptr() as *const _,
+                    &self.bitmap_info,
+                    DIB_RGB_COLORS,
+                    SRCCOPY
+                );
+            }
+        }
+        #[cfg(feature="window_profile")]
+        eprintln!("\tScreen Draw Time: {}ms", screen_draw_timer.elapsed().as_millis());
+    }
+
+    // blocks until the compositor's next frame
+    fn wait_for_vsync(&mut self) {
+        unsafe { DwmFlush() };
+    }
+
+    fn poll_events(&mut self, input: &mut Vec<InputEvent>, events: &mut Vec<Event>) {
+        self.handle_messages(input, events);
+    }
+
+    // focus messages are sent straight to the window procedure rather
+    // than queued, so the foreground window is checked instead
+    fn has_focus(&self) -> bool {
+        unsafe { GetForegroundWindow() == self.handle }
+    }
+
+    fn close(&mut self) {
+        unsafe { DestroyWindow(self.handle) };
+    }
+}
+
+// text in windows is in wide format
+fn win_32_string(text: &str) -> Vec<u16> {
+    OsStr::new(text).encode_wide().chain(once(0)).collect()
+}
+
+fn key_from_virtual_key(code: WPARAM) -> Key {
+    let code = code as i32;
+    match code {
+        // letters and digits share their ascii codes
+        0x41..=0x5A => [
+            Key::A, Key::B, Key::C, Key::D, Key::E, Key::F, Key::G, Key::H, Key::I,
+            Key::J, Key::K, Key::L, Key::M, Key::N, Key::O, Key::P, Key::Q, Key::R,
+            Key::S, Key::T, Key::U, Key::V, Key::W, Key::X, Key::Y, Key::Z
+        ][(code - 0x41) as usize],
+        0x30..=0x39 => [
+            Key::Num0, Key::Num1, Key::Num2, Key::Num3, Key::Num4,
+            Key::Num5, Key::Num6, Key::Num7, Key::Num8, Key::Num9
+        ][(code - 0x30) as usize],
+        _ if code >= VK_F1 && code < VK_F1 + 12 => [
+            Key::F1, Key::F2, Key::F3, Key::F4, Key::F5, Key::F6,
+            Key::F7, Key::F8, Key::F9, Key::F10, Key::F11, Key::F12
+        ][(code - VK_F1) as usize],
+        VK_UP => Key::Up,
+        VK_DOWN => Key::Down,
+        VK_LEFT => Key::Left,
+        VK_RIGHT => Key::Right,
+        VK_SPACE => Key::Space,
+        VK_RETURN => Key::Enter,
+        VK_ESCAPE => Key::Escape,
+        VK_TAB => Key::Tab,
+        VK_BACK => Key::Backspace,
+        VK_DELETE => Key::Delete,
+        VK_INSERT => Key::Insert,
+        VK_HOME => Key::Home,
+        VK_END => Key::End,
+        VK_PRIOR => Key::PageUp,
+        VK_NEXT => Key::PageDown,
+        VK_SHIFT => Key::Shift,
+        VK_CONTROL => Key::Control,
+        VK_MENU => Key::Alt,
+        _ => Key::Other(code as u32)
+    }
+}
+
+// relative to top left of screen
+fn get_cursor_pos() -> (i32, i32) {
+    let mut point = POINT{ x: 0, y: 0 };
+    unsafe{ GetCursorPos(&mut point) };
+    (point.x, point.y)
+}
+
+fn generate_bitmap_info(width: i32, height: i32) -> BITMAPINFO {
+    let mut bitmap_info: BITMAPINFO = Default::default();
+    bitmap_info.bmiHeader = Default::default();
+    bitmap_info.bmiHeader.biSize = mem::size_of::<BITMAPINFOHEADER>() as DWORD;
+    bitmap_info.bmiHeader.biWidth = width;
+    bitmap_info.bmiHeader.biHeight = -height;
+    bitmap_info.bmiHeader.biPlanes = 1;
+    bitmap_info.bmiHeader.biBitCount = 32;
+    bitmap_info.bmiHeader.biCompression = BI_RGB;
+
+    bitmap_info
+}
\ No newline at end of file
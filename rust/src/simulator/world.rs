@@ -0,0 +1,507 @@
+// entity component storage for the simulator
+//
+// an entity is only a handle, everything about it lives in components, any
+// Send + Sync type can be used as one. entities with a Transform, Geometry and
+// Material are drawn by the simulator, systems added to it run every frame (or
+// every fixed step) and work on the world through component queries
+//
+// registered objects are entities too, so components, names and tags can be
+// attached to them, but they keep drawing from their own transform, vertices
+// and colors
+
+use std::any::TypeId;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use super::Color;
+use super::handle::{HandleAllocator, ObjectHandle};
+use super::objects::{AsAny, Cube, Object, Vertex};
+use super::renderer::Transform;
+use super::renderer::linearalgebra::{Quaternion, Vector3D};
+
+pub type Entity = ObjectHandle;
+
+// components are shared with the projection worker threads
+pub trait Component: Send + Sync + 'static {}
+
+impl<T: Send + Sync + 'static> Component for T {}
+
+// called with the world and the time to advance it by in seconds
+pub type System = Box<dyn FnMut(&mut World, f32)>;
+
+//#region Components
+// the vertices an entity is drawn from, connections are indices into them
+#[derive(Clone, Debug)]
+pub struct Geometry {
+    pub verticies: Vec<Vertex>
+}
+
+impl Geometry {
+    pub fn new(verticies: Vec<Vertex>) -> Self {
+        Self { verticies }
+    }
+
+    pub fn cube() -> Self {
+        Geometry::new(Cube::new().get_verticies().clone())
+    }
+
+    pub fn point() -> Self {
+        Geometry::new(vec![Vertex::new(Vector3D::ZERO)])
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Material {
+    pub frame_color: Color,
+    pub fill_color: Color
+}
+
+impl Material {
+    pub fn new(frame_color: Color, fill_color: Color) -> Self {
+        Self { frame_color, fill_color }
+    }
+
+    pub fn solid(color: Color) -> Self {
+        Material::new(color, color)
+    }
+}
+
+// units per second, angular is in degrees per second around each axis
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Velocity {
+    pub linear: Vector3D,
+    pub angular: Vector3D
+}
+
+impl Velocity {
+    pub fn new(linear: Vector3D, angular: Vector3D) -> Self {
+        Self { linear, angular }
+    }
+}
+
+// entities and objects with this aren't drawn
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Hidden;
+
+// layers are drawn in this order, each over the ones before it whatever
+// their depth, anything without a layer is in the world layer
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Layer {
+    Background,
+    #[default]
+    World,
+    Overlay
+}
+
+impl Layer {
+    pub const ALL: [Layer; 3] = [Layer::Background, Layer::World, Layer::Overlay];
+}
+
+// order within a layer, higher is drawn later so ends up on top in 2D, in 3D
+// depth decides and this only settles ties, zero without one
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ZOrder(pub i32);
+
+// moves every entity with a velocity, add it with Simulator::add_system
+pub fn apply_velocity(world: &mut World, delta: f32) {
+    world.for_each_pair_mut::<Transform, Velocity, _>(|_, transform, velocity| {
+        transform.position += velocity.linear * delta;
+        if transform.uses_quaternion() {
+            transform.rotate_by(Quaternion::from_euler(velocity.angular * (delta * std::f32::consts::PI / 180.0)));
+        }
+        else {
+            let rotation = transform.get_rotation() + velocity.angular * delta;
+            transform.set_rotation(rotation.x, rotation.y, rotation.z);
+        }
+    });
+}
+//#endregion
+
+//#region Storage
+trait Storage: AsAny + Send + Sync {
+    fn remove_entity(&mut self, entity: Entity);
+}
+
+struct ComponentStorage<T> {
+    // ordered so queries always visit entities in the same order
+    components: BTreeMap<Entity, T>
+}
+
+impl<T: Component> Storage for ComponentStorage<T> {
+    fn remove_entity(&mut self, entity: Entity) {
+        self.components.remove(&entity);
+    }
+}
+//#endregion
+
+//#region World
+pub struct World {
+    handles: HandleAllocator,
+    entities: BTreeSet<Entity>,
+    storages: HashMap<TypeId, Box<dyn Storage>>,
+    // transforms as they were before the last fixed step, for interpolation
+    cached_transforms: BTreeMap<Entity, Transform>,
+    // names don't have to be unique, both are indexed so lookups don't have
+    // to visit every entity
+    names: BTreeMap<Entity, String>,
+    named: HashMap<String, BTreeSet<Entity>>,
+    tags: BTreeMap<Entity, BTreeSet<String>>,
+    tagged: HashMap<String, BTreeSet<Entity>>
+}
+
+impl World {
+    pub fn new() -> Self {
+        Self {
+            handles: HandleAllocator::new(),
+            entities: BTreeSet::new(),
+            storages: HashMap::new(),
+            cached_transforms: BTreeMap::new(),
+            names: BTreeMap::new(),
+            named: HashMap::new(),
+            tags: BTreeMap::new(),
+            tagged: HashMap::new()
+        }
+    }
+
+    pub fn spawn(&mut self) -> Entity {
+        let entity = self.handles.allocate();
+        self.entities.insert(entity);
+        entity
+    }
+
+    // removes the entity and all of its components, registered objects
+    // despawned here are removed from the simulator next frame
+    pub fn despawn(&mut self, entity: Entity) -> bool {
+        if !self.handles.release(entity) {
+            return false;
+        }
+        self.entities.remove(&entity);
+        for storage in self.storages.values_mut() {
+            storage.remove_entity(entity);
+        }
+        self.cached_transforms.remove(&entity);
+        self.clear_name(entity);
+        for tag in self.tags.remove(&entity).unwrap_or_default() {
+            remove_from_index(&mut self.tagged, &tag, entity);
+        }
+        true
+    }
+
+    pub fn is_alive(&self, entity: Entity) -> bool {
+        self.handles.is_valid(entity)
+    }
+
+    pub fn entity_count(&self) -> usize {
+        self.entities.len()
+    }
+
+    // every live entity, registered objects included, in handle order
+    pub fn entities(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.entities.iter().copied()
+    }
+
+    fn storage<T: Component>(&self) -> Option<&BTreeMap<Entity, T>> {
+        self.storages
+            .get(&TypeId::of::<T>())
+            .and_then(|storage| storage.as_ref().as_any().downcast_ref::<ComponentStorage<T>>())
+            .map(|storage| &storage.components)
+    }
+
+    fn storage_mut<T: Component>(&mut self) -> Option<&mut BTreeMap<Entity, T>> {
+        self.storages
+            .get_mut(&TypeId::of::<T>())
+            .and_then(|storage| storage.as_mut().as_any_mut().downcast_mut::<ComponentStorage<T>>())
+            .map(|storage| &mut storage.components)
+    }
+
+    // replaces any component of the same type, ignored for despawned entities
+    pub fn insert<T: Component>(&mut self, entity: Entity, component: T) -> &mut Self {
+        if !self.is_alive(entity) {
+            return self;
+        }
+        self.storages
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(ComponentStorage::<T> { components: BTreeMap::new() }));
+        if let Some(storage) = self.storage_mut::<T>() {
+            storage.insert(entity, component);
+        }
+        self
+    }
+
+    pub fn remove<T: Component>(&mut self, entity: Entity) -> Option<T> {
+        self.storage_mut::<T>()?.remove(&entity)
+    }
+
+    pub fn get<T: Component>(&self, entity: Entity) -> Option<&T> {
+        self.storage::<T>()?.get(&entity)
+    }
+
+    pub fn get_mut<T: Component>(&mut self, entity: Entity) -> Option<&mut T> {
+        self.storage_mut::<T>()?.get_mut(&entity)
+    }
+
+    pub fn has<T: Component>(&self, entity: Entity) -> bool {
+        self.get::<T>(entity).is_some()
+    }
+
+    // every entity with the component
+    pub fn query<T: Component>(&self) -> impl Iterator<Item = (Entity, &T)> {
+        self.storage::<T>()
+            .into_iter()
+            .flat_map(|storage| storage.iter().map(|(entity, component)| (*entity, component)))
+    }
+
+    pub fn query_mut<T: Component>(&mut self) -> impl Iterator<Item = (Entity, &mut T)> {
+        self.storage_mut::<T>()
+            .into_iter()
+            .flat_map(|storage| storage.iter_mut().map(|(entity, component)| (*entity, component)))
+    }
+
+    // every entity with both components
+    pub fn query_pair<A: Component, B: Component>(&self) -> impl Iterator<Item = (Entity, &A, &B)> {
+        let second = self.storage::<B>();
+        self.query::<A>().filter_map(move |(entity, first)| {
+            second?.get(&entity).map(|second| (entity, first, second))
+        })
+    }
+
+    // calls the closure for every entity with both components, the
+    // components must be of different types
+    pub fn for_each_pair_mut<A: Component, B: Component, F: FnMut(Entity, &mut A, &mut B)>(&mut self, mut f: F) {
+        assert!(TypeId::of::<A>() != TypeId::of::<B>(), "Can't borrow a component type twice");
+        // the first storage is taken out so both can be borrowed at once
+        let mut first = match self.storages.remove(&TypeId::of::<A>()) {
+            Some(storage) => storage,
+            None => return
+        };
+        if let (Some(first), Some(second)) = (
+            first.as_mut().as_any_mut().downcast_mut::<ComponentStorage<A>>(),
+            self.storage_mut::<B>()
+        ) {
+            for (entity, a) in first.components.iter_mut() {
+                if let Some(b) = second.get_mut(entity) {
+                    f(*entity, a, b);
+                }
+            }
+        }
+        self.storages.insert(TypeId::of::<A>(), first);
+    }
+
+    // remembers every transform so drawing can interpolate from it
+    pub fn cache_transforms(&mut self) {
+        let transforms: Vec<(Entity, Transform)> = self.query::<Transform>()
+            .map(|(entity, transform)| (entity, *transform))
+            .collect();
+        self.cached_transforms.extend(transforms);
+    }
+
+    // the transform before the last fixed step, the current one if it
+    // didn't have one then
+    pub fn get_cached_transform(&self, entity: Entity) -> Option<&Transform> {
+        self.cached_transforms.get(&entity).or_else(|| self.get::<Transform>(entity))
+    }
+
+    // replaces any previous name, ignored for despawned entities
+    pub fn set_name(&mut self, entity: Entity, name: &str) -> &mut Self {
+        if !self.is_alive(entity) {
+            return self;
+        }
+        self.clear_name(entity);
+        self.names.insert(entity, String::from(name));
+        self.named.entry(String::from(name)).or_default().insert(entity);
+        self
+    }
+
+    pub fn clear_name(&mut self, entity: Entity) -> Option<String> {
+        let name = self.names.remove(&entity)?;
+        remove_from_index(&mut self.named, &name, entity);
+        Some(name)
+    }
+
+    pub fn get_name(&self, entity: Entity) -> Option<&str> {
+        self.names.get(&entity).map(|name| name.as_str())
+    }
+
+    // the entity with the lowest slot index when several share the name,
+    // as slots are reused this isn't necessarily the one named first
+    pub fn find_by_name(&self, name: &str) -> Option<Entity> {
+        self.find_all_by_name(name).next()
+    }
+
+    pub fn find_all_by_name(&self, name: &str) -> impl Iterator<Item = Entity> + '_ {
+        self.named.get(name).into_iter().flat_map(|entities| entities.iter().copied())
+    }
+
+    pub fn add_tag(&mut self, entity: Entity, tag: &str) -> &mut Self {
+        if !self.is_alive(entity) {
+            return self;
+        }
+        self.tags.entry(entity).or_default().insert(String::from(tag));
+        self.tagged.entry(String::from(tag)).or_default().insert(entity);
+        self
+    }
+
+    // false if the entity didn't have the tag
+    pub fn remove_tag(&mut self, entity: Entity, tag: &str) -> bool {
+        let removed = match self.tags.get_mut(&entity) {
+            Some(tags) => tags.remove(tag),
+            None => false
+        };
+        if removed {
+            if self.tags.get(&entity).is_some_and(|tags| tags.is_empty()) {
+                self.tags.remove(&entity);
+            }
+            remove_from_index(&mut self.tagged, tag, entity);
+        }
+        removed
+    }
+
+    pub fn has_tag(&self, entity: Entity, tag: &str) -> bool {
+        self.tags.get(&entity).is_some_and(|tags| tags.contains(tag))
+    }
+
+    pub fn get_tags(&self, entity: Entity) -> impl Iterator<Item = &str> {
+        self.tags.get(&entity).into_iter().flat_map(|tags| tags.iter().map(|tag| tag.as_str()))
+    }
+
+    pub fn find_by_tag(&self, tag: &str) -> impl Iterator<Item = Entity> + '_ {
+        self.tagged.get(tag).into_iter().flat_map(|entities| entities.iter().copied())
+    }
+}
+
+// drops the entity from the set under the key, and the set once it is empty
+fn remove_from_index(index: &mut HashMap<String, BTreeSet<Entity>>, key: &str, entity: Entity) {
+    if let Some(entities) = index.get_mut(key) {
+        entities.remove(&entity);
+        if entities.is_empty() {
+            index.remove(key);
+        }
+    }
+}
+//#endregion
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Health(i32);
+
+    #[test]
+    fn spawns_and_despawns_entities() {
+        let mut world = World::new();
+        let first = world.spawn();
+        let second = world.spawn();
+        assert_eq!(world.entity_count(), 2);
+        assert!(world.despawn(first));
+        assert!(!world.despawn(first));
+        assert!(!world.is_alive(first));
+
+        // the slot is reused but the old handle doesn't see the new entity
+        let third = world.spawn();
+        assert_eq!(third.get_index(), first.get_index());
+        world.insert(first, Health(1));
+        assert!(!world.has::<Health>(first));
+        assert!(!world.has::<Health>(third));
+        assert_eq!(world.entities().collect::<Vec<_>>(), vec![third, second]);
+    }
+
+    #[test]
+    fn stores_components_by_type() {
+        let mut world = World::new();
+        let entity = world.spawn();
+        world.insert(entity, Health(10)).insert(entity, ZOrder(3));
+        assert_eq!(world.get::<Health>(entity), Some(&Health(10)));
+        world.insert(entity, Health(5));
+        world.get_mut::<Health>(entity).unwrap().0 -= 1;
+        assert_eq!(world.get::<Health>(entity), Some(&Health(4)));
+        assert_eq!(world.remove::<Health>(entity), Some(Health(4)));
+        assert!(!world.has::<Health>(entity));
+        assert!(world.has::<ZOrder>(entity));
+
+        world.despawn(entity);
+        assert_eq!(world.query::<ZOrder>().count(), 0);
+    }
+
+    #[test]
+    fn queries_in_handle_order() {
+        let mut world = World::new();
+        let entities: Vec<Entity> = (0..4).map(|_| world.spawn()).collect();
+        for (index, entity) in entities.iter().enumerate().rev() {
+            world.insert(*entity, Health(index as i32));
+        }
+        world.insert(entities[1], ZOrder(1)).insert(entities[3], ZOrder(3));
+        let healths: Vec<i32> = world.query::<Health>().map(|(_, health)| health.0).collect();
+        assert_eq!(healths, vec![0, 1, 2, 3]);
+        let pairs: Vec<(Entity, i32, i32)> = world.query_pair::<Health, ZOrder>()
+            .map(|(entity, health, order)| (entity, health.0, order.0))
+            .collect();
+        assert_eq!(pairs, vec![(entities[1], 1, 1), (entities[3], 3, 3)]);
+
+        for (_, health) in world.query_mut::<Health>() {
+            health.0 *= 10;
+        }
+        world.for_each_pair_mut::<Health, ZOrder, _>(|_, health, order| {
+            order.0 += health.0;
+        });
+        assert_eq!(world.get::<ZOrder>(entities[3]), Some(&ZOrder(33)));
+        assert_eq!(world.get::<Health>(entities[2]), Some(&Health(20)));
+    }
+
+    #[test]
+    fn applies_velocity() {
+        let mut world = World::new();
+        let entity = world.spawn();
+        world.insert(entity, Transform::new());
+        world.insert(entity, Velocity::new(Vector3D::new(2.0, 0.0, -1.0), Vector3D::ZERO));
+        apply_velocity(&mut world, 0.5);
+        assert_eq!(world.get::<Transform>(entity).unwrap().position, Vector3D::new(1.0, 0.0, -0.5));
+    }
+
+    #[test]
+    fn indexes_names() {
+        let mut world = World::new();
+        let first = world.spawn();
+        let second = world.spawn();
+        world.set_name(first, "player").set_name(second, "player");
+        assert_eq!(world.find_by_name("player"), Some(first));
+        assert_eq!(world.find_all_by_name("player").count(), 2);
+
+        world.set_name(first, "enemy");
+        assert_eq!(world.get_name(first), Some("enemy"));
+        assert_eq!(world.find_all_by_name("player").collect::<Vec<_>>(), vec![second]);
+        assert_eq!(world.clear_name(second), Some(String::from("player")));
+        assert_eq!(world.find_by_name("player"), None);
+
+        world.despawn(first);
+        assert_eq!(world.find_by_name("enemy"), None);
+
+        // the reused slot comes first, though it was named last
+        world.set_name(second, "crate");
+        let third = world.spawn();
+        world.set_name(third, "crate");
+        assert_eq!(third.get_index(), first.get_index());
+        assert_eq!(world.find_by_name("crate"), Some(third));
+        // despawned entities can't be named
+        world.set_name(first, "ghost");
+        assert_eq!(world.get_name(first), None);
+    }
+
+    #[test]
+    fn indexes_tags() {
+        let mut world = World::new();
+        let first = world.spawn();
+        let second = world.spawn();
+        world.add_tag(first, "solid").add_tag(first, "red").add_tag(second, "solid");
+        assert!(world.has_tag(first, "red"));
+        assert_eq!(world.get_tags(first).collect::<Vec<_>>(), vec!["red", "solid"]);
+        assert_eq!(world.find_by_tag("solid").collect::<Vec<_>>(), vec![first, second]);
+
+        assert!(world.remove_tag(first, "solid"));
+        assert!(!world.remove_tag(first, "solid"));
+        assert_eq!(world.find_by_tag("solid").collect::<Vec<_>>(), vec![second]);
+
+        world.despawn(second);
+        assert_eq!(world.find_by_tag("solid").count(), 0);
+        assert_eq!(world.get_tags(second).count(), 0);
+    }
+}